@@ -0,0 +1,196 @@
+//! Dragging and scrolling a [`FragmentShaderWidget`], on a plain Mandelbrot shader. It is
+//! colored by a palette texture, which can be swapped for any PNG image.
+//!
+//! ```text
+//! cargo run --example mandelbrot
+//! ```
+
+use fragment_shader_widget::generic::Texture;
+use fragment_shader_widget::FragmentShaderWidget;
+use glam::Vec2;
+use iced::mouse::{self, Cursor};
+use iced::widget::shader::Event;
+use iced::widget::{button, column, row, shader, text, text_input};
+use iced::{Alignment, Element, Length, Point, Rectangle, Sandbox, Settings, Size};
+
+/// Same layout as `Uniforms` in mandelbrot.wgsl. The widget pads it to the WGSL struct size.
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    center: Vec2,
+    height: f32,
+    aspect: f32,
+    max_iter: u32,
+}
+
+/// Zoom factor per wheel notch.
+const ZOOM_STEP: f32 = 0.8;
+
+/// Colors the default palette goes through, and back to the first.
+const PALETTE_STOPS: [[f32; 3]; 4] = [
+    [0.0, 0.03, 0.4],
+    [0.1, 0.5, 0.9],
+    [1.0, 1.0, 0.9],
+    [1.0, 0.6, 0.0],
+];
+const PALETTE_WIDTH: usize = 256;
+
+fn default_palette() -> Texture {
+    let pixels = (0..PALETTE_WIDTH)
+        .flat_map(|x| {
+            let position = x as f32 / PALETTE_WIDTH as f32 * PALETTE_STOPS.len() as f32;
+            let (from, to) = (
+                position as usize,
+                (position as usize + 1) % PALETTE_STOPS.len(),
+            );
+            let t = position.fract();
+            let rgb = [0, 1, 2].map(|i| {
+                PALETTE_STOPS[from][i] + (PALETTE_STOPS[to][i] - PALETTE_STOPS[from][i]) * t
+            });
+            [rgb[0], rgb[1], rgb[2], 1.0].map(|channel| (channel * 255.0).round() as u8)
+        })
+        .collect();
+    Texture::from_rgba(PALETTE_WIDTH as u32, 1, pixels).unwrap()
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Pressed(Point),
+    Moved(Point, Size),
+    Released,
+    Scrolled(Point, Size, f32),
+    PalettePathChanged(String),
+    LoadPalette,
+}
+
+/// Positions are relative to the widget, in logical pixels.
+fn on_event(event: Event, bounds: Rectangle, cursor: Cursor) -> Option<Message> {
+    let Event::Mouse(event) = event else {
+        return None;
+    };
+    match event {
+        mouse::Event::ButtonPressed(mouse::Button::Left) => {
+            cursor.position_in(bounds).map(Message::Pressed)
+        }
+        // Drags go on outside the widget.
+        mouse::Event::CursorMoved { position } => {
+            let position = Point::ORIGIN + (position - bounds.position());
+            Some(Message::Moved(position, bounds.size()))
+        }
+        mouse::Event::ButtonReleased(mouse::Button::Left) => Some(Message::Released),
+        mouse::Event::WheelScrolled { delta } => {
+            let (mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. }) =
+                delta;
+            let position = cursor.position_in(bounds)?;
+            Some(Message::Scrolled(position, bounds.size(), y.signum()))
+        }
+        _ => None,
+    }
+}
+
+struct Mandelbrot {
+    widget: FragmentShaderWidget<Uniforms, Message>,
+    /// Where the left button went down, or the drag last moved to.
+    drag: Option<Point>,
+    palette_path: String,
+    palette_error: Option<String>,
+}
+
+impl Mandelbrot {
+    /// The point of the complex plane at `position` within a widget of `size`.
+    fn plane_point(&self, position: Point, size: Size) -> Vec2 {
+        let uniforms = &self.widget.uniforms;
+        let offset = Vec2::new(
+            position.x - size.width / 2.0,
+            size.height / 2.0 - position.y,
+        );
+        uniforms.center + offset * uniforms.height / size.height
+    }
+}
+
+impl Sandbox for Mandelbrot {
+    type Message = Message;
+
+    fn new() -> Self {
+        let uniforms = Uniforms {
+            center: Vec2::new(-0.5, 0.0),
+            height: 3.0,
+            aspect: 1.0,
+            max_iter: 200,
+        };
+        let widget = FragmentShaderWidget::new(include_str!("mandelbrot.wgsl"), uniforms)
+            .on_bounds(|uniforms, size| uniforms.aspect = size.width / size.height.max(1.0))
+            .on_event(on_event)
+            .with_texture(default_palette());
+        Self {
+            widget,
+            drag: None,
+            palette_path: String::new(),
+            palette_error: None,
+        }
+    }
+
+    fn title(&self) -> String {
+        String::from("Mandelbrot")
+    }
+
+    fn update(&mut self, message: Message) {
+        match message {
+            Message::Pressed(position) => self.drag = Some(position),
+            Message::Moved(position, size) => {
+                if let Some(last) = self.drag.replace(position) {
+                    let moved = self.plane_point(position, size) - self.plane_point(last, size);
+                    self.widget.uniforms.center -= moved;
+                }
+            }
+            Message::Released => self.drag = None,
+            Message::Scrolled(position, size, notches) => {
+                // Keep the point under the cursor in place.
+                let anchor = self.plane_point(position, size);
+                let uniforms = &mut self.widget.uniforms;
+                let factor = ZOOM_STEP.powf(notches);
+                uniforms.height *= factor;
+                uniforms.center = anchor + (uniforms.center - anchor) * factor;
+            }
+            Message::PalettePathChanged(path) => self.palette_path = path,
+            Message::LoadPalette => {
+                let texture = if self.palette_path.is_empty() {
+                    Ok(default_palette())
+                } else {
+                    std::fs::read(&self.palette_path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|bytes| Texture::from_png(&bytes))
+                };
+                match texture {
+                    Ok(texture) => {
+                        self.widget.set_texture(Some(texture));
+                        self.palette_error = None;
+                    }
+                    Err(err) => self.palette_error = Some(err),
+                }
+            }
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let palette = row![
+            text_input("Palette PNG, empty for the default", &self.palette_path)
+                .on_input(Message::PalettePathChanged)
+                .on_submit(Message::LoadPalette),
+            button("Load palette").on_press(Message::LoadPalette),
+            text(self.palette_error.as_deref().unwrap_or_default()),
+        ]
+        .spacing(8)
+        .padding(8)
+        .align_items(Alignment::Center);
+
+        let view = shader(&self.widget)
+            .width(Length::Fill)
+            .height(Length::Fill);
+        column![palette, view].into()
+    }
+}
+
+fn main() -> iced::Result {
+    Mandelbrot::run(Settings::default())
+}
@@ -0,0 +1,41 @@
+// A plain Mandelbrot set, without the viewer's deep zoom and coloring options, colored by a
+// palette texture.
+
+struct Uniforms {
+	center: vec2f,
+	// Height of the view on the complex plane.
+	height: f32,
+	// Widget width over height.
+	aspect: f32,
+	max_iter: u32,
+}
+
+// `uniforms` itself is declared by FragmentShaderWidget.
+
+// Iterations per repetition of the palette.
+const PALETTE_PERIOD = 64.;
+
+// Read along its middle row, from left to right, repeating.
+@group(1) @binding(0) var palette: texture_2d<f32>;
+@group(1) @binding(1) var palette_sampler: sampler;
+
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+	// The imaginary axis points up, uv.y down.
+	let c = uniforms.center + (uv - .5) * vec2f(uniforms.aspect, -1.) * uniforms.height;
+
+	var z = vec2f(0.);
+	var i = 0u;
+	for (; i < uniforms.max_iter; i++) {
+		z = vec2f(z.x * z.x - z.y * z.y, 2. * z.x * z.y) + c;
+		if (dot(z, z) > 4.) {
+			break;
+		}
+	}
+
+	if (i == uniforms.max_iter) {
+		return vec4f(0., 0., 0., 1.);
+	}
+	// At level 0, as implicit derivatives need uniform control flow.
+	return textureSampleLevel(palette, palette_sampler, vec2f(f32(i) / PALETTE_PERIOD, .5), 0.);
+}
@@ -0,0 +1,72 @@
+//! An animated plasma, about the smallest use of [`FragmentShaderWidget`].
+//!
+//! ```text
+//! cargo run --example plasma
+//! ```
+
+use fragment_shader_widget::FragmentShaderWidget;
+use iced::time::{self, Duration, Instant};
+use iced::widget::shader;
+use iced::{executor, Application, Command, Element, Length, Settings, Subscription, Theme};
+
+/// Same layout as `Uniforms` in plasma.wgsl.
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    time: f32,
+    aspect: f32,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Tick(Instant),
+}
+
+struct Plasma {
+    start: Instant,
+    widget: FragmentShaderWidget<Uniforms, Message>,
+}
+
+impl Application for Plasma {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    type Flags = ();
+
+    fn new(_flags: ()) -> (Self, Command<Message>) {
+        let uniforms = Uniforms {
+            time: 0.0,
+            aspect: 1.0,
+        };
+        let widget = FragmentShaderWidget::new(include_str!("plasma.wgsl"), uniforms)
+            .on_bounds(|uniforms, size| uniforms.aspect = size.width / size.height.max(1.0));
+        let start = Instant::now();
+        (Self { start, widget }, Command::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Plasma")
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::Tick(now) => self.widget.uniforms.time = (now - self.start).as_secs_f32(),
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        shader(&self.widget)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        time::every(Duration::from_millis(16)).map(Message::Tick)
+    }
+}
+
+fn main() -> iced::Result {
+    Plasma::run(Settings::default())
+}
@@ -0,0 +1,27 @@
+// A plasma: a few sine waves over the widget, drifting with time.
+
+struct Uniforms {
+	// Seconds since the start.
+	time: f32,
+	// Widget width over height.
+	aspect: f32,
+}
+
+// `uniforms` itself is declared by FragmentShaderWidget.
+
+const PI = 3.14159265;
+
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+	let p = (uv - .5) * vec2f(uniforms.aspect, 1.) * 8.;
+	let t = uniforms.time;
+
+	var v = sin(p.x + t);
+	v += sin((p.y + t) * .5);
+	v += sin((p.x + p.y + t) * .5);
+	let c = p + 4. * vec2f(sin(t / 3.), cos(t / 2.));
+	v += sin(sqrt(dot(c, c) + 1.) + t);
+
+	let phase = v * .5 * PI + vec3f(0., 2., 4.) * PI / 3.;
+	return vec4f(sin(phase) * .5 + .5, 1.);
+}
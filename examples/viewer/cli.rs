@@ -0,0 +1,251 @@
+//! Command line arguments of the viewer.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use fragment_shader_widget::{
+    ColoringMode, Controls, Formula, ITERS_MAX, ITERS_MIN, MULTIBROT_POWER_DEFAULT,
+    MULTIBROT_POWER_MAX, MULTIBROT_POWER_MIN,
+};
+use glam::DVec2;
+use iced::Size;
+
+use crate::parse::{parse_finite, parse_in_range, parse_zoom};
+
+/// Fractal explorer drawn by a custom iced shader widget.
+///
+/// The view is restored from the state saved on exit, any arguments given override it.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// Real part of the view center.
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_finite)]
+    pub center_x: Option<f64>,
+    /// Imaginary part of the view center.
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_finite)]
+    pub center_y: Option<f64>,
+    /// View center as `x,y`, e.g. `--center=-0.7436,0.1318`.
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_center, conflicts_with_all = ["center_x", "center_y"])]
+    pub center: Option<DVec2>,
+    /// Zoom level, doubling the magnification with each step.
+    #[arg(long, value_parser = parse_zoom)]
+    pub zoom: Option<f32>,
+    /// Iteration count, disables automatic iterations.
+    #[arg(long, visible_alias = "iters", value_parser = clap::value_parser!(u32).range(ITERS_MIN as i64..=ITERS_MAX as i64))]
+    pub max_iter: Option<u32>,
+    /// Fractal formula: mandelbrot, burning-ship, tricorn or multibrot.
+    #[arg(long, value_parser = parse_formula)]
+    pub formula: Option<Formula>,
+    /// Exponent of the multibrot formula.
+    #[arg(long, value_parser = parse_power)]
+    pub power: Option<f32>,
+    /// How escaped points are shaded: iterations, distance, distance-lines, orbit-trap-point,
+    /// orbit-trap-line or orbit-trap-circle.
+    #[arg(long, value_parser = parse_coloring)]
+    pub coloring: Option<ColoringMode>,
+    /// Start from the default view and settings instead of the saved ones.
+    #[arg(long)]
+    pub fresh: bool,
+    /// Render the view to this PNG file at the window size and exit, without opening a window.
+    /// Starts from the default view rather than the saved one, so the image only depends on
+    /// the arguments.
+    #[arg(long, value_name = "PNG")]
+    pub render: Option<PathBuf>,
+    /// Time rendering a fixed set of views and exit, without opening a window.
+    #[arg(long, conflicts_with = "render")]
+    pub benchmark: bool,
+    /// Iteration shader to use instead of the built-in one, reloaded whenever the file changes.
+    #[arg(long, value_name = "WGSL")]
+    pub watch_shader: Option<PathBuf>,
+    /// Window width in logical pixels.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+    /// Window height in logical pixels.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+    /// Window size as `WIDTHxHEIGHT`, e.g. `--size=1280x720`.
+    #[arg(long, value_parser = parse_size, conflicts_with_all = ["width", "height"])]
+    pub size: Option<Size<u32>>,
+}
+
+fn parse_center(arg: &str) -> Result<DVec2, String> {
+    let (x, y) = arg
+        .split_once(',')
+        .ok_or_else(|| String::from("must be two numbers separated by a comma"))?;
+    Ok(DVec2::new(parse_finite(x.trim())?, parse_finite(y.trim())?))
+}
+
+pub fn parse_size(arg: &str) -> Result<Size<u32>, String> {
+    let dimension = |value: &str| match value.trim().parse::<u32>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(String::from("must be two positive integers like 1280x720")),
+    };
+    let (width, height) = arg
+        .split_once('x')
+        .ok_or_else(|| String::from("must be two positive integers like 1280x720"))?;
+    Ok(Size::new(dimension(width)?, dimension(height)?))
+}
+
+fn parse_formula(arg: &str) -> Result<Formula, String> {
+    Formula::all(MULTIBROT_POWER_DEFAULT)
+        .into_iter()
+        .find(|formula| formula_arg_name(formula) == arg)
+        .ok_or_else(|| String::from("must be mandelbrot, burning-ship, tricorn or multibrot"))
+}
+
+/// Name of a formula in the `--formula` argument.
+fn formula_arg_name(formula: &Formula) -> &'static str {
+    match formula {
+        Formula::Mandelbrot => "mandelbrot",
+        Formula::BurningShip => "burning-ship",
+        Formula::Tricorn => "tricorn",
+        Formula::Multibrot { .. } => "multibrot",
+    }
+}
+
+fn parse_coloring(arg: &str) -> Result<ColoringMode, String> {
+    ColoringMode::ALL
+        .into_iter()
+        .find(|coloring| coloring_arg_name(coloring) == arg)
+        .ok_or_else(|| {
+            String::from(
+                "must be iterations, distance, distance-lines, orbit-trap-point, orbit-trap-line \
+                 or orbit-trap-circle",
+            )
+        })
+}
+
+/// Name of a coloring mode in the `--coloring` argument.
+fn coloring_arg_name(coloring: &ColoringMode) -> &'static str {
+    match coloring {
+        ColoringMode::Iterations => "iterations",
+        ColoringMode::Distance => "distance",
+        ColoringMode::DistanceLines => "distance-lines",
+        ColoringMode::OrbitTrapPoint => "orbit-trap-point",
+        ColoringMode::OrbitTrapLine => "orbit-trap-line",
+        ColoringMode::OrbitTrapCircle => "orbit-trap-circle",
+    }
+}
+
+fn parse_power(arg: &str) -> Result<f32, String> {
+    parse_in_range(arg, MULTIBROT_POWER_MIN..=MULTIBROT_POWER_MAX)
+}
+
+impl Args {
+    /// Window size from `--size`, `--width` and `--height`, `default` for what's not given.
+    pub fn window_size(&self, default: Size) -> Size {
+        let width = self.size.map(|size| size.width).or(self.width);
+        let height = self.size.map(|size| size.height).or(self.height);
+        Size::new(
+            width.map_or(default.width, |width| width as f32),
+            height.map_or(default.height, |height| height as f32),
+        )
+    }
+
+    pub fn apply(&self, controls: &mut Controls) {
+        if let Some(center) = self.center {
+            controls.center = center;
+        }
+        if let Some(x) = self.center_x {
+            controls.center.x = x;
+        }
+        if let Some(y) = self.center_y {
+            controls.center.y = y;
+        }
+        if let Some(zoom) = self.zoom {
+            controls.zoom = zoom;
+        }
+        if let Some(max_iter) = self.max_iter {
+            controls.max_iter = max_iter;
+            controls.auto_iter = false;
+        }
+        if let Some(formula) = self.formula {
+            controls.formula = formula;
+        }
+        if let (Some(power), Formula::Multibrot { .. }) = (self.power, controls.formula) {
+            controls.formula = Formula::Multibrot { power };
+        }
+        if let Some(coloring) = self.coloring {
+            controls.coloring = coloring;
+        }
+    }
+}
+
+/// Command line arguments reproducing the view, see [`Args`].
+pub fn view_args(controls: &Controls) -> String {
+    let mut args = format!(
+        "--center-x={} --center-y={} --zoom={}",
+        controls.center.x, controls.center.y, controls.zoom
+    );
+    if !controls.auto_iter {
+        args += &format!(" --max-iter={}", controls.max_iter);
+    }
+    args += &format!(" --formula={}", formula_arg_name(&controls.formula));
+    if let Formula::Multibrot { power } = controls.formula {
+        args += &format!(" --power={power}");
+    }
+    args + &format!(" --coloring={}", coloring_arg_name(&controls.coloring))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fragment_shader_widget::CENTER_DEFAULT;
+
+    #[test]
+    fn view_args_round_trip() {
+        let controls = Controls {
+            center: DVec2::new(-0.743643887037151, 0.131825904205330),
+            zoom: 9.42,
+            max_iter: 500,
+            formula: Formula::Multibrot { power: 4.5 },
+            coloring: ColoringMode::OrbitTrapLine,
+            ..Controls::default()
+        };
+
+        let view_args = view_args(&controls);
+        let args =
+            Args::try_parse_from(std::iter::once("test").chain(view_args.split_whitespace()))
+                .unwrap();
+        let mut parsed = Controls::default();
+        args.apply(&mut parsed);
+
+        assert_eq!(parsed.view_string(), controls.view_string());
+    }
+
+    #[test]
+    fn args_override_controls() {
+        let args =
+            Args::try_parse_from(["test", "--center-x", "-0.75", "--max-iter", "500"]).unwrap();
+        let mut controls = Controls {
+            auto_iter: true,
+            ..Controls::default()
+        };
+        args.apply(&mut controls);
+
+        assert_eq!(controls.center, DVec2::new(-0.75, CENTER_DEFAULT.y));
+        assert_eq!(controls.max_iter, 500);
+        assert!(!controls.auto_iter);
+
+        let args = Args::try_parse_from([
+            "test",
+            "--center=-0.7436,0.1318",
+            "--iters=1500",
+            "--size=1280x720",
+        ])
+        .unwrap();
+        args.apply(&mut controls);
+        assert_eq!(controls.center, DVec2::new(-0.7436, 0.1318));
+        assert_eq!(controls.max_iter, 1500);
+        assert_eq!(
+            args.window_size(Size::new(1.0, 1.0)),
+            Size::new(1280.0, 720.0)
+        );
+
+        assert!(Args::try_parse_from(["test", "--zoom", "100"]).is_err());
+        assert!(Args::try_parse_from(["test", "--center=1"]).is_err());
+        assert!(Args::try_parse_from(["test", "--center=1,2", "--center-x=3"]).is_err());
+        assert!(Args::try_parse_from(["test", "--size=0x720"]).is_err());
+        assert!(Args::try_parse_from(["test", "--center-y", "NaN"]).is_err());
+        assert!(Args::try_parse_from(["test", "--width", "wide"]).is_err());
+    }
+}
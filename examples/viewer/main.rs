@@ -0,0 +1,3570 @@
+//! The Mandelbrot viewer: [`FragmentShaderProgram`] with controls for every setting, bookmarks,
+//! image and zoom sequence export, and the command line arguments in `cli.rs`.
+//!
+//! ```text
+//! cargo run --example viewer -- --help
+//! ```
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use clap::Parser;
+use directories::ProjectDirs;
+use fragment_shader_widget::{
+    apply_zoom, render_errors, wheel_zoom, AspectRatio, ColoringMode, Config, Controls, Formula,
+    FragmentShaderProgram, HeadlessRenderer, InteriorMode, Message as ViewerMessage, Palette,
+    RenderMode, AA_SAMPLES, CENTER_DEFAULT, COLOR_CYCLE_SPEED_MAX, COLOR_FREQUENCY_MAX,
+    COLOR_FREQUENCY_MIN, ESCAPE_RADIUS_MAX, ESCAPE_RADIUS_MIN, EXPOSURE_MAX, EXPOSURE_MIN,
+    GAMMA_MAX, GAMMA_MIN, JULIA_CENTER, JULIA_SEED_DEFAULT, LINE_WIDTH_MAX, LINE_WIDTH_MIN,
+    MULTIBROT_POWER_DEFAULT, MULTIBROT_POWER_MAX, MULTIBROT_POWER_MIN, STRIPE_FREQUENCY_MAX,
+    STRIPE_FREQUENCY_MIN, TILES, TRAP_RADIUS_MAX, TRAP_RADIUS_MIN, ZOOM_DEFAULT, ZOOM_MAX,
+    ZOOM_MIN,
+};
+use glam::{DVec2, Vec2};
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Renderer as _};
+use iced::advanced::text::Renderer as _;
+use iced::advanced::widget::{Tree, Widget};
+use iced::advanced::{Clipboard, Shell};
+use iced::event::{self, Status};
+use iced::keyboard;
+use iced::mouse;
+use iced::mouse::Cursor;
+use iced::time::{self, Duration, Instant};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, progress_bar, row, scrollable, shader, slider,
+    text, text_input, TextInput,
+};
+use iced::{
+    advanced, alignment, clipboard, executor, system, theme, window, Alignment, Application, Color,
+    Command, Element, Length, Pixels, Rectangle, Settings, Size, Subscription, Theme, Vector,
+};
+use serde::{Deserialize, Serialize};
+
+use cli::{parse_size, view_args, Args};
+
+mod cli;
+#[path = "../../src/parse.rs"]
+mod parse;
+
+const AUTO_ZOOM_SPEED_MIN: f32 = 0.1;
+const AUTO_ZOOM_SPEED_DEFAULT: f32 = 0.5;
+const AUTO_ZOOM_SPEED_MAX: f32 = 3.0;
+const AUTO_ZOOM_CENTERING: f64 = 0.02;
+
+const EXPORT_ZOOM_LEVELS_MIN: f32 = 1.0;
+const EXPORT_ZOOM_LEVELS_DEFAULT: f32 = 10.0;
+const EXPORT_ZOOM_LEVELS_MAX: f32 = 40.0;
+const EXPORT_FRAMES_DEFAULT: u32 = 300;
+const EXPORT_SIZE_DEFAULT: &str = "1280x720";
+const EXPORT_DIRECTORY_DEFAULT: &str = "zoom";
+
+const TICK_INTERVAL: Duration = Duration::from_millis(16);
+/// Number of colors in the palette editor's preview strip.
+const PALETTE_PREVIEW_STEPS: usize = 48;
+/// How often the debug overlay is refreshed while nothing else redraws the window.
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long after the last panning or zooming step full quality rendering resumes.
+const INTERACTION_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// In fullscreen, the controls are hidden once the cursor left them for this long.
+const CONTROLS_HIDE_DELAY: Duration = Duration::from_secs(2);
+/// In fullscreen, moving the cursor within this many pixels of the bottom edge shows the
+/// controls.
+const CONTROLS_REVEAL_EDGE: f32 = 8.0;
+
+/// Text size of the coordinate grid labels.
+const GRID_LABEL_SIZE: f32 = 12.0;
+
+/// Fraction of the remaining way the view moves each tick when gliding to a double-clicked point.
+const RECENTER_SMOOTHING: f64 = 0.2;
+/// Gliding snaps to the target when closer than this, as a fraction of the view height.
+const RECENTER_PRECISION: f64 = 0.0005;
+
+/// Fraction of the remaining way to the target zoom covered each tick after a wheel step, getting
+/// there within about 150 ms.
+const ZOOM_SMOOTHING: f32 = 0.27;
+/// Smooth zooming snaps to the target when closer than this, in zoom levels.
+const ZOOM_SMOOTHING_PRECISION: f32 = 0.001;
+
+/// Fraction of the remaining rotation undone each tick after "Reset rotation".
+const ROTATION_RESET_SMOOTHING: f32 = 0.2;
+/// Resetting snaps to no rotation when closer than this, in radians.
+const ROTATION_RESET_PRECISION: f32 = 0.001;
+
+/// Decay rate of the gliding velocity after a pan is released, per second. Higher values stop
+/// sooner.
+const MOMENTUM_FRICTION: f32 = 4.0;
+/// Gliding stops below this speed, in pixels per second.
+const MOMENTUM_MIN_SPEED: f32 = 20.0;
+
+/// Number of jittered samples averaged in progressive mode.
+const ACCUMULATION_FRAMES: u32 = 64;
+
+/// Well-known places of the Mandelbrot set, loaded with the number keys 1 to 4: name, center,
+/// zoom and iteration count.
+const PRESETS: [(&str, DVec2, f32, u32); 4] = [
+    ("Seahorse Valley", DVec2::new(-0.7453, 0.1127), 8.0, 500),
+    ("Elephant Valley", DVec2::new(0.2855, 0.0115), 8.0, 800),
+    ("Triple Spiral", DVec2::new(-0.088, 0.654), 9.0, 1000),
+    ("Mini-Mandelbrot", DVec2::new(-1.7548776, 0.0), 6.5, 500),
+];
+
+/// `controls` moved to the preset at `index` of [`PRESETS`], keeping the coloring.
+fn preset(controls: Controls, index: usize) -> Option<Controls> {
+    let (_, center, zoom, max_iter) = *PRESETS.get(index)?;
+    Some(Controls {
+        center,
+        zoom,
+        max_iter,
+        auto_iter: false,
+        formula: Formula::Mandelbrot,
+        julia: None,
+        rotation: 0.0,
+        zoom_anchor: None,
+        ..controls
+    })
+}
+
+/// Loads a preset for the number keys 1 to 4, unless Ctrl, Alt or Logo make them another
+/// shortcut.
+fn preset_key(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    if modifiers.control() || modifiers.alt() || modifiers.logo() {
+        return None;
+    }
+    match key.as_ref() {
+        keyboard::Key::Character(digit @ ("1" | "2" | "3" | "4")) => {
+            Some(Message::LoadPreset(digit.parse::<usize>().ok()? - 1))
+        }
+        _ => None,
+    }
+}
+
+/// Location of `file` in the platform config directory.
+fn config_path(file: &str) -> Option<PathBuf> {
+    ProjectDirs::from("", "", "fragment-shader-widget").map(|dirs| dirs.config_dir().join(file))
+}
+
+/// Location of the state file saved on exit.
+fn state_path() -> Option<PathBuf> {
+    config_path("state.json")
+}
+
+/// Current version of the bookmarks file format.
+const BOOKMARKS_VERSION: u32 = 1;
+
+/// A named view to come back to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bookmark {
+    name: String,
+    controls: Controls,
+    /// `None` for bookmarks made before palettes could be edited.
+    #[serde(default)]
+    palette: Option<Palette>,
+}
+
+/// Contents of the bookmarks file. Bookmarks from older versions load with defaults for the
+/// controls they lack, see [`Controls`].
+#[derive(Debug, Serialize, Deserialize)]
+struct BookmarksFile {
+    version: u32,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmark {
+    /// Loads the saved bookmarks, empty if there are none or the file is malformed.
+    fn load_all() -> Vec<Self> {
+        let Some(path) = config_path("bookmarks.json") else {
+            return Vec::new();
+        };
+        let Ok(json) = std::fs::read_to_string(&path) else {
+            return Vec::new();
+        };
+
+        match serde_json::from_str::<BookmarksFile>(&json) {
+            Ok(file) if file.version <= BOOKMARKS_VERSION => file.bookmarks,
+            Ok(file) => {
+                eprintln!(
+                    "Ignoring bookmarks file {} of unsupported version {}",
+                    path.display(),
+                    file.version
+                );
+                Vec::new()
+            }
+            Err(err) => {
+                eprintln!(
+                    "Ignoring malformed bookmarks file {}: {err}",
+                    path.display()
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn save_all(bookmarks: &[Bookmark]) -> std::io::Result<()> {
+        let path = config_path("bookmarks.json").ok_or(std::io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = BookmarksFile {
+            version: BOOKMARKS_VERSION,
+            bookmarks: bookmarks.to_vec(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&file)?)
+    }
+}
+
+/// Current version of the state file format.
+const SESSION_VERSION: u32 = 1;
+
+/// View and UI options saved on exit and restored on the next run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Session {
+    /// Files without a version predate it and are not loaded.
+    #[serde(default)]
+    version: u32,
+    controls: Controls,
+    auto_zoom_speed: f32,
+    animate_colors: bool,
+    pan_momentum: bool,
+    smooth_zoom: bool,
+    #[serde(default)]
+    slider_anchor: bool,
+    progressive: bool,
+    show_minimap: bool,
+    show_grid: bool,
+    show_orbit: bool,
+    #[serde(default)]
+    probe: bool,
+    show_fps: bool,
+    /// Dark by default, fractals are usually viewed against a dark UI.
+    #[serde(default)]
+    light_theme: bool,
+    #[serde(default)]
+    render_in_background: bool,
+    palette: Palette,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            version: SESSION_VERSION,
+            controls: Controls::default(),
+            auto_zoom_speed: AUTO_ZOOM_SPEED_DEFAULT,
+            animate_colors: false,
+            pan_momentum: true,
+            smooth_zoom: true,
+            slider_anchor: false,
+            progressive: false,
+            show_minimap: true,
+            show_grid: false,
+            show_orbit: false,
+            probe: false,
+            show_fps: false,
+            light_theme: false,
+            render_in_background: false,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Session {
+    /// Parses a saved session, `None` if it's malformed or of another version.
+    fn parse(json: &str) -> Option<Self> {
+        serde_json::from_str(json)
+            .ok()
+            .filter(|session: &Session| session.version == SESSION_VERSION)
+    }
+
+    /// Loads the session saved by [`Session::save`], `None` if there's no usable one.
+    fn load() -> Option<Self> {
+        Self::parse(&std::fs::read_to_string(state_path()?).ok()?)
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let path = state_path().ok_or(std::io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deletes the saved session, if any.
+    fn clear() -> std::io::Result<()> {
+        let path = state_path().ok_or(std::io::ErrorKind::NotFound)?;
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    SetRenderMode(RenderMode),
+    UpdateExposure(f32),
+    SetFormula(Formula),
+    UpdateMaxIterations(u32),
+    ToggleAutoIterations(bool),
+    UpdateMultibrotPower(f32),
+    UpdateEscapeRadius(f32),
+    ToggleSmooth(bool),
+    ToggleStripe(bool),
+    UpdateStripeFrequency(f32),
+    ToggleHistogram(bool),
+    SetColoringMode(ColoringMode),
+    SetInteriorMode(InteriorMode),
+    UpdateTrapRadius(f32),
+    UpdateLineWidth(f32),
+    UpdateGamma(f32),
+    UpdateColorOffset(f32),
+    UpdateColorFrequency(f32),
+    UpdateColorCycleSpeed(f32),
+    SetAntialiasing(u32),
+    /// Split the iteration into this many tiles per axis.
+    SetTiles(u32),
+    SetAspectRatio(AspectRatio),
+    UpdateZoom(f32),
+    /// Rotation in degrees.
+    UpdateRotation(f32),
+    ResetRotation,
+    Viewer(ViewerMessage),
+    EditViewField(ViewField, String),
+    SetViewField(ViewField, String),
+    UpdateAutoZoomSpeed(f32),
+    ToggleAnimateColors(bool),
+    TogglePanMomentum(bool),
+    ToggleSmoothZoom(bool),
+    ToggleSliderAnchor(bool),
+    ToggleProgressive(bool),
+    ToggleMinimap(bool),
+    ToggleGrid(bool),
+    ToggleOrbit(bool),
+    ToggleProbe(bool),
+    /// Compare the view with other coloring and iteration settings, right of a split line.
+    ToggleCompare(bool),
+    UpdateCompareIterations(u32),
+    SetCompareColoring(ColoringMode),
+    SetCompareInterior(InteriorMode),
+    ToggleCompareSmooth(bool),
+    ToggleLightTheme(bool),
+    ToggleRenderInBackground(bool),
+    ToggleFps(bool),
+    /// Name and backend of the graphics adapter, shown in the debug overlay.
+    AdapterInfo(String),
+    /// Rendering failed with the given error, or works again for `None`.
+    RenderError(Option<String>),
+    Tick,
+    SaveState,
+    ResetToDefaults,
+    CopyView,
+    PasteView,
+    CopyCoords,
+    EditBookmarkName(String),
+    AddBookmark,
+    GoToBookmark(usize),
+    /// Jump to the preset at the index of [`PRESETS`].
+    LoadPreset(usize),
+    DeleteBookmark(usize),
+    /// Clipboard contents read for [`Message::PasteView`].
+    PastedView(Option<String>),
+    ShowExport(bool),
+    ShowPalette(bool),
+    AddColorStop,
+    RemoveColorStop(usize),
+    /// Move the stop at an index to a position along the palette.
+    MoveColorStop(usize, f32),
+    /// Color of the stop at an index as typed.
+    EditColorStop(usize, String),
+    SetExportEnd(ExportEnd),
+    UpdateExportZoomLevels(f32),
+    EditExportFrames(String),
+    ToggleExportIterations(bool),
+    EditExportSize(String),
+    EditExportDirectory(String),
+    /// Render the zoom sequence from the current view to the chosen end view.
+    ExportSequence,
+    CancelExport,
+    ExportProgress(ExportEvent),
+    ShowBenchmark(bool),
+    EditBenchmarkCsv(String),
+    /// Time the current view at each of [`BENCHMARK_SWEEP`] iterations.
+    RunBenchmark,
+    CancelBenchmark,
+    BenchmarkProgress(BenchmarkEvent),
+    /// New contents of the `--watch-shader` file, or why it couldn't be read.
+    ShaderChanged(Result<String, String>),
+    CloseRequested(window::Id),
+    ToggleFullscreen,
+    ExitFullscreen,
+    /// The cursor moved to the given height within the window, in fullscreen.
+    FullscreenCursor(f32),
+    /// The cursor reached the bottom edge in fullscreen, showing the controls.
+    Activity,
+    /// Whether the cursor is over the controls, which stay shown meanwhile in fullscreen.
+    HoverControls(bool),
+    /// The window was resized to the given height.
+    WindowResized(f32),
+    /// The window gained or lost the keyboard focus.
+    WindowFocused(bool),
+    /// Hide the controls in fullscreen if the cursor stood still long enough.
+    HideControls,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ViewField {
+    CenterX,
+    CenterY,
+    Zoom,
+    /// The zoom as a magnification factor, see [`Controls::magnification`].
+    Magnification,
+}
+
+/// Text typed into one of the exact view fields, kept separately from `Controls`
+/// so that half-typed or invalid values never reach the shader.
+struct FieldInput {
+    text: String,
+    invalid: bool,
+    /// Typed into since the last successful submit, so not refreshed from the view.
+    editing: bool,
+}
+
+impl FieldInput {
+    fn new(value: impl ToString) -> Self {
+        Self {
+            text: value.to_string(),
+            invalid: false,
+            editing: false,
+        }
+    }
+
+    /// Shows `value` unless the user is editing the field.
+    fn sync(&mut self, value: impl ToString) {
+        if !self.editing {
+            self.text = value.to_string();
+        }
+    }
+}
+
+/// Text input style highlighting a value that failed to parse.
+struct InvalidInput;
+
+impl text_input::StyleSheet for InvalidInput {
+    type Style = Theme;
+
+    fn active(&self, style: &Theme) -> text_input::Appearance {
+        let mut appearance = style.active(&theme::TextInput::Default);
+        appearance.border.color = style.palette().danger;
+        appearance
+    }
+
+    fn focused(&self, style: &Theme) -> text_input::Appearance {
+        let mut appearance = style.focused(&theme::TextInput::Default);
+        appearance.border.color = style.palette().danger;
+        appearance
+    }
+
+    fn placeholder_color(&self, style: &Theme) -> Color {
+        style.placeholder_color(&theme::TextInput::Default)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.palette().danger
+    }
+
+    fn disabled_color(&self, style: &Theme) -> Color {
+        style.disabled_color(&theme::TextInput::Default)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        style.selection_color(&theme::TextInput::Default)
+    }
+
+    fn disabled(&self, style: &Theme) -> text_input::Appearance {
+        style.disabled(&theme::TextInput::Default)
+    }
+}
+
+/// Draws `overlay` on top of the top-left corner of `base`. Events only go to `base`.
+struct Overlay<'a> {
+    base: Element<'a, Message>,
+    overlay: Element<'a, Message>,
+    /// Whether `overlay` spans the bottom edge of `base` instead, taking events over it first.
+    bottom: bool,
+    /// Makes the message sent when the cursor moves, from whether it is over a bottom overlay.
+    on_hover: Option<fn(bool) -> Message>,
+}
+
+impl<'a> Overlay<'a> {
+    fn new(
+        base: impl Into<Element<'a, Message>>,
+        overlay: impl Into<Element<'a, Message>>,
+    ) -> Self {
+        Self {
+            base: base.into(),
+            overlay: overlay.into(),
+            bottom: false,
+            on_hover: None,
+        }
+    }
+
+    /// Interactive `overlay` along the bottom edge of `base`, which only gets the events outside
+    /// of it.
+    fn bottom(
+        base: impl Into<Element<'a, Message>>,
+        overlay: impl Into<Element<'a, Message>>,
+    ) -> Self {
+        Self {
+            bottom: true,
+            ..Self::new(base, overlay)
+        }
+    }
+
+    fn on_hover(mut self, on_hover: fn(bool) -> Message) -> Self {
+        self.on_hover = Some(on_hover);
+        self
+    }
+}
+
+impl<'a> Widget<Message, Theme, iced::Renderer> for Overlay<'a> {
+    fn size(&self) -> Size<Length> {
+        self.base.as_widget().size()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.base), Tree::new(&self.overlay)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.base, &self.overlay]);
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let base = self
+            .base
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits);
+        let mut overlay = self.overlay.as_widget().layout(
+            &mut tree.children[1],
+            renderer,
+            &layout::Limits::new(Size::ZERO, base.size()),
+        );
+        if self.bottom {
+            let y = base.size().height - overlay.size().height;
+            overlay.move_to_mut(iced::Point::new(0.0, y));
+        }
+        layout::Node::with_children(base.size(), vec![base, overlay])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        viewport: &Rectangle,
+    ) {
+        let mut children = layout.children();
+        let (base, overlay) = (children.next().unwrap(), children.next().unwrap());
+
+        self.base.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            base,
+            cursor,
+            viewport,
+        );
+
+        // Shader primitives are drawn after text within a layer, so the overlay needs its own.
+        renderer.with_layer(layout.bounds(), |renderer| {
+            self.overlay.as_widget().draw(
+                &tree.children[1],
+                renderer,
+                theme,
+                style,
+                overlay,
+                cursor,
+                viewport,
+            );
+        });
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: iced::Event,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> Status {
+        let mut children = layout.children();
+        let (base, overlay) = (children.next().unwrap(), children.next().unwrap());
+
+        let mut base_cursor = cursor;
+        if self.bottom {
+            let hovered = cursor.is_over(overlay.bounds());
+            if let (Some(on_hover), iced::Event::Mouse(mouse::Event::CursorMoved { .. })) =
+                (self.on_hover, &event)
+            {
+                shell.publish(on_hover(hovered));
+            }
+
+            let status = self.overlay.as_widget_mut().on_event(
+                &mut tree.children[1],
+                event.clone(),
+                overlay,
+                cursor,
+                renderer,
+                clipboard,
+                shell,
+                viewport,
+            );
+            if status == Status::Captured {
+                return status;
+            }
+
+            // Presses and scrolling over the overlay don't reach the view beneath it, while
+            // drags of the view started elsewhere go on.
+            if hovered {
+                if let iced::Event::Mouse(
+                    mouse::Event::ButtonPressed(_) | mouse::Event::WheelScrolled { .. },
+                ) = event
+                {
+                    return Status::Captured;
+                }
+                base_cursor = Cursor::Unavailable;
+            }
+        }
+
+        self.base.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            base,
+            base_cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        let mut children = layout.children();
+        let (base, overlay) = (children.next().unwrap(), children.next().unwrap());
+        if self.bottom && cursor.is_over(overlay.bounds()) {
+            return self.overlay.as_widget().mouse_interaction(
+                &tree.children[1],
+                overlay,
+                cursor,
+                viewport,
+                renderer,
+            );
+        }
+
+        self.base
+            .as_widget()
+            .mouse_interaction(&tree.children[0], base, cursor, viewport, renderer)
+    }
+
+    // Menus of pick lists on a bottom overlay.
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        translation: Vector,
+    ) -> Option<advanced::overlay::Element<'b, Message, Theme, iced::Renderer>> {
+        if !self.bottom {
+            return None;
+        }
+        self.overlay.as_widget_mut().overlay(
+            &mut tree.children[1],
+            layout.children().nth(1).unwrap(),
+            renderer,
+            translation,
+        )
+    }
+}
+
+impl<'a> From<Overlay<'a>> for Element<'a, Message> {
+    fn from(overlay: Overlay<'a>) -> Self {
+        Element::new(overlay)
+    }
+}
+
+/// Values of the coordinate grid lines, along the edges of the view.
+struct GridLabels<'a> {
+    program: &'a FragmentShaderProgram,
+}
+
+impl<'a> GridLabels<'a> {
+    fn new(program: &'a FragmentShaderProgram) -> Self {
+        Self { program }
+    }
+}
+
+impl<'a> Widget<Message, Theme, iced::Renderer> for GridLabels<'a> {
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Fill, Length::Fill)
+    }
+
+    fn layout(
+        &self,
+        _tree: &mut Tree,
+        _renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        layout::Node::new(limits.max())
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut iced::Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        for label in self.program.grid_labels(bounds) {
+            // Real parts are centered above the bottom edge, imaginary ones right of the left.
+            let (horizontal_alignment, vertical_alignment) = if label.real {
+                (alignment::Horizontal::Center, alignment::Vertical::Bottom)
+            } else {
+                (alignment::Horizontal::Left, alignment::Vertical::Center)
+            };
+            let text = advanced::Text {
+                content: &label.text,
+                bounds: Size::INFINITY,
+                size: Pixels(GRID_LABEL_SIZE),
+                line_height: advanced::text::LineHeight::default(),
+                font: renderer.default_font(),
+                horizontal_alignment,
+                vertical_alignment,
+                shaping: advanced::text::Shaping::Basic,
+            };
+            let position = bounds.position() + Vector::new(label.position.x, label.position.y);
+            // Shadowed, to stay readable on light parts of the image too.
+            renderer.fill_text(text, position + Vector::new(1.0, 1.0), Color::BLACK, bounds);
+            renderer.fill_text(text, position, Color::WHITE, bounds);
+        }
+    }
+}
+
+impl<'a> From<GridLabels<'a>> for Element<'a, Message> {
+    fn from(labels: GridLabels<'a>) -> Self {
+        Element::new(labels)
+    }
+}
+
+/// Last view of an exported zoom sequence.
+#[derive(Debug, Clone, PartialEq)]
+enum ExportEnd {
+    /// The start view zoomed in by [`ExportDialog::zoom_levels`].
+    ZoomIn,
+    Bookmark {
+        index: usize,
+        name: String,
+    },
+}
+
+impl std::fmt::Display for ExportEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportEnd::ZoomIn => f.write_str("Zoom in"),
+            ExportEnd::Bookmark { name, .. } => f.write_str(name),
+        }
+    }
+}
+
+/// Settings of the zoom sequence export, as typed into the sidebar.
+struct ExportDialog {
+    end: ExportEnd,
+    zoom_levels: f32,
+    frames: String,
+    /// Whether the iteration count follows the zoom, rather than staying at the start's.
+    ramp_iterations: bool,
+    size: String,
+    directory: String,
+    /// Outcome of the last export.
+    status: Option<String>,
+}
+
+impl Default for ExportDialog {
+    fn default() -> Self {
+        Self {
+            end: ExportEnd::ZoomIn,
+            zoom_levels: EXPORT_ZOOM_LEVELS_DEFAULT,
+            frames: EXPORT_FRAMES_DEFAULT.to_string(),
+            ramp_iterations: true,
+            size: EXPORT_SIZE_DEFAULT.to_string(),
+            directory: EXPORT_DIRECTORY_DEFAULT.to_string(),
+            status: None,
+        }
+    }
+}
+
+impl ExportDialog {
+    /// Sequence from `start` to the chosen end view, or what's wrong with the settings.
+    fn sequence(
+        &self,
+        start: Controls,
+        palette: &Palette,
+        bookmarks: &[Bookmark],
+    ) -> Result<Sequence, String> {
+        let end = match &self.end {
+            ExportEnd::ZoomIn => Controls {
+                zoom: (start.zoom + self.zoom_levels).min(ZOOM_MAX),
+                ..start
+            },
+            ExportEnd::Bookmark { index, .. } => {
+                bookmarks
+                    .get(*index)
+                    .ok_or("The end bookmark no longer exists")?
+                    .controls
+            }
+        };
+        let frames = match self.frames.trim().parse::<u32>() {
+            Ok(frames) if frames > 0 => frames,
+            _ => return Err(String::from("Frames must be a positive integer")),
+        };
+        let size = parse_size(&self.size).map_err(|err| format!("Size {err}"))?;
+        if self.directory.trim().is_empty() {
+            return Err(String::from("Directory must not be empty"));
+        }
+
+        Ok(Sequence {
+            start,
+            end,
+            frames,
+            ramp_iterations: self.ramp_iterations,
+            palette: palette.clone(),
+            size,
+            directory: PathBuf::from(self.directory.trim()),
+        })
+    }
+}
+
+/// Zoom sequence export running on a background thread.
+struct ExportJob {
+    id: u64,
+    sequence: Arc<Sequence>,
+    /// Frames written so far.
+    rendered: u32,
+    /// Set to stop the export before the next frame.
+    cancel: Arc<AtomicBool>,
+}
+
+static NEXT_EXPORT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `job` on a background thread, reporting its progress.
+fn export_progress(job: &ExportJob) -> Subscription<Message> {
+    use iced::futures::{SinkExt as _, StreamExt as _};
+
+    let sequence = job.sequence.clone();
+    let cancel = job.cancel.clone();
+    iced::subscription::channel(job.id, 1, |mut output| async move {
+        let (sender, mut events) = iced::futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || {
+            export_sequence(&sequence, &cancel, |event| {
+                let _ = sender.unbounded_send(event);
+            })
+        });
+
+        while let Some(event) = events.next().await {
+            let _ = output.send(Message::ExportProgress(event)).await;
+        }
+
+        std::future::pending().await
+    })
+}
+
+/// Benchmark of a view running on a background thread, see [`benchmark_sweep`].
+struct BenchmarkJob {
+    id: u64,
+    controls: Controls,
+    csv: Option<PathBuf>,
+    /// Set to stop the benchmark before the next iteration count.
+    cancel: Arc<AtomicBool>,
+}
+
+static NEXT_BENCHMARK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `job` on a background thread, reporting its progress.
+fn benchmark_progress(job: &BenchmarkJob) -> Subscription<Message> {
+    use iced::futures::{SinkExt as _, StreamExt as _};
+
+    let controls = job.controls;
+    let csv = job.csv.clone();
+    let cancel = job.cancel.clone();
+    // Subscriptions are told apart by the type and value of their id, export jobs use a bare
+    // `u64`.
+    let id = ("benchmark", job.id);
+    iced::subscription::channel(id, 1, move |mut output| async move {
+        let (sender, mut events) = iced::futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || {
+            benchmark_sweep(
+                controls,
+                BENCHMARK_SIZE,
+                &BENCHMARK_SWEEP,
+                csv.as_deref(),
+                &cancel,
+                |event| {
+                    let _ = sender.unbounded_send(event);
+                },
+            )
+        });
+
+        while let Some(event) = events.next().await {
+            let _ = output.send(Message::BenchmarkProgress(event)).await;
+        }
+
+        std::future::pending().await
+    })
+}
+
+/// Settings and results of the benchmark panel.
+#[derive(Debug, Default)]
+struct BenchmarkDialog {
+    /// File to write the results to as CSV, none if empty.
+    csv: String,
+    results: Vec<BenchmarkResult>,
+    /// Adapter and timing method of the last run.
+    adapter: Option<String>,
+    status: Option<String>,
+}
+
+/// What the progressively accumulated image depends on, besides the jitter of its samples.
+#[derive(PartialEq)]
+struct ImageSettings {
+    /// Without the zoom anchor, which isn't drawn.
+    controls: Controls,
+    palette: Palette,
+    grid: bool,
+    compare: Option<Controls>,
+    split: f32,
+}
+
+struct FragmentShaderApp {
+    program: FragmentShaderProgram,
+    preview: FragmentShaderProgram,
+    auto_zoom: Option<DVec2>,
+    auto_zoom_speed: f32,
+    /// Velocity the view keeps gliding with after a pan, in pixels per second of the view with
+    /// the given bounds.
+    momentum: Option<(Vec2, Rectangle)>,
+    /// Center and zoom the view glides to after a double-click.
+    recenter: Option<(DVec2, f32)>,
+    /// Zoom the view glides to after wheel steps, and the point on the complex plane kept in
+    /// place meanwhile.
+    zoom_target: Option<(DVec2, f32)>,
+    /// Whether the rotation is easing back to none.
+    resetting_rotation: bool,
+    /// Point under the cursor, shown in the status bar.
+    hovered: Option<DVec2>,
+    /// Whether the orbit of the hovered point is drawn over the view.
+    show_orbit: bool,
+    light_theme: bool,
+    /// Whether the window has the keyboard focus.
+    window_focused: bool,
+    window_minimized: bool,
+    /// Whether animations and accumulation keep going while the window is unfocused or
+    /// minimized, e.g. to record it.
+    render_in_background: bool,
+    animate_colors: bool,
+    /// Whether released pans keep gliding.
+    pan_momentum: bool,
+    /// Whether wheel steps zoom gradually rather than at once.
+    smooth_zoom: bool,
+    /// Whether the zoom slider keeps [`Controls::zoom_anchor`] in place rather than the center.
+    slider_anchor: bool,
+    /// Time of the last panning or zooming step, `None` once it settled.
+    last_interaction: Option<Instant>,
+    /// Start of the animation time passed to the shader while colors are animated.
+    start: Instant,
+    center_x_input: FieldInput,
+    center_y_input: FieldInput,
+    zoom_input: FieldInput,
+    magnification_input: FieldInput,
+    bookmarks: Vec<Bookmark>,
+    /// Name for the next bookmark, as typed.
+    bookmark_name: String,
+    /// Shader file given with `--watch-shader`.
+    watch_shader: Option<PathBuf>,
+    /// Why the watched shader file couldn't be read.
+    shader_read_error: Option<String>,
+    /// Graphics adapter and backend iced renders with, once fetched.
+    adapter: Option<String>,
+    /// Why the view can't be rendered.
+    render_error: Option<String>,
+    /// Why the last pasted view couldn't be applied.
+    paste_error: Option<String>,
+    /// Whether the sidebar shows the export settings instead of the bookmarks.
+    show_export: bool,
+    /// Whether the sidebar shows the palette editor instead of the bookmarks.
+    show_palette: bool,
+    /// Colors of the palette stops as typed, applied once they parse.
+    color_inputs: Vec<String>,
+    /// Whether the window is fullscreen, toggled with F11.
+    fullscreen: bool,
+    /// Time the cursor was last over the controls or the bottom edge, for hiding the controls
+    /// in fullscreen.
+    last_activity: Instant,
+    controls_hidden: bool,
+    hovering_controls: bool,
+    window_height: f32,
+    export: ExportDialog,
+    export_job: Option<ExportJob>,
+    /// Whether the sidebar shows the benchmark instead of the bookmarks.
+    show_benchmark: bool,
+    benchmark: BenchmarkDialog,
+    benchmark_job: Option<BenchmarkJob>,
+}
+
+/// Start-up state of [`FragmentShaderApp`].
+struct Flags {
+    session: Session,
+    /// Shader file given with `--watch-shader` and its contents.
+    shader: Option<(PathBuf, String)>,
+}
+
+/// Sends the contents of the file at `path` whenever it changes.
+fn watch_file(path: PathBuf) -> Subscription<Message> {
+    use iced::futures::{SinkExt as _, StreamExt as _};
+    use notify::Watcher as _;
+
+    iced::subscription::channel(path.clone(), 1, |mut output| async move {
+        let (sender, mut events) = iced::futures::channel::mpsc::unbounded();
+        // Editors often save by replacing the file, so watch the directory it's in.
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.unbounded_send(event);
+        })
+        .and_then(|mut watcher| {
+            let directory = path.parent().unwrap_or(&path);
+            watcher.watch(directory, notify::RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+
+        match watcher {
+            Ok(_watcher) => {
+                let mut last = std::fs::read_to_string(&path).ok();
+                while let Some(event) = events.next().await {
+                    let changed = event.is_ok_and(|event: notify::Event| {
+                        !event.kind.is_access() && event.paths.contains(&path)
+                    });
+                    if !changed {
+                        continue;
+                    }
+
+                    let code = std::fs::read_to_string(&path);
+                    if code.as_ref().ok() == last.as_ref() {
+                        continue;
+                    }
+                    last = code.as_ref().ok().cloned();
+                    let message = code.map_err(|err| format!("{}: {err}", path.display()));
+                    let _ = output.send(Message::ShaderChanged(message)).await;
+                }
+            }
+            Err(err) => {
+                let message = format!("Can't watch {}: {err}", path.display());
+                let _ = output.send(Message::ShaderChanged(Err(message))).await;
+            }
+        }
+
+        std::future::pending().await
+    })
+}
+
+/// Moves the zoom one tick's way towards `target`, keeping `point` on the complex plane in place.
+/// Returns whether the target was reached.
+fn zoom_towards(controls: &mut Controls, point: DVec2, target: f32, config: &Config) -> bool {
+    let mut zoom = controls.zoom + (target - controls.zoom) * ZOOM_SMOOTHING;
+    let reached = (target - zoom).abs() < ZOOM_SMOOTHING_PRECISION;
+    if reached {
+        zoom = target;
+    }
+    controls.zoom_around(point, zoom, config);
+    reached
+}
+
+/// `controls` at `zoom` for the zoom slider, keeping [`Controls::zoom_anchor`] in place on
+/// screen if `anchored` and set, the center otherwise.
+fn slider_zoom(mut controls: Controls, zoom: f32, anchored: bool, config: &Config) -> Controls {
+    match controls.zoom_anchor.filter(|_| anchored) {
+        Some(anchor) => controls.zoom_around(anchor, zoom, config),
+        None => controls.zoom = zoom,
+    }
+    controls
+}
+
+fn control<'a>(
+    label: &'static str,
+    control: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    row![text(label), control.into()].spacing(10).into()
+}
+
+/// Magnification as shown next to the zoom slider, e.g. `×1.0e6`.
+fn format_magnification(magnification: f64) -> String {
+    format!("\u{d7}{magnification:.1e}")
+}
+
+/// Magnification in its field, precise enough to leave the zoom as is when submitted.
+fn magnification_field(magnification: f64) -> String {
+    format!("{magnification:.6e}")
+}
+
+/// Parses a color written as `#rrggbb`, the `#` being optional.
+fn parse_hex_color(hex: &str) -> Result<[u8; 3], String> {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(format!("expected #rrggbb, not {hex:?}"));
+    }
+    let mut color = [0; 3];
+    for (i, channel) in color.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("expected #rrggbb, not {hex:?}"))?;
+    }
+    Ok(color)
+}
+
+fn field_input<'a>(field: ViewField, input: &FieldInput) -> TextInput<'a, Message> {
+    let input_field = text_input("", &input.text)
+        .on_input(move |text| Message::EditViewField(field, text))
+        .on_submit(Message::SetViewField(field, input.text.clone()));
+
+    if input.invalid {
+        input_field.style(theme::TextInput::Custom(Box::new(InvalidInput)))
+    } else {
+        input_field
+    }
+}
+
+impl FragmentShaderApp {
+    fn field_input_mut(&mut self, field: ViewField) -> &mut FieldInput {
+        match field {
+            ViewField::CenterX => &mut self.center_x_input,
+            ViewField::CenterY => &mut self.center_y_input,
+            ViewField::Zoom => &mut self.zoom_input,
+            ViewField::Magnification => &mut self.magnification_input,
+        }
+    }
+
+    fn session(&self) -> Session {
+        Session {
+            version: SESSION_VERSION,
+            controls: self.program.controls,
+            auto_zoom_speed: self.auto_zoom_speed,
+            animate_colors: self.animate_colors,
+            pan_momentum: self.pan_momentum,
+            smooth_zoom: self.smooth_zoom,
+            slider_anchor: self.slider_anchor,
+            progressive: self.program.frame_index.is_some(),
+            show_minimap: self.program.minimap,
+            show_grid: self.program.grid,
+            show_orbit: self.show_orbit,
+            probe: self.program.probe,
+            show_fps: self.program.frame_stats.is_some(),
+            light_theme: self.light_theme,
+            render_in_background: self.render_in_background,
+            palette: self.program.palette.clone(),
+        }
+    }
+
+    /// Why the watched shader can't be used, the last one that could stays in use meanwhile.
+    fn shader_error(&self) -> Option<String> {
+        if let Some(err) = &self.shader_read_error {
+            return Some(err.clone());
+        }
+        self.program.shader_result()?.err()
+    }
+
+    fn image_settings(&self) -> ImageSettings {
+        ImageSettings {
+            controls: Controls {
+                zoom_anchor: None,
+                ..self.program.controls
+            },
+            palette: self.program.palette.clone(),
+            grid: self.program.grid,
+            compare: self.program.compare,
+            split: self.program.split,
+        }
+    }
+
+    /// Whether the view is kept redrawn, which stops while the window is in the background
+    /// unless opted out of.
+    fn window_active(&self) -> bool {
+        self.render_in_background || (self.window_focused && !self.window_minimized)
+    }
+
+    /// Whether the watched shader is yet to be compiled, keeping the view redrawn until then.
+    fn shader_pending(&self) -> bool {
+        self.watch_shader.is_some() && self.program.shader_result().is_none()
+    }
+
+    fn apply_session(&mut self, session: Session) {
+        self.jump_to(session.controls);
+        self.auto_zoom_speed = session.auto_zoom_speed;
+        self.animate_colors = session.animate_colors;
+        self.pan_momentum = session.pan_momentum;
+        self.smooth_zoom = session.smooth_zoom;
+        self.slider_anchor = session.slider_anchor;
+        self.program.frame_index = session.progressive.then_some(0);
+        self.program.minimap = session.show_minimap;
+        self.program.grid = session.show_grid;
+        self.show_orbit = session.show_orbit;
+        self.program.probe = session.probe;
+        self.program.frame_stats = session.show_fps.then(Default::default);
+        self.light_theme = session.light_theme;
+        self.render_in_background = session.render_in_background;
+        self.set_palette(session.palette);
+    }
+
+    /// Replaces the palette, along with the text of its color fields.
+    fn set_palette(&mut self, palette: Palette) {
+        self.color_inputs = palette.stops().iter().map(|stop| stop.hex()).collect();
+        self.program.palette = palette;
+    }
+
+    fn save_state(&self) {
+        if let Err(err) = self.session().save() {
+            eprintln!("Failed to save state: {err}");
+        }
+    }
+
+    /// Settings of the right side of a comparison.
+    fn compare_controls<'a>(compare: &Controls, config: &Config) -> Element<'a, Message> {
+        row![
+            text("Right of the split:"),
+            control(
+                "Max iterations",
+                slider(
+                    config.iter_range(),
+                    compare.max_iter,
+                    Message::UpdateCompareIterations,
+                )
+                .width(Length::Fill)
+            ),
+            pick_list(
+                ColoringMode::ALL,
+                Some(compare.coloring),
+                Message::SetCompareColoring
+            ),
+            pick_list(
+                InteriorMode::ALL,
+                Some(compare.interior),
+                Message::SetCompareInterior
+            ),
+            checkbox("Smooth coloring", compare.smooth).on_toggle(Message::ToggleCompareSmooth),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    /// Cursor position and view readout, with enough digits for the current zoom level.
+    fn status_bar(&self) -> Element<'_, Message> {
+        let controls = &self.program.controls;
+        let precision = controls.coordinate_precision();
+        let point = |point: DVec2| format!("{:.precision$}, {:.precision$}", point.x, point.y);
+
+        let mut row = row![
+            text(format!(
+                "Cursor: {}",
+                self.hovered.map_or_else(|| "-".to_owned(), point)
+            )),
+            text(format!("Center: {}", point(controls.center))),
+            text(format!(
+                "Magnification: {}",
+                format_magnification(controls.magnification())
+            )),
+            text(format!("Iterations: {}", controls.iterations())),
+        ]
+        .spacing(20);
+
+        if controls.mode == RenderMode::Buddhabrot {
+            row = row.push(text(format!(
+                "Samples: {}",
+                self.program.buddhabrot_samples()
+            )));
+        }
+
+        let tiles = self.program.tile_progress();
+        if tiles.pending() {
+            row = row.push(text(format!("Tiles: {}/{}", tiles.done, tiles.total)));
+        }
+
+        if let Some(info) = self.program.inspected() {
+            let iterations = info.iterations.map_or_else(
+                || "interior".to_owned(),
+                |iterations| format!("{iterations:.3} iterations"),
+            );
+            row = row.push(text(format!(
+                "Inspected {}: {iterations}, |z| = {:.4}",
+                point(info.point),
+                info.magnitude
+            )));
+        }
+        if let Some(err) = self.program.inspection_error() {
+            row = row.push(text(err));
+        }
+
+        row.into()
+    }
+
+    fn save_bookmarks(&self) {
+        if let Err(err) = Bookmark::save_all(&self.bookmarks) {
+            eprintln!("Failed to save bookmarks: {err}");
+        }
+    }
+
+    /// Replaces the whole view, e.g. with a bookmark, stopping any animated movement.
+    fn jump_to(&mut self, controls: Controls) {
+        self.program.controls = controls;
+        self.preview.controls.formula = controls.formula;
+        self.preview.controls.coloring = controls.coloring;
+        self.preview.controls.interior = controls.interior;
+        self.auto_zoom = None;
+        self.momentum = None;
+        self.recenter = None;
+        self.zoom_target = None;
+        self.resetting_rotation = false;
+    }
+
+    fn export_panel(&self) -> Element<'_, Message> {
+        let export = &self.export;
+        let ends: Vec<ExportEnd> =
+            std::iter::once(ExportEnd::ZoomIn)
+                .chain(self.bookmarks.iter().enumerate().map(|(index, bookmark)| {
+                    ExportEnd::Bookmark {
+                        index,
+                        name: bookmark.name.clone(),
+                    }
+                }))
+                .collect();
+
+        let mut panel = column![
+            row![
+                text("Export zoom sequence").width(Length::Fill),
+                button("x").on_press(Message::ShowExport(false)),
+            ]
+            .align_items(Alignment::Center),
+            text("End view"),
+            pick_list(ends, Some(export.end.clone()), Message::SetExportEnd).width(Length::Fill),
+        ]
+        .spacing(5);
+
+        if export.end == ExportEnd::ZoomIn {
+            panel = panel
+                .push(text(format!("Zoom levels: {}", export.zoom_levels)))
+                .push(
+                    slider(
+                        EXPORT_ZOOM_LEVELS_MIN..=EXPORT_ZOOM_LEVELS_MAX,
+                        export.zoom_levels,
+                        Message::UpdateExportZoomLevels,
+                    )
+                    .step(1.0),
+                );
+        }
+
+        panel = panel
+            .push(text("Frames"))
+            .push(text_input("", &export.frames).on_input(Message::EditExportFrames))
+            .push(
+                checkbox("Ramp up iterations", export.ramp_iterations)
+                    .on_toggle(Message::ToggleExportIterations),
+            )
+            .push(text("Size"))
+            .push(text_input(EXPORT_SIZE_DEFAULT, &export.size).on_input(Message::EditExportSize))
+            .push(text("Directory"))
+            .push(text_input("", &export.directory).on_input(Message::EditExportDirectory));
+
+        panel = match &self.export_job {
+            Some(job) => panel
+                .push(
+                    progress_bar(0.0..=job.sequence.frames as f32, job.rendered as f32)
+                        .height(Length::Fixed(20.0)),
+                )
+                .push(text(format!(
+                    "{} of {} frames",
+                    job.rendered, job.sequence.frames
+                )))
+                .push(button("Cancel").on_press(Message::CancelExport)),
+            None => panel.push(button("Export").on_press(Message::ExportSequence)),
+        };
+
+        if let Some(status) = &export.status {
+            panel = panel.push(text(status));
+        }
+
+        scrollable(panel).height(Length::Fill).into()
+    }
+
+    fn benchmark_panel(&self) -> Element<'_, Message> {
+        let benchmark = &self.benchmark;
+        let sweep: Vec<String> = BENCHMARK_SWEEP.iter().map(u32::to_string).collect();
+        let mut panel = column![
+            row![
+                text("Benchmark").width(Length::Fill),
+                button("x").on_press(Message::ShowBenchmark(false)),
+            ]
+            .align_items(Alignment::Center),
+            text(format!(
+                "Times the current view at {}x{} with {} iterations, {} frames each after {} warmup frames.",
+                BENCHMARK_SIZE.width,
+                BENCHMARK_SIZE.height,
+                sweep.join(", "),
+                BENCHMARK_FRAMES,
+                BENCHMARK_WARMUP_FRAMES,
+            ))
+            .size(14),
+            text("CSV file"),
+            text_input("none", &benchmark.csv).on_input(Message::EditBenchmarkCsv),
+        ]
+        .spacing(5);
+
+        panel = match &self.benchmark_job {
+            Some(_) => panel
+                .push(
+                    progress_bar(
+                        0.0..=BENCHMARK_SWEEP.len() as f32,
+                        benchmark.results.len() as f32,
+                    )
+                    .height(Length::Fixed(20.0)),
+                )
+                .push(button("Cancel").on_press(Message::CancelBenchmark)),
+            None => panel.push(button("Run benchmark").on_press(Message::RunBenchmark)),
+        };
+
+        if let Some(adapter) = &benchmark.adapter {
+            panel = panel.push(text(adapter).size(14));
+        }
+
+        if !benchmark.results.is_empty() {
+            let cell = |content: String| text(content).width(Length::FillPortion(1));
+            panel = panel.push(row![
+                cell("Iterations".into()),
+                cell("Mean ms".into()),
+                cell("Min ms".into()),
+            ]);
+            for result in &benchmark.results {
+                panel = panel.push(row![
+                    cell(result.max_iter.to_string()),
+                    cell(format!("{:.2}", result.mean * 1000.0)),
+                    cell(format!("{:.2}", result.min * 1000.0)),
+                ]);
+            }
+        }
+
+        if let Some(status) = &benchmark.status {
+            panel = panel.push(text(status));
+        }
+
+        scrollable(panel).height(Length::Fill).into()
+    }
+
+    fn palette_panel(&self) -> Element<'_, Message> {
+        let palette = &self.program.palette;
+        let swatch = |color: [u8; 3], width| {
+            let [r, g, b] = color;
+            container(text(""))
+                .width(width)
+                .height(Length::Fixed(20.0))
+                .style(move |_: &Theme| container::Appearance {
+                    background: Some(Color::from_rgb8(r, g, b).into()),
+                    ..Default::default()
+                })
+        };
+        let preview = (0..PALETTE_PREVIEW_STEPS).fold(row![], |preview, step| {
+            let position = step as f32 / (PALETTE_PREVIEW_STEPS - 1) as f32;
+            preview.push(swatch(palette.color_at(position), Length::Fill))
+        });
+
+        let removable = palette.stops().len() > 2;
+        let stops = palette.stops().iter().enumerate().fold(
+            column![].spacing(5),
+            |stops, (index, stop)| {
+                let input = &self.color_inputs[index];
+                let mut color = text_input("#rrggbb", input)
+                    .on_input(move |hex| Message::EditColorStop(index, hex))
+                    .width(Length::Fixed(80.0));
+                if parse_hex_color(input).is_err() {
+                    color = color.style(theme::TextInput::Custom(Box::new(InvalidInput)));
+                }
+                stops.push(
+                    row![
+                        swatch(stop.color, Length::Fixed(20.0)),
+                        slider(0.0..=1.0, stop.position, move |position| {
+                            Message::MoveColorStop(index, position)
+                        })
+                        .step(0.01),
+                        color,
+                        button("x")
+                            .on_press_maybe(removable.then_some(Message::RemoveColorStop(index))),
+                    ]
+                    .spacing(5)
+                    .align_items(Alignment::Center),
+                )
+            },
+        );
+
+        let panel = column![
+            row![
+                text("Palette").width(Length::Fill),
+                button("x").on_press(Message::ShowPalette(false)),
+            ]
+            .align_items(Alignment::Center),
+            preview,
+            stops,
+            button("Add stop").on_press(Message::AddColorStop),
+            text("Bookmarks keep the palette they were made with."),
+        ]
+        .spacing(10);
+
+        scrollable(panel).height(Length::Fill).into()
+    }
+
+    fn bookmarks_panel(&self) -> Element<'_, Message> {
+        // Stacked, the sidebar is too narrow for a row.
+        let add = column![
+            text_input("Bookmark name", &self.bookmark_name)
+                .on_input(Message::EditBookmarkName)
+                .on_submit(Message::AddBookmark),
+            button("Bookmark this view").on_press(Message::AddBookmark),
+        ]
+        .spacing(5);
+
+        if self.bookmarks.is_empty() {
+            return column![add, text("No bookmarks yet")].spacing(10).into();
+        }
+
+        let list = self.bookmarks.iter().enumerate().fold(
+            column![].spacing(5),
+            |list, (index, bookmark)| {
+                list.push(
+                    row![
+                        button(text(&bookmark.name))
+                            .on_press(Message::GoToBookmark(index))
+                            .width(Length::Fill),
+                        button("x").on_press(Message::DeleteBookmark(index)),
+                    ]
+                    .spacing(5),
+                )
+            },
+        );
+
+        column![add, scrollable(list).height(Length::Fill)]
+            .spacing(10)
+            .into()
+    }
+
+    /// Switches to reduced resolution until the view stops moving for a while.
+    fn start_interaction(&mut self) {
+        self.program.interacting = true;
+        self.last_interaction = Some(Instant::now());
+    }
+
+    /// Applies input on the fractal view.
+    fn update_viewer(&mut self, message: ViewerMessage) {
+        match message {
+            ViewerMessage::UpdateMaxIterations(max_iter) => {
+                self.program.controls.max_iter = max_iter;
+                self.program.controls.auto_iter = false;
+            }
+            ViewerMessage::MoveTrap(point) => {
+                self.momentum = None;
+                self.program.controls.trap_point = point;
+            }
+            ViewerMessage::PanningDelta(delta, bounds) => {
+                self.start_interaction();
+                self.auto_zoom = None;
+                self.program.controls.pan(delta, bounds);
+            }
+            ViewerMessage::StartMomentum(velocity, bounds) => {
+                if self.pan_momentum {
+                    self.momentum = Some((velocity, bounds));
+                }
+            }
+            ViewerMessage::StopMomentum => {
+                self.momentum = None;
+                self.recenter = None;
+            }
+            ViewerMessage::ZoomDelta(pos, bounds, delta) if self.smooth_zoom => {
+                self.auto_zoom = None;
+                self.momentum = None;
+                self.recenter = None;
+                // Further steps add up, the point kept in place follows the cursor.
+                let zoom = self
+                    .zoom_target
+                    .map_or(self.program.controls.zoom, |(_, zoom)| zoom);
+                let point = self.program.controls.fractal_point(pos, bounds);
+                let zoom_range = self.program.config.zoom_range();
+                let target = wheel_zoom(zoom, delta).clamp(*zoom_range.start(), *zoom_range.end());
+                self.zoom_target = Some((point, target));
+            }
+            ViewerMessage::ZoomDelta(pos, bounds, delta)
+            | ViewerMessage::PinchZoom(pos, bounds, delta) => {
+                self.start_interaction();
+                self.auto_zoom = None;
+                self.momentum = None;
+                self.recenter = None;
+                self.zoom_target = None;
+                self.program.controls = apply_zoom(
+                    self.program.controls,
+                    pos,
+                    bounds,
+                    delta,
+                    &self.program.config,
+                );
+            }
+            ViewerMessage::RotationDelta(angle) => {
+                self.start_interaction();
+                self.momentum = None;
+                self.resetting_rotation = false;
+                // The image turns along with the cursor.
+                self.program.controls.rotate(-angle);
+            }
+            ViewerMessage::HoveredPoint(point) => {
+                if let Some(point) = point {
+                    self.preview.controls.julia = Some(point.as_vec2());
+                }
+                self.hovered = point;
+            }
+            ViewerMessage::StartAutoZoom(pos, bounds) => {
+                self.momentum = None;
+                self.recenter = None;
+                self.zoom_target = None;
+                self.auto_zoom = Some(self.program.controls.fractal_point(pos, bounds));
+            }
+            ViewerMessage::CenterOn(center, zoom_in) => {
+                let controls = &self.program.controls;
+                let zoom = if zoom_in {
+                    (controls.zoom + 1.0).min(*self.program.config.zoom_range().end())
+                } else {
+                    controls.zoom
+                };
+                self.momentum = None;
+                self.auto_zoom = None;
+                self.zoom_target = None;
+                self.recenter = Some((center, zoom));
+            }
+            ViewerMessage::Inspect(pos, bounds) => {
+                self.momentum = None;
+                self.program.inspect(pos, bounds);
+            }
+            ViewerMessage::Clicked(point) => {
+                self.program.controls.zoom_anchor = Some(point);
+            }
+            ViewerMessage::MoveSplit(split) => {
+                self.program.split = split;
+            }
+            ViewerMessage::Probe(pos, bounds) => {
+                let point = self.program.controls.fractal_point(pos, bounds);
+                self.update_viewer(ViewerMessage::HoveredPoint(Some(point)));
+                self.program.inspect(pos, bounds);
+            }
+            ViewerMessage::JumpTo(center) => {
+                self.jump_to(Controls {
+                    center,
+                    ..self.program.controls
+                });
+            }
+        }
+    }
+
+    /// Refreshes the view fields after the view moved, except those being edited.
+    fn sync_view_fields(&mut self) {
+        let controls = &self.program.controls;
+        self.center_x_input.sync(controls.center.x);
+        self.center_y_input.sync(controls.center.y);
+        self.zoom_input.sync(controls.zoom);
+        self.magnification_input
+            .sync(magnification_field(controls.magnification()));
+    }
+
+    /// Applies a submitted field value, returning `false` if it was rejected.
+    fn set_view_field(&mut self, field: ViewField, text: &str) -> bool {
+        let mut text = text.trim();
+        if matches!(field, ViewField::Magnification) {
+            text = text.trim_start_matches(['\u{d7}', 'x']);
+        }
+        let Ok(value) = text.parse::<f64>() else {
+            return false;
+        };
+
+        if !value.is_finite() {
+            return false;
+        }
+
+        let controls = &mut self.program.controls;
+        match field {
+            ViewField::CenterX => controls.center.x = value,
+            ViewField::CenterY => controls.center.y = value,
+            ViewField::Zoom => {
+                let zoom = value as f32;
+                if !self.program.config.zoom_range().contains(&zoom) {
+                    return false;
+                }
+                controls.zoom = zoom;
+            }
+            ViewField::Magnification => {
+                let zoom = Controls::zoom_for_magnification(value);
+                if !self.program.config.zoom_range().contains(&zoom) {
+                    return false;
+                }
+                controls.zoom = zoom;
+            }
+        }
+
+        self.auto_zoom = None;
+        true
+    }
+}
+
+impl Application for FragmentShaderApp {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    type Flags = Flags;
+
+    fn new(flags: Flags) -> (Self, Command<Message>) {
+        let Flags { session, shader } = flags;
+        let controls = session.controls;
+        let mut app = Self {
+            program: FragmentShaderProgram::new(Controls::default()),
+            preview: FragmentShaderProgram::preview(Controls {
+                zoom: ZOOM_MIN,
+                center: JULIA_CENTER,
+                julia: Some(JULIA_SEED_DEFAULT),
+                formula: controls.formula,
+                coloring: controls.coloring,
+                ..Controls::default()
+            }),
+            auto_zoom: None,
+            auto_zoom_speed: AUTO_ZOOM_SPEED_DEFAULT,
+            momentum: None,
+            recenter: None,
+            zoom_target: None,
+            resetting_rotation: false,
+            hovered: None,
+            show_orbit: false,
+            light_theme: false,
+            window_focused: true,
+            window_minimized: false,
+            render_in_background: false,
+            animate_colors: false,
+            pan_momentum: true,
+            smooth_zoom: true,
+            slider_anchor: false,
+            last_interaction: None,
+            start: Instant::now(),
+            center_x_input: FieldInput::new(controls.center.x),
+            center_y_input: FieldInput::new(controls.center.y),
+            zoom_input: FieldInput::new(controls.zoom),
+            magnification_input: FieldInput::new(magnification_field(controls.magnification())),
+            bookmarks: Bookmark::load_all(),
+            bookmark_name: String::new(),
+            watch_shader: None,
+            shader_read_error: None,
+            adapter: None,
+            render_error: None,
+            paste_error: None,
+            show_export: false,
+            show_palette: false,
+            color_inputs: Vec::new(),
+            fullscreen: false,
+            last_activity: Instant::now(),
+            controls_hidden: false,
+            hovering_controls: false,
+            window_height: 0.0,
+            export: ExportDialog::default(),
+            export_job: None,
+            show_benchmark: false,
+            benchmark: BenchmarkDialog::default(),
+            benchmark_job: None,
+        };
+        app.preview.errors = app.program.errors.clone();
+        if let Some((path, code)) = shader {
+            app.watch_shader = Some(path);
+            app.program.set_shader(code);
+        }
+        app.apply_session(session);
+        app.program.update_reference_orbit();
+
+        let adapter = system::fetch_information(|information| {
+            Message::AdapterInfo(format!(
+                "{} ({})",
+                information.graphics_adapter, information.graphics_backend
+            ))
+        });
+
+        (app, adapter)
+    }
+
+    fn title(&self) -> String {
+        String::from("Fragment Shader Widget - Iced")
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let config = &self.program.config;
+        let iter_range = config.iter_range();
+        let iterations: Element<'_, Message> = if self.program.controls.auto_iter {
+            progress_bar(
+                *iter_range.start() as f32..=*iter_range.end() as f32,
+                self.program.controls.iterations() as f32,
+            )
+            .height(Length::Fixed(20.0))
+            .into()
+        } else {
+            slider(
+                iter_range,
+                self.program.controls.max_iter,
+                Message::UpdateMaxIterations,
+            )
+            .width(Length::Fill)
+            .into()
+        };
+
+        let controls = row![
+            control("Max iterations", iterations),
+            checkbox("Auto", self.program.controls.auto_iter)
+                .on_toggle(Message::ToggleAutoIterations),
+            control(
+                "Zoom",
+                slider(
+                    config.zoom_range(),
+                    self.program.controls.zoom,
+                    move |zoom| { Message::UpdateZoom(zoom) }
+                )
+                .step(0.01)
+                .width(Length::Fill)
+            ),
+            text(format_magnification(self.program.controls.magnification())),
+            control(
+                "Rotation",
+                slider(
+                    -180.0..=180.0,
+                    self.program.controls.rotation.to_degrees(),
+                    Message::UpdateRotation
+                )
+                .width(Length::Fill)
+            ),
+            button("Reset rotation").on_press(Message::ResetRotation),
+            control(
+                "Auto-zoom speed",
+                slider(
+                    AUTO_ZOOM_SPEED_MIN..=AUTO_ZOOM_SPEED_MAX,
+                    self.auto_zoom_speed,
+                    move |speed| { Message::UpdateAutoZoomSpeed(speed) }
+                )
+                .step(0.01)
+                .width(Length::Fill)
+            ),
+        ];
+
+        let selected_formula = self.program.controls.formula;
+        let mut formula = row![
+            pick_list(
+                RenderMode::ALL,
+                Some(self.program.controls.mode),
+                Message::SetRenderMode
+            ),
+            pick_list(
+                Formula::all(match selected_formula {
+                    Formula::Multibrot { power } => power,
+                    _ => MULTIBROT_POWER_DEFAULT,
+                }),
+                Some(selected_formula),
+                Message::SetFormula
+            )
+        ];
+
+        if self.program.controls.mode == RenderMode::Buddhabrot {
+            formula = formula.push(control(
+                "Exposure",
+                slider(
+                    EXPOSURE_MIN..=EXPOSURE_MAX,
+                    self.program.controls.exposure,
+                    Message::UpdateExposure,
+                )
+                .step(0.05)
+                .width(Length::Fixed(100.0)),
+            ));
+        }
+
+        if let Formula::Multibrot { power } = selected_formula {
+            formula = formula.push(control(
+                "Power",
+                slider(
+                    MULTIBROT_POWER_MIN..=MULTIBROT_POWER_MAX,
+                    power,
+                    Message::UpdateMultibrotPower,
+                )
+                .step(0.01)
+                .width(Length::Fill),
+            ));
+        }
+
+        formula = formula
+            .push(control(
+                "Escape radius",
+                slider(
+                    ESCAPE_RADIUS_MIN..=ESCAPE_RADIUS_MAX,
+                    self.program.controls.escape_radius,
+                    Message::UpdateEscapeRadius,
+                )
+                .width(Length::Fill),
+            ))
+            .push(pick_list(
+                ColoringMode::ALL,
+                Some(self.program.controls.coloring),
+                Message::SetColoringMode,
+            ));
+
+        if self.program.controls.coloring.trap() != 0 {
+            formula = formula.push(text("Ctrl+drag moves the trap"));
+        }
+
+        if self.program.controls.coloring == ColoringMode::DistanceLines {
+            formula = formula.push(control(
+                "Line width",
+                slider(
+                    LINE_WIDTH_MIN..=LINE_WIDTH_MAX,
+                    self.program.controls.line_width,
+                    Message::UpdateLineWidth,
+                )
+                .step(0.1)
+                .width(Length::Fill),
+            ));
+        }
+
+        if self.program.controls.coloring == ColoringMode::OrbitTrapCircle {
+            formula = formula.push(control(
+                "Trap radius",
+                slider(
+                    TRAP_RADIUS_MIN..=TRAP_RADIUS_MAX,
+                    self.program.controls.trap_radius,
+                    Message::UpdateTrapRadius,
+                )
+                .step(0.01)
+                .width(Length::Fill),
+            ));
+        }
+
+        if self.program.controls.coloring == ColoringMode::Iterations {
+            formula = formula.push(
+                checkbox("Stripes", self.program.controls.stripe).on_toggle(Message::ToggleStripe),
+            );
+            if self.program.controls.stripe {
+                formula = formula.push(control(
+                    "Stripe frequency",
+                    slider(
+                        STRIPE_FREQUENCY_MIN..=STRIPE_FREQUENCY_MAX,
+                        self.program.controls.stripe_frequency,
+                        Message::UpdateStripeFrequency,
+                    )
+                    .step(0.5)
+                    .width(Length::Fixed(100.0)),
+                ));
+            }
+        }
+
+        let formula = formula
+            .push(pick_list(
+                InteriorMode::ALL,
+                Some(self.program.controls.interior),
+                Message::SetInteriorMode,
+            ))
+            .push(
+                checkbox("Smooth coloring", self.program.controls.smooth)
+                    .on_toggle(Message::ToggleSmooth),
+            )
+            .push(
+                checkbox("Histogram", self.program.controls.histogram)
+                    .on_toggle(Message::ToggleHistogram),
+            )
+            .push(control(
+                "Gamma",
+                slider(
+                    GAMMA_MIN..=GAMMA_MAX,
+                    self.program.controls.gamma,
+                    Message::UpdateGamma,
+                )
+                .step(0.05)
+                .width(Length::Fixed(100.0)),
+            ))
+            .push(control(
+                "Offset",
+                slider(
+                    0.0..=1.0,
+                    self.program.controls.color_offset,
+                    Message::UpdateColorOffset,
+                )
+                .step(0.01)
+                .width(Length::Fixed(100.0)),
+            ))
+            .push(control(
+                "Frequency",
+                slider(
+                    COLOR_FREQUENCY_MIN..=COLOR_FREQUENCY_MAX,
+                    self.program.controls.color_frequency,
+                    Message::UpdateColorFrequency,
+                )
+                .step(0.1)
+                .width(Length::Fixed(100.0)),
+            ))
+            .push(control(
+                "Cycle speed",
+                slider(
+                    0.0..=COLOR_CYCLE_SPEED_MAX,
+                    self.program.controls.color_cycle_speed,
+                    Message::UpdateColorCycleSpeed,
+                )
+                .step(0.01)
+                .width(Length::Fixed(100.0)),
+            ))
+            .push(control(
+                "AA",
+                pick_list(
+                    AA_SAMPLES,
+                    Some(self.program.controls.aa_samples),
+                    Message::SetAntialiasing,
+                ),
+            ))
+            .push(text("(higher is slower)"))
+            .push(control(
+                "Tiles",
+                pick_list(TILES, Some(self.program.controls.tiles), Message::SetTiles),
+            ))
+            .push(control(
+                "Aspect",
+                pick_list(
+                    &AspectRatio::ALL[..],
+                    Some(self.program.controls.aspect),
+                    Message::SetAspectRatio,
+                ),
+            ))
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+        let view_fields = row![
+            control(
+                "Center x",
+                field_input(ViewField::CenterX, &self.center_x_input)
+            ),
+            control(
+                "Center y",
+                field_input(ViewField::CenterY, &self.center_y_input)
+            ),
+            control("Zoom", field_input(ViewField::Zoom, &self.zoom_input)),
+            control(
+                "Magnification",
+                field_input(ViewField::Magnification, &self.magnification_input)
+            ),
+            checkbox("Animate colors", self.animate_colors).on_toggle(Message::ToggleAnimateColors),
+            checkbox("Pan momentum", self.pan_momentum).on_toggle(Message::TogglePanMomentum),
+            checkbox("Smooth zoom", self.smooth_zoom).on_toggle(Message::ToggleSmoothZoom),
+            checkbox("Slider zooms at last click", self.slider_anchor)
+                .on_toggle(Message::ToggleSliderAnchor),
+            checkbox("Progressive AA", self.program.frame_index.is_some())
+                .on_toggle(Message::ToggleProgressive),
+            checkbox("Minimap", self.program.minimap).on_toggle(Message::ToggleMinimap),
+            checkbox("Grid", self.program.grid).on_toggle(Message::ToggleGrid),
+            checkbox("Show orbit", self.show_orbit).on_toggle(Message::ToggleOrbit),
+            checkbox("Probe", self.program.probe).on_toggle(Message::ToggleProbe),
+            checkbox("Compare", self.program.compare.is_some()).on_toggle(Message::ToggleCompare),
+            checkbox("Debug overlay", self.program.frame_stats.is_some())
+                .on_toggle(Message::ToggleFps),
+            checkbox("Light theme", self.light_theme).on_toggle(Message::ToggleLightTheme),
+            checkbox("Render in background", self.render_in_background)
+                .on_toggle(Message::ToggleRenderInBackground),
+            button("Save state").on_press(Message::SaveState),
+            button("Reset to defaults").on_press(Message::ResetToDefaults),
+            button("Copy view").on_press(Message::CopyView),
+            button("Paste view").on_press(Message::PasteView),
+            button("Copy coords").on_press(Message::CopyCoords),
+            button("Export sequence").on_press(Message::ShowExport(true)),
+            button("Edit palette").on_press(Message::ShowPalette(true)),
+            button("Benchmark").on_press(Message::ShowBenchmark(true)),
+            button(if self.fullscreen {
+                "Exit fullscreen"
+            } else {
+                "Fullscreen"
+            })
+            .on_press(Message::ToggleFullscreen),
+        ]
+        .spacing(10);
+
+        let preview = Element::from(
+            shader(&self.preview)
+                .width(Length::Fixed(240.0))
+                .height(Length::Fixed(180.0)),
+        )
+        .map(Message::Viewer);
+        let panel = if self.show_export {
+            self.export_panel()
+        } else if self.show_palette {
+            self.palette_panel()
+        } else if self.show_benchmark {
+            self.benchmark_panel()
+        } else {
+            self.bookmarks_panel()
+        };
+        let sidebar = column![preview, panel]
+            .spacing(10)
+            .width(Length::Fixed(240.0))
+            .height(Length::Fill);
+
+        let mut shader: Element<'_, Message> = Element::from(
+            shader(&self.program)
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .map(Message::Viewer);
+        if self.program.grid {
+            shader = Overlay::new(shader, GridLabels::new(&self.program)).into();
+        }
+
+        let mut overlay = column![].spacing(5);
+        if let Some(frame_stats) = &self.program.frame_stats {
+            let mut frame_stats = frame_stats.lock().unwrap();
+            // Without timestamp queries, only the wall-clock frame time is known.
+            let gpu_time = frame_stats.gpu_time.map_or(String::new(), |gpu_time| {
+                format!(", iteration {:.2} ms on GPU", gpu_time * 1000.0)
+            });
+            let fps = frame_stats.fps();
+            let label = format!(
+                "{}\n{}x{} px, {}x{} samples\n{:.1} ms{gpu_time}, {fps:.0} FPS\n{} rendered, {} blitted",
+                self.adapter.as_deref().unwrap_or("Unknown adapter"),
+                frame_stats.resolution.x,
+                frame_stats.resolution.y,
+                frame_stats.aa_samples,
+                frame_stats.aa_samples,
+                frame_stats.frame_time * 1000.0,
+                frame_stats.rendered,
+                frame_stats.blitted
+            );
+            overlay = overlay.push(text(label));
+        }
+        let errors = [
+            self.render_error.clone(),
+            self.shader_error(),
+            self.paste_error.clone(),
+        ];
+        let mut has_errors = false;
+        for error in errors.into_iter().flatten() {
+            overlay = overlay.push(text(error).style(Color::from_rgb(1.0, 0.3, 0.3)));
+            has_errors = true;
+        }
+        if self.program.frame_stats.is_some() || has_errors {
+            shader = Overlay::new(shader, container(overlay).padding(5)).into();
+        }
+
+        let mut panel = column![controls, formula];
+        if let Some(compare) = &self.program.compare {
+            panel = panel.push(Self::compare_controls(compare, config));
+        }
+        let presets = PRESETS.iter().enumerate().fold(
+            row![text("Presets")]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            |presets, (index, (name, ..))| {
+                presets.push(
+                    button(text(format!("{} {name}", index + 1)))
+                        .on_press(Message::LoadPreset(index)),
+                )
+            },
+        );
+        let panel = panel
+            .push(view_fields)
+            .push(presets)
+            .push(self.status_bar())
+            .align_items(Alignment::Center)
+            .spacing(10);
+
+        // The view fills the screen, with the controls drawn over its bottom while shown.
+        if self.fullscreen {
+            if self.controls_hidden {
+                return shader;
+            }
+            let panel = container(panel)
+                .padding(10)
+                .width(Length::Fill)
+                .style(theme::Container::Box);
+            return Overlay::bottom(shader, panel)
+                .on_hover(Message::HoverControls)
+                .into();
+        }
+
+        column![row![shader, sidebar].spacing(10), panel]
+            .align_items(Alignment::Center)
+            .padding(10)
+            .spacing(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        let image = self.image_settings();
+        let shader_changed = matches!(message, Message::ShaderChanged(Ok(_)));
+
+        match message {
+            Message::SetFormula(formula) => {
+                let controls = &mut self.program.controls;
+                *controls = Controls {
+                    max_iter: controls.max_iter,
+                    auto_iter: controls.auto_iter,
+                    escape_radius: controls.escape_radius,
+                    smooth: controls.smooth,
+                    stripe: controls.stripe,
+                    stripe_frequency: controls.stripe_frequency,
+                    histogram: controls.histogram,
+                    coloring: controls.coloring,
+                    line_width: controls.line_width,
+                    trap_point: controls.trap_point,
+                    trap_radius: controls.trap_radius,
+                    interior: controls.interior,
+                    gamma: controls.gamma,
+                    aa_samples: controls.aa_samples,
+                    ..Controls::for_formula(formula)
+                };
+                self.preview.controls.formula = formula;
+                self.auto_zoom = None;
+                self.momentum = None;
+            }
+            Message::UpdateMaxIterations(max_iter) => {
+                self.program.controls.max_iter = max_iter;
+            }
+            Message::ToggleAutoIterations(auto_iter) => {
+                self.program.controls.auto_iter = auto_iter;
+            }
+            Message::UpdateMultibrotPower(power) => {
+                let formula = Formula::Multibrot { power };
+                self.program.controls.formula = formula;
+                self.preview.controls.formula = formula;
+            }
+            Message::UpdateEscapeRadius(escape_radius) => {
+                self.program.controls.escape_radius = escape_radius;
+            }
+            Message::ToggleSmooth(smooth) => {
+                self.program.controls.smooth = smooth;
+            }
+            Message::ToggleStripe(stripe) => {
+                self.program.controls.stripe = stripe;
+            }
+            Message::UpdateStripeFrequency(frequency) => {
+                self.program.controls.stripe_frequency = frequency;
+            }
+            Message::ToggleHistogram(histogram) => {
+                self.program.controls.histogram = histogram;
+            }
+            Message::SetColoringMode(coloring) => {
+                self.program.controls.coloring = coloring;
+                self.preview.controls.coloring = coloring;
+            }
+            Message::SetInteriorMode(interior) => {
+                self.program.controls.interior = interior;
+                self.preview.controls.interior = interior;
+            }
+            Message::UpdateTrapRadius(radius) => {
+                self.program.controls.trap_radius = radius;
+            }
+            Message::UpdateLineWidth(line_width) => {
+                self.program.controls.line_width = line_width;
+            }
+            Message::SetRenderMode(mode) => {
+                self.program.controls.mode = mode;
+            }
+            Message::UpdateExposure(exposure) => {
+                self.program.controls.exposure = exposure;
+            }
+            Message::UpdateGamma(gamma) => {
+                self.program.controls.gamma = gamma;
+            }
+            Message::UpdateColorOffset(offset) => {
+                self.program.controls.color_offset = offset;
+            }
+            Message::UpdateColorFrequency(frequency) => {
+                self.program.controls.color_frequency = frequency;
+            }
+            Message::UpdateColorCycleSpeed(speed) => {
+                self.program.controls.color_cycle_speed = speed;
+            }
+            Message::SetAntialiasing(aa_samples) => {
+                self.program.controls.aa_samples = aa_samples;
+            }
+            Message::SetAspectRatio(aspect) => {
+                self.program.controls.aspect = aspect;
+            }
+            Message::SetTiles(tiles) => {
+                self.program.controls.tiles = tiles;
+            }
+            Message::UpdateZoom(zoom) => {
+                self.auto_zoom = None;
+                self.zoom_target = None;
+                self.program.controls = slider_zoom(
+                    self.program.controls,
+                    zoom,
+                    self.slider_anchor,
+                    &self.program.config,
+                );
+            }
+            Message::UpdateRotation(degrees) => {
+                self.resetting_rotation = false;
+                self.program.controls.rotation = 0.0;
+                self.program.controls.rotate(degrees.to_radians());
+            }
+            Message::ResetRotation => {
+                self.resetting_rotation = true;
+            }
+            Message::Viewer(message) => self.update_viewer(message),
+            Message::EditViewField(field, text) => {
+                let input = self.field_input_mut(field);
+                input.text = text;
+                input.invalid = false;
+                input.editing = true;
+            }
+            Message::SetViewField(field, text) => {
+                let valid = self.set_view_field(field, &text);
+                let input = self.field_input_mut(field);
+                input.invalid = !valid;
+                input.editing = !valid;
+            }
+            Message::UpdateAutoZoomSpeed(speed) => {
+                self.auto_zoom_speed = speed;
+            }
+            Message::ToggleAnimateColors(animate) => {
+                self.animate_colors = animate;
+            }
+            Message::TogglePanMomentum(pan_momentum) => {
+                self.pan_momentum = pan_momentum;
+                self.momentum = None;
+            }
+            Message::ToggleSmoothZoom(smooth_zoom) => {
+                self.smooth_zoom = smooth_zoom;
+            }
+            Message::ToggleSliderAnchor(slider_anchor) => {
+                self.slider_anchor = slider_anchor;
+            }
+            Message::ToggleProgressive(progressive) => {
+                self.program.frame_index = progressive.then_some(0);
+            }
+            Message::ToggleMinimap(minimap) => {
+                self.program.minimap = minimap;
+            }
+            Message::ToggleGrid(grid) => {
+                self.program.grid = grid;
+            }
+            Message::ToggleOrbit(show_orbit) => {
+                self.show_orbit = show_orbit;
+            }
+            Message::ToggleProbe(probe) => {
+                self.program.probe = probe;
+            }
+            Message::ToggleCompare(compare) => {
+                let controls = self.program.controls;
+                self.program.compare = compare.then_some(Controls {
+                    max_iter: controls.iterations(),
+                    auto_iter: false,
+                    ..controls
+                });
+            }
+            Message::UpdateCompareIterations(max_iter) => {
+                if let Some(compare) = &mut self.program.compare {
+                    compare.max_iter = max_iter;
+                }
+            }
+            Message::SetCompareColoring(coloring) => {
+                if let Some(compare) = &mut self.program.compare {
+                    compare.coloring = coloring;
+                }
+            }
+            Message::SetCompareInterior(interior) => {
+                if let Some(compare) = &mut self.program.compare {
+                    compare.interior = interior;
+                }
+            }
+            Message::ToggleCompareSmooth(smooth) => {
+                if let Some(compare) = &mut self.program.compare {
+                    compare.smooth = smooth;
+                }
+            }
+            Message::ToggleRenderInBackground(render_in_background) => {
+                self.render_in_background = render_in_background;
+            }
+            Message::ToggleLightTheme(light_theme) => {
+                self.light_theme = light_theme;
+            }
+            Message::ToggleFps(show) => {
+                self.program.frame_stats = show.then(Default::default);
+            }
+            Message::AdapterInfo(adapter) => {
+                self.adapter = Some(adapter);
+            }
+            Message::RenderError(error) => {
+                self.render_error = error;
+            }
+            Message::SaveState => self.save_state(),
+            Message::ResetToDefaults => {
+                self.apply_session(Session::default());
+                if let Err(err) = Session::clear() {
+                    eprintln!("Failed to clear saved state: {err}");
+                }
+            }
+            Message::CopyView => {
+                return clipboard::write(self.program.controls.view_string());
+            }
+            Message::PasteView => return clipboard::read(Message::PastedView),
+            Message::CopyCoords => return clipboard::write(view_args(&self.program.controls)),
+            Message::PastedView(view) => {
+                let view = view.unwrap_or_default();
+                let mut controls = self.program.controls;
+                self.paste_error = match controls.apply_view_string(&view) {
+                    Ok(()) => {
+                        self.jump_to(controls);
+                        None
+                    }
+                    Err(err) => Some(format!("Ignoring pasted view: {err}")),
+                };
+            }
+            Message::EditBookmarkName(name) => {
+                self.bookmark_name = name;
+            }
+            Message::AddBookmark => {
+                let name = match self.bookmark_name.trim() {
+                    "" => format!("Bookmark {}", self.bookmarks.len() + 1),
+                    name => name.to_owned(),
+                };
+                self.bookmarks.push(Bookmark {
+                    name,
+                    controls: self.program.controls,
+                    palette: Some(self.program.palette.clone()),
+                });
+                self.bookmark_name.clear();
+                self.save_bookmarks();
+            }
+            Message::LoadPreset(index) => {
+                if let Some(controls) = preset(self.program.controls, index) {
+                    self.jump_to(controls);
+                }
+            }
+            Message::GoToBookmark(index) => {
+                if let Some(bookmark) = self.bookmarks.get(index).cloned() {
+                    self.jump_to(bookmark.controls);
+                    if let Some(palette) = bookmark.palette {
+                        self.set_palette(palette);
+                    }
+                }
+            }
+            Message::DeleteBookmark(index) => {
+                if index < self.bookmarks.len() {
+                    self.bookmarks.remove(index);
+                    self.save_bookmarks();
+                    // Bookmark indices past the deleted one shifted.
+                    if matches!(self.export.end, ExportEnd::Bookmark { index: end, .. } if end >= index)
+                    {
+                        self.export.end = ExportEnd::ZoomIn;
+                    }
+                }
+            }
+            Message::ShowExport(show_export) => {
+                self.show_export = show_export;
+                self.show_palette = false;
+                self.show_benchmark = false;
+            }
+            Message::ShowPalette(show_palette) => {
+                self.show_palette = show_palette;
+                self.show_export = false;
+                self.show_benchmark = false;
+            }
+            Message::AddColorStop => {
+                let mut palette = self.program.palette.clone();
+                palette.add_stop();
+                self.set_palette(palette);
+            }
+            Message::RemoveColorStop(index) => {
+                let mut palette = self.program.palette.clone();
+                palette.remove_stop(index);
+                self.set_palette(palette);
+            }
+            Message::MoveColorStop(index, position) => {
+                self.program.palette.set_position(index, position);
+            }
+            Message::EditColorStop(index, hex) => {
+                if let Ok(color) = parse_hex_color(&hex) {
+                    self.program.palette.set_color(index, color);
+                }
+                if let Some(input) = self.color_inputs.get_mut(index) {
+                    *input = hex;
+                }
+            }
+            Message::SetExportEnd(end) => {
+                self.export.end = end;
+            }
+            Message::UpdateExportZoomLevels(zoom_levels) => {
+                self.export.zoom_levels = zoom_levels;
+            }
+            Message::EditExportFrames(frames) => {
+                self.export.frames = frames;
+            }
+            Message::ToggleExportIterations(ramp_iterations) => {
+                self.export.ramp_iterations = ramp_iterations;
+            }
+            Message::EditExportSize(size) => {
+                self.export.size = size;
+            }
+            Message::EditExportDirectory(directory) => {
+                self.export.directory = directory;
+            }
+            Message::ExportSequence => {
+                if self.export_job.is_none() {
+                    match self.export.sequence(
+                        self.program.controls,
+                        &self.program.palette,
+                        &self.bookmarks,
+                    ) {
+                        Ok(sequence) => {
+                            self.export.status = None;
+                            self.export_job = Some(ExportJob {
+                                id: NEXT_EXPORT_ID.fetch_add(1, Ordering::Relaxed),
+                                sequence: Arc::new(sequence),
+                                rendered: 0,
+                                cancel: Arc::default(),
+                            });
+                        }
+                        Err(err) => self.export.status = Some(err),
+                    }
+                }
+            }
+            Message::CancelExport => {
+                if let Some(job) = self.export_job.take() {
+                    job.cancel.store(true, Ordering::Relaxed);
+                    self.export.status = Some(format!(
+                        "Cancelled after {} of {} frames",
+                        job.rendered, job.sequence.frames
+                    ));
+                }
+            }
+            Message::ExportProgress(event) => match event {
+                ExportEvent::Rendered(rendered) => {
+                    if let Some(job) = &mut self.export_job {
+                        job.rendered = rendered;
+                    }
+                }
+                ExportEvent::Finished => {
+                    if let Some(job) = self.export_job.take() {
+                        self.export.status = Some(format!(
+                            "Wrote {} frames to {}",
+                            job.rendered,
+                            job.sequence.directory.display()
+                        ));
+                    }
+                }
+                ExportEvent::Failed(err) => {
+                    self.export_job = None;
+                    self.export.status = Some(format!("Export failed: {err}"));
+                }
+            },
+            Message::ShowBenchmark(show_benchmark) => {
+                self.show_benchmark = show_benchmark;
+                self.show_export = false;
+                self.show_palette = false;
+            }
+            Message::EditBenchmarkCsv(csv) => {
+                self.benchmark.csv = csv;
+            }
+            Message::RunBenchmark => {
+                if self.benchmark_job.is_none() {
+                    let csv = self.benchmark.csv.trim();
+                    self.benchmark.results.clear();
+                    self.benchmark.adapter = None;
+                    self.benchmark.status = None;
+                    self.benchmark_job = Some(BenchmarkJob {
+                        id: NEXT_BENCHMARK_ID.fetch_add(1, Ordering::Relaxed),
+                        controls: self.program.controls,
+                        csv: (!csv.is_empty()).then(|| PathBuf::from(csv)),
+                        cancel: Arc::default(),
+                    });
+                }
+            }
+            Message::CancelBenchmark => {
+                if let Some(job) = self.benchmark_job.take() {
+                    job.cancel.store(true, Ordering::Relaxed);
+                    self.benchmark.status = Some("Cancelled".into());
+                }
+            }
+            Message::BenchmarkProgress(event) => match event {
+                BenchmarkEvent::Started {
+                    adapter,
+                    gpu_timestamps,
+                } => {
+                    let timing = if gpu_timestamps {
+                        "GPU timestamps"
+                    } else {
+                        "CPU fences"
+                    };
+                    self.benchmark.adapter = Some(format!("{adapter}, timed with {timing}"));
+                }
+                BenchmarkEvent::Measured(result) => {
+                    if self.benchmark_job.is_some() {
+                        self.benchmark.results.push(result);
+                    }
+                }
+                BenchmarkEvent::Finished => {
+                    if let Some(job) = self.benchmark_job.take() {
+                        self.benchmark.status = job
+                            .csv
+                            .map(|path| format!("Wrote results to {}", path.display()));
+                    }
+                }
+                BenchmarkEvent::Failed(err) => {
+                    self.benchmark_job = None;
+                    self.benchmark.status = Some(format!("Benchmark failed: {err}"));
+                }
+            },
+            Message::ShaderChanged(Ok(code)) => {
+                self.shader_read_error = None;
+                self.program.set_shader(code);
+            }
+            Message::ShaderChanged(Err(err)) => {
+                self.shader_read_error = Some(err);
+            }
+            Message::CloseRequested(id) => {
+                self.save_state();
+                return window::close(id);
+            }
+            Message::ToggleFullscreen => {
+                self.fullscreen = !self.fullscreen;
+                self.controls_hidden = false;
+                self.hovering_controls = false;
+                self.last_activity = Instant::now();
+                let mode = if self.fullscreen {
+                    window::Mode::Fullscreen
+                } else {
+                    window::Mode::Windowed
+                };
+                return window::change_mode(window::Id::MAIN, mode);
+            }
+            Message::ExitFullscreen => {
+                if self.fullscreen {
+                    return self.update(Message::ToggleFullscreen);
+                }
+            }
+            Message::FullscreenCursor(y) => {
+                if y >= self.window_height - CONTROLS_REVEAL_EDGE {
+                    return self.update(Message::Activity);
+                }
+            }
+            Message::Activity => {
+                self.last_activity = Instant::now();
+                self.controls_hidden = false;
+            }
+            Message::HoverControls(hovering) => {
+                if hovering || self.hovering_controls {
+                    self.last_activity = Instant::now();
+                }
+                self.hovering_controls = hovering;
+            }
+            Message::WindowResized(height) => {
+                // Minimized windows are resized to nothing.
+                self.window_minimized = height == 0.0;
+                if !self.window_minimized {
+                    self.window_height = height;
+                }
+            }
+            Message::WindowFocused(focused) => {
+                self.window_focused = focused;
+            }
+            Message::HideControls => {
+                if self.fullscreen
+                    && !self.hovering_controls
+                    && self.last_activity.elapsed() >= CONTROLS_HIDE_DELAY
+                {
+                    self.controls_hidden = true;
+                }
+            }
+            Message::Tick => {
+                if self
+                    .last_interaction
+                    .is_some_and(|last| last.elapsed() >= INTERACTION_SETTLE_TIME)
+                {
+                    self.last_interaction = None;
+                    self.program.interacting = false;
+                }
+
+                // The next sample waits for the tiles of this one.
+                let tiling = self.program.tile_progress().pending();
+                if let Some(frame_index) = &mut self.program.frame_index {
+                    *frame_index = if self.animate_colors
+                        || self.auto_zoom.is_some()
+                        || self.program.interacting
+                    {
+                        0
+                    } else if tiling {
+                        *frame_index
+                    } else {
+                        (*frame_index + 1).min(ACCUMULATION_FRAMES)
+                    };
+                }
+
+                // Moving the offset itself, rather than the animation time, keeps the colors
+                // where they are when the cycling stops or its speed changes.
+                if self.animate_colors {
+                    self.program.elapsed = self.start.elapsed().as_secs_f32();
+                    let controls = &mut self.program.controls;
+                    controls.color_offset = (controls.color_offset
+                        + controls.color_cycle_speed * TICK_INTERVAL.as_secs_f32())
+                    .rem_euclid(1.0);
+                }
+
+                if let Some((velocity, bounds)) = &mut self.momentum {
+                    let bounds = *bounds;
+                    let dt = TICK_INTERVAL.as_secs_f32();
+                    let delta = *velocity * dt;
+                    *velocity *= (-MOMENTUM_FRICTION * dt).exp();
+                    if velocity.length() < MOMENTUM_MIN_SPEED {
+                        self.momentum = None;
+                    }
+
+                    self.start_interaction();
+                    self.program.controls.pan(delta, bounds);
+                }
+
+                if let Some((point, target)) = self.zoom_target {
+                    self.start_interaction();
+                    if zoom_towards(
+                        &mut self.program.controls,
+                        point,
+                        target,
+                        &self.program.config,
+                    ) {
+                        self.zoom_target = None;
+                    }
+                }
+
+                if self.resetting_rotation {
+                    self.start_interaction();
+                    let controls = &mut self.program.controls;
+                    controls.rotation *= 1.0 - ROTATION_RESET_SMOOTHING;
+                    if controls.rotation.abs() < ROTATION_RESET_PRECISION {
+                        controls.rotation = 0.0;
+                        self.resetting_rotation = false;
+                    }
+                }
+
+                if let Some((center, zoom)) = self.recenter {
+                    self.start_interaction();
+                    let controls = &mut self.program.controls;
+                    controls.center += (center - controls.center) * RECENTER_SMOOTHING;
+                    controls.zoom += (zoom - controls.zoom) * RECENTER_SMOOTHING as f32;
+
+                    let offset = (center - controls.center).length() / controls.extent();
+                    if offset < RECENTER_PRECISION && (zoom - controls.zoom).abs() < 0.01 {
+                        controls.center = center;
+                        controls.zoom = zoom;
+                        self.recenter = None;
+                    }
+                }
+
+                if let Some(target) = self.auto_zoom {
+                    let controls = &mut self.program.controls;
+                    let delta = self.auto_zoom_speed * TICK_INTERVAL.as_secs_f32();
+                    controls.zoom_around(target, controls.zoom + delta, &self.program.config);
+                    controls.center += (target - controls.center) * AUTO_ZOOM_CENTERING;
+
+                    if controls.zoom >= *self.program.config.zoom_range().end() {
+                        self.auto_zoom = None;
+                    }
+                }
+            }
+        }
+
+        self.program.controls.sanitize(&self.program.config);
+        // Progressive rendering only starts over when the image changes, not when a slider or
+        // a pan leaves the view as it was, or a click moves the zoom anchor. Settled samples
+        // are only blitted, so anything else drawn into them has to restart it as well.
+        if self.image_settings() != image || shader_changed {
+            if let Some(frame_index) = &mut self.program.frame_index {
+                *frame_index = 0;
+            }
+        }
+        self.program.update_reference_orbit();
+        self.program.orbit_point = self.hovered.filter(|_| self.show_orbit);
+        self.sync_view_fields();
+
+        Command::none()
+    }
+
+    fn theme(&self) -> Theme {
+        if self.light_theme {
+            Theme::Light
+        } else {
+            Theme::Dark
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let accumulating = self
+            .program
+            .frame_index
+            .is_some_and(|frame_index| frame_index < ACCUMULATION_FRAMES);
+
+        // Paused animations and accumulation resume where they were, ticks advance them by a
+        // fixed step.
+        let ticks = if !self.window_active() {
+            Subscription::none()
+        } else if self.auto_zoom.is_some()
+            || self.momentum.is_some()
+            || self.recenter.is_some()
+            || self.zoom_target.is_some()
+            || self.resetting_rotation
+            || self.animate_colors
+            || accumulating
+            || self.last_interaction.is_some()
+            || self.shader_pending()
+            || self.program.inspecting()
+            || self.program.accumulating_buddhabrot()
+            || self.program.tile_progress().pending()
+        {
+            time::every(TICK_INTERVAL).map(|_| Message::Tick)
+        } else if self.program.frame_stats.is_some() {
+            // Slow enough for an idle window to show (almost) no redraws.
+            time::every(STATS_REFRESH_INTERVAL).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        };
+
+        let window_events = event::listen_with(|event, _status| match event {
+            iced::Event::Window(id, window::Event::CloseRequested) => {
+                Some(Message::CloseRequested(id))
+            }
+            iced::Event::Window(_, window::Event::Resized { height, .. }) => {
+                Some(Message::WindowResized(height as f32))
+            }
+            iced::Event::Window(_, window::Event::Focused) => Some(Message::WindowFocused(true)),
+            iced::Event::Window(_, window::Event::Unfocused) => Some(Message::WindowFocused(false)),
+            _ => None,
+        });
+
+        let shader_changes = match &self.watch_shader {
+            Some(path) => watch_file(path.clone()),
+            None => Subscription::none(),
+        };
+
+        let export = match &self.export_job {
+            Some(job) => export_progress(job),
+            None => Subscription::none(),
+        };
+
+        let benchmark = match &self.benchmark_job {
+            Some(job) => benchmark_progress(job),
+            None => Subscription::none(),
+        };
+
+        let fullscreen_toggles = keyboard::on_key_press(|key, _modifiers| match key {
+            keyboard::Key::Named(keyboard::key::Named::F11) => Some(Message::ToggleFullscreen),
+            keyboard::Key::Named(keyboard::key::Named::Escape) => Some(Message::ExitFullscreen),
+            _ => None,
+        });
+
+        // Keys typed into text inputs are captured before reaching this.
+        let preset_keys = keyboard::on_key_press(preset_key);
+
+        let cursor_activity = if self.fullscreen {
+            let moves = event::listen_with(|event, _status| match event {
+                iced::Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                    Some(Message::FullscreenCursor(position.y))
+                }
+                _ => None,
+            });
+            let hiding = if self.controls_hidden {
+                Subscription::none()
+            } else {
+                time::every(CONTROLS_HIDE_DELAY / 4).map(|_| Message::HideControls)
+            };
+            Subscription::batch([moves, hiding])
+        } else {
+            Subscription::none()
+        };
+
+        // Any key stops gliding, like a press or the wheel do.
+        let key_presses = if self.momentum.is_some() {
+            keyboard::on_key_press(|_, _| Some(Message::Viewer(ViewerMessage::StopMomentum)))
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([
+            ticks,
+            window_events,
+            shader_changes,
+            export,
+            benchmark,
+            fullscreen_toggles,
+            preset_keys,
+            cursor_activity,
+            key_presses,
+            render_errors(self.program.errors.clone()).map(Message::RenderError),
+        ])
+    }
+}
+
+/// Renders `controls` into a PNG file without iced.
+fn render_png(controls: Controls, size: Size<u32>, path: &std::path::Path) -> Result<(), String> {
+    HeadlessRenderer::new(size)?.render_png(controls, path)
+}
+
+/// Views timed by `--benchmark`, from cheap to expensive, with fixed iteration counts so that
+/// results stay comparable across versions.
+const BENCHMARK_VIEWS: [(&str, DVec2, f32, u32); 3] = [
+    ("shallow", CENTER_DEFAULT, ZOOM_DEFAULT, 500),
+    ("medium", DVec2::new(-0.743643887, 0.131825904), 12.0, 2000),
+    (
+        "deep",
+        DVec2::new(-0.743643887037151, 0.13182590420533),
+        36.0,
+        5000,
+    ),
+];
+const BENCHMARK_SIZE: Size<u32> = Size::new(1280, 720);
+/// Frames rendered before timing starts, so that buffer allocation isn't measured.
+const BENCHMARK_WARMUP_FRAMES: u32 = 3;
+const BENCHMARK_FRAMES: u32 = 20;
+
+/// Renders each of [`BENCHMARK_VIEWS`] from scratch repeatedly and prints the frame times.
+fn run_benchmark() -> Result<(), String> {
+    let mut renderer = HeadlessRenderer::new(BENCHMARK_SIZE)?;
+    println!(
+        "Adapter: {} ({:?})",
+        renderer.adapter.name, renderer.adapter.backend
+    );
+    if renderer.gpu_timestamps() {
+        println!("Timing: GPU timestamps");
+    } else {
+        println!("Timing: wall clock, the adapter doesn't support TIMESTAMP_QUERY");
+    }
+    println!(
+        "{}x{}, {BENCHMARK_FRAMES} frames per view",
+        BENCHMARK_SIZE.width, BENCHMARK_SIZE.height
+    );
+
+    for (name, center, zoom, max_iter) in BENCHMARK_VIEWS {
+        let controls = Controls {
+            center,
+            zoom,
+            max_iter,
+            auto_iter: false,
+            ..Controls::default()
+        };
+        let result = time_frames(&mut renderer, controls)?;
+        println!(
+            "{name:<8} {:>9.2} ms mean {:>9.2} ms min  ({max_iter} iterations)",
+            result.mean * 1000.0,
+            result.min * 1000.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Iteration counts "Run benchmark" renders the current view with.
+const BENCHMARK_SWEEP: [u32; 4] = [100, 500, 1000, 5000];
+
+/// Frame times of a view at an iteration count, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BenchmarkResult {
+    max_iter: u32,
+    mean: f32,
+    min: f32,
+}
+
+/// Renders `controls` from scratch [`BENCHMARK_FRAMES`] times after the warmup frames and
+/// times them.
+fn time_frames(
+    renderer: &mut HeadlessRenderer,
+    controls: Controls,
+) -> Result<BenchmarkResult, String> {
+    let mut times = Vec::new();
+    for frame in 0..BENCHMARK_WARMUP_FRAMES + BENCHMARK_FRAMES {
+        renderer.invalidate();
+        let time = renderer.draw(controls)?;
+        if frame >= BENCHMARK_WARMUP_FRAMES {
+            times.push(time);
+        }
+    }
+
+    Ok(BenchmarkResult {
+        max_iter: controls.max_iter,
+        mean: times.iter().sum::<f32>() / times.len() as f32,
+        min: times.iter().copied().fold(f32::INFINITY, f32::min),
+    })
+}
+
+/// `results` as CSV, with times in milliseconds.
+fn benchmark_csv(results: &[BenchmarkResult]) -> String {
+    let mut csv = String::from("iterations,mean_ms,min_ms\n");
+    for result in results {
+        csv += &format!(
+            "{},{:.3},{:.3}\n",
+            result.max_iter,
+            result.mean * 1000.0,
+            result.min * 1000.0
+        );
+    }
+    csv
+}
+
+/// Progress of [`benchmark_sweep`].
+#[derive(Debug, Clone)]
+enum BenchmarkEvent {
+    /// Adapter the frames are rendered with, and whether they are timed with GPU timestamps.
+    Started {
+        adapter: String,
+        gpu_timestamps: bool,
+    },
+    Measured(BenchmarkResult),
+    Finished,
+    Failed(String),
+}
+
+/// Times `controls` offscreen at `size` with each of `iterations`, writing the results to
+/// `csv` if given.
+fn benchmark_sweep(
+    controls: Controls,
+    size: Size<u32>,
+    iterations: &[u32],
+    csv: Option<&std::path::Path>,
+    cancel: &AtomicBool,
+    report: impl Fn(BenchmarkEvent),
+) {
+    let result = (|| {
+        let mut renderer = HeadlessRenderer::new(size)?;
+        report(BenchmarkEvent::Started {
+            adapter: format!("{} ({:?})", renderer.adapter.name, renderer.adapter.backend),
+            gpu_timestamps: renderer.gpu_timestamps(),
+        });
+
+        let mut results = Vec::new();
+        for &max_iter in iterations {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let controls = Controls {
+                max_iter,
+                auto_iter: false,
+                mode: RenderMode::EscapeTime,
+                ..controls
+            };
+            let result = time_frames(&mut renderer, controls)?;
+            results.push(result);
+            report(BenchmarkEvent::Measured(result));
+        }
+
+        if let Some(path) = csv {
+            std::fs::write(path, benchmark_csv(&results))
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+        }
+        Ok(())
+    })();
+
+    report(match result {
+        Ok(()) => BenchmarkEvent::Finished,
+        Err(err) => BenchmarkEvent::Failed(err),
+    });
+}
+
+/// Zoom animation between two views, see [`Sequence::frame`].
+#[derive(Debug, Clone)]
+struct Sequence {
+    start: Controls,
+    end: Controls,
+    frames: u32,
+    /// Whether iterations ramp up with the zoom, see [`Sequence::frame`].
+    ramp_iterations: bool,
+    palette: Palette,
+    size: Size<u32>,
+    /// Directory the frames are written into as `frame_0001.png` etc.
+    directory: PathBuf,
+}
+
+/// Progress of a [`Sequence`] export, reported by [`export_sequence`].
+#[derive(Debug, Clone)]
+enum ExportEvent {
+    /// Number of frames written so far.
+    Rendered(u32),
+    Finished,
+    Failed(String),
+}
+
+impl Sequence {
+    /// View of frame `index`. Zoom changes linearly, so the magnification grows at a constant
+    /// rate, and the center moves in step with the scale so the end view doesn't drift out of
+    /// the frame.
+    fn frame(&self, index: u32) -> Controls {
+        let t = if self.frames > 1 {
+            index as f64 / (self.frames - 1) as f64
+        } else {
+            0.0
+        };
+
+        let zoom = self.start.zoom + (self.end.zoom - self.start.zoom) * t as f32;
+        let controls = if self.ramp_iterations {
+            Controls {
+                zoom,
+                auto_iter: true,
+                ..self.start
+            }
+        } else {
+            Controls {
+                zoom,
+                max_iter: self.start.iterations(),
+                auto_iter: false,
+                ..self.start
+            }
+        };
+
+        let start_extent = self.start.extent();
+        let end_extent = self.end.extent();
+        let remaining = if start_extent != end_extent {
+            (controls.extent() - end_extent) / (start_extent - end_extent)
+        } else {
+            1.0 - t
+        };
+
+        Controls {
+            center: self.end.center + (self.start.center - self.end.center) * remaining,
+            ..controls
+        }
+    }
+}
+
+/// Renders the frames of `sequence` until done or `cancel` is set, reporting to `report`.
+fn export_sequence(sequence: &Sequence, cancel: &AtomicBool, report: impl Fn(ExportEvent)) {
+    let result = (|| {
+        std::fs::create_dir_all(&sequence.directory).map_err(|err| err.to_string())?;
+        let mut renderer = HeadlessRenderer::new(sequence.size)?;
+        renderer.set_palette(sequence.palette.clone());
+        for index in 0..sequence.frames {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let path = sequence
+                .directory
+                .join(format!("frame_{:04}.png", index + 1));
+            renderer
+                .render_png(sequence.frame(index), &path)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            report(ExportEvent::Rendered(index + 1));
+        }
+        Ok(())
+    })();
+
+    report(match result {
+        Ok(()) => ExportEvent::Finished,
+        Err(err) => ExportEvent::Failed(err),
+    });
+}
+
+fn main() -> iced::Result {
+    let args = Args::parse();
+
+    if args.benchmark {
+        if let Err(err) = run_benchmark() {
+            eprintln!("Benchmark failed: {err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &args.render {
+        let mut controls = Controls::default();
+        args.apply(&mut controls);
+        let size = args.window_size(window::Settings::default().size);
+        let size = Size::new(size.width as u32, size.height as u32);
+        if let Err(err) = render_png(controls, size, path) {
+            eprintln!("Failed to render {}: {err}", path.display());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Command line arguments take precedence over the saved state.
+    let mut session = if args.fresh {
+        Session::default()
+    } else {
+        Session::load().unwrap_or_default()
+    };
+    args.apply(&mut session.controls);
+
+    let shader = match &args.watch_shader {
+        Some(path) => {
+            let read = std::fs::canonicalize(path)
+                .and_then(|path| Ok((std::fs::read_to_string(&path)?, path)));
+            match read {
+                Ok((code, path)) => Some((path, code)),
+                Err(err) => {
+                    eprintln!("Failed to read {}: {err}", path.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let window = window::Settings::default();
+    let size = args.window_size(window.size);
+
+    FragmentShaderApp::run(Settings {
+        window: window::Settings {
+            size,
+            // The state is saved before closing the window, see `Message::CloseRequested`.
+            exit_on_close_request: false,
+            ..window
+        },
+        ..Settings::with_flags(Flags { session, shader })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_file_loads_older_controls() {
+        let json = r#"{
+            "version": 1,
+            "bookmarks": [{ "name": "Seahorse", "controls": { "zoom": 12.5 } }]
+        }"#;
+        let file: BookmarksFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(file.bookmarks.len(), 1);
+        assert_eq!(file.bookmarks[0].name, "Seahorse");
+        assert_eq!(file.bookmarks[0].controls.zoom, 12.5);
+        assert_eq!(
+            file.bookmarks[0].controls.max_iter,
+            Controls::default().max_iter
+        );
+    }
+
+    #[test]
+    fn settled_accumulation_restarts_when_the_image_changes() {
+        let (mut app, _) = FragmentShaderApp::new(Flags {
+            session: Session {
+                progressive: true,
+                ..Session::default()
+            },
+            shader: None,
+        });
+        let mut restarts = |message| {
+            app.program.frame_index = Some(ACCUMULATION_FRAMES);
+            let _ = app.update(message);
+            app.program.frame_index == Some(0)
+        };
+
+        assert!(restarts(Message::EditColorStop(0, "#ff0000".into())));
+        restarts(Message::AddColorStop);
+        assert!(restarts(Message::MoveColorStop(1, 0.3)));
+        assert!(restarts(Message::RemoveColorStop(1)));
+        assert!(restarts(Message::ToggleGrid(true)));
+        // Already shown.
+        assert!(!restarts(Message::ToggleGrid(true)));
+        assert!(restarts(Message::ToggleCompare(true)));
+        assert!(restarts(Message::UpdateCompareIterations(50)));
+        assert!(restarts(Message::SetCompareColoring(
+            ColoringMode::Distance
+        )));
+        assert!(restarts(Message::ToggleCompareSmooth(true)));
+        assert!(restarts(Message::SetCompareInterior(
+            InteriorMode::Magnitude
+        )));
+        assert!(restarts(Message::Viewer(ViewerMessage::MoveSplit(0.3))));
+        // Not drawn.
+        assert!(!restarts(Message::Viewer(ViewerMessage::Clicked(
+            DVec2::ONE
+        ))));
+    }
+
+    #[test]
+    fn slider_zoom_keeps_the_clicked_point_in_place() {
+        let height = 600.0;
+        let anchor = DVec2::new(-0.7, 0.3);
+        let controls = Controls {
+            rotation: 0.5,
+            zoom_anchor: Some(anchor),
+            ..Controls::default()
+        };
+        // Pixels from the view center to `point`.
+        let on_screen = |controls: &Controls, point: DVec2| {
+            controls.screen_offset(point - controls.center) / controls.scale(height)
+        };
+
+        for zoom in [controls.zoom + 2.5, controls.zoom - 0.5] {
+            let zoomed = slider_zoom(controls, zoom, true, &Config::default());
+            assert_eq!(zoomed.zoom, zoom);
+            let drift = on_screen(&zoomed, anchor) - on_screen(&controls, anchor);
+            assert!(drift.length() < 1e-6, "drifted by {drift}");
+            // Zooming in moves the center towards the anchor, zooming out away from it.
+            let closer = zoomed.center.distance(anchor) < controls.center.distance(anchor);
+            assert_eq!(closer, zoom > controls.zoom);
+        }
+
+        // Around the center without the option or a click.
+        let zoom = controls.zoom + 1.0;
+        assert_eq!(
+            slider_zoom(controls, zoom, false, &Config::default()).center,
+            controls.center
+        );
+        let unclicked = Controls {
+            zoom_anchor: None,
+            ..controls
+        };
+        assert_eq!(
+            slider_zoom(unclicked, zoom, true, &Config::default()).center,
+            controls.center
+        );
+    }
+
+    #[test]
+    fn smooth_zoom_keeps_the_anchor_in_place() {
+        let mut controls = Controls {
+            rotation: 0.5,
+            ..Controls::default()
+        };
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(800.0, 600.0));
+        let pos = Vec2::new(600.0, 150.0);
+        let point = controls.fractal_point(pos, bounds);
+        let target = wheel_zoom(controls.zoom, 3.0);
+
+        let ticks = (1..100)
+            .find(|_| {
+                let reached = zoom_towards(&mut controls, point, target, &Config::default());
+                let drift = (controls.fractal_point(pos, bounds) - point).length();
+                assert!(
+                    drift < controls.scale(bounds.height) * 1e-3,
+                    "drifted by {drift}"
+                );
+                reached
+            })
+            .unwrap();
+        assert_eq!(controls.zoom, target);
+        // Mostly there after 150 ms, the rest is settled soon after.
+        assert!(
+            (ticks as u32 * TICK_INTERVAL).as_millis() < 500,
+            "{ticks} ticks"
+        );
+    }
+
+    #[test]
+    fn invalid_pasted_views_are_shown_until_one_applies() {
+        let (mut app, _) = FragmentShaderApp::new(Flags {
+            session: Session::default(),
+            shader: None,
+        });
+        let controls = Controls {
+            zoom: 7.5,
+            ..Controls::default()
+        };
+
+        let _ = app.update(Message::PastedView(Some("not a view".to_owned())));
+        assert!(app.paste_error.is_some());
+        assert_eq!(app.program.controls.zoom, Controls::default().zoom);
+
+        let _ = app.update(Message::PastedView(Some(controls.view_string())));
+        assert_eq!(app.paste_error, None);
+        assert_eq!(app.program.controls.zoom, 7.5);
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#ff8000"), Ok([255, 128, 0]));
+        assert_eq!(parse_hex_color(" 0a0B0c "), Ok([10, 11, 12]));
+        assert!(parse_hex_color("#ff80").is_err());
+        assert!(parse_hex_color("#gg0000").is_err());
+        assert!(parse_hex_color("#ff800é").is_err());
+    }
+
+    #[test]
+    fn session_falls_back_on_unusable_files() {
+        let session = Session {
+            animate_colors: true,
+            ..Session::default()
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        assert!(Session::parse(&json).unwrap().animate_colors);
+
+        assert!(Session::parse("{ \"version\": 2 }").is_none());
+        assert!(Session::parse("{ \"zoom\": 5.0 }").is_none());
+        assert!(Session::parse("not json").is_none());
+    }
+
+    #[test]
+    fn sequence_zooms_at_constant_speed() {
+        let start = Controls::default();
+        let end = Controls {
+            center: DVec2::new(-0.743643887, 0.131825904),
+            zoom: 20.0,
+            ..start
+        };
+        let sequence = Sequence {
+            start,
+            end,
+            frames: 11,
+            ramp_iterations: true,
+            palette: Palette::default(),
+            size: Size::new(64, 48),
+            directory: PathBuf::new(),
+        };
+
+        let first = sequence.frame(0);
+        let last = sequence.frame(10);
+        assert_eq!(first.zoom, start.zoom);
+        assert_eq!(first.center, start.center);
+        assert_eq!(last.zoom, end.zoom);
+        assert!(last.center.distance(end.center) < 1e-12);
+
+        // Equal steps in zoom, so a constant magnification rate.
+        let expected_step = (end.zoom - start.zoom) / 10.0;
+        for index in 1..10 {
+            let step = sequence.frame(index + 1).zoom - sequence.frame(index).zoom;
+            assert!((step - expected_step).abs() < 1e-4);
+            assert!(sequence.frame(index).auto_iter);
+        }
+
+        // The end center stays within the frame, at a shrinking distance from its middle.
+        let mut offset = f64::INFINITY;
+        for index in 0..10 {
+            let controls = sequence.frame(index);
+            let heights = (end.center - controls.center).length() / controls.extent();
+            assert!(heights <= offset);
+            offset = heights;
+        }
+
+        // Without ramping, every frame keeps the start's iteration count.
+        let fixed = Sequence {
+            ramp_iterations: false,
+            ..sequence
+        };
+        for index in 0..11 {
+            assert_eq!(fixed.frame(index).iterations(), start.iterations());
+        }
+    }
+
+    #[test]
+    fn presets_are_mandelbrot_views_keeping_the_coloring() {
+        let julia = Controls {
+            julia: Some(Vec2::new(-0.8, 0.156)),
+            formula: Formula::BurningShip,
+            rotation: 1.0,
+            auto_iter: true,
+            gamma: 1.5,
+            ..Controls::default()
+        };
+
+        let seahorse = preset(julia, 0).unwrap();
+        assert_eq!(seahorse.center, PRESETS[0].1);
+        assert_eq!(seahorse.zoom, PRESETS[0].2);
+        assert_eq!(seahorse.iterations(), PRESETS[0].3);
+        assert_eq!(seahorse.formula, Formula::Mandelbrot);
+        assert_eq!(seahorse.julia, None);
+        assert_eq!(seahorse.rotation, 0.0);
+        assert_eq!(seahorse.gamma, 1.5);
+        assert!(preset(julia, PRESETS.len()).is_none());
+    }
+
+    #[test]
+    fn preset_keys_skip_other_shortcuts() {
+        let digit = |digit: &str| keyboard::Key::Character(digit.into());
+        assert!(matches!(
+            preset_key(digit("2"), keyboard::Modifiers::default()),
+            Some(Message::LoadPreset(1))
+        ));
+        assert!(preset_key(digit("5"), keyboard::Modifiers::default()).is_none());
+        for modifiers in [
+            keyboard::Modifiers::CTRL,
+            keyboard::Modifiers::ALT,
+            keyboard::Modifiers::LOGO,
+        ] {
+            assert!(preset_key(digit("1"), modifiers).is_none());
+        }
+    }
+
+    #[test]
+    fn benchmark_csv_lists_times_in_milliseconds() {
+        let results = [
+            BenchmarkResult {
+                max_iter: 100,
+                mean: 0.0025,
+                min: 0.002,
+            },
+            BenchmarkResult {
+                max_iter: 5000,
+                mean: 0.125,
+                min: 0.1,
+            },
+        ];
+
+        assert_eq!(
+            benchmark_csv(&results),
+            "iterations,mean_ms,min_ms\n100,2.500,2.000\n5000,125.000,100.000\n"
+        );
+    }
+
+    #[test]
+    fn benchmark_sweep_measures_each_iteration_count() {
+        let csv = std::env::temp_dir().join(format!("benchmark-{}.csv", std::process::id()));
+        let events = std::sync::Mutex::new(Vec::new());
+        benchmark_sweep(
+            Controls::default(),
+            Size::new(32, 32),
+            &[10, 20],
+            Some(&csv),
+            &AtomicBool::new(false),
+            |event| events.lock().unwrap().push(event),
+        );
+
+        let events = events.into_inner().unwrap();
+        if let [BenchmarkEvent::Failed(_)] = events.as_slice() {
+            // No adapter.
+            return;
+        }
+        let measured: Vec<u32> = events
+            .iter()
+            .filter_map(|event| match event {
+                BenchmarkEvent::Measured(result) => Some(result.max_iter),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(measured, [10, 20]);
+        assert!(matches!(events.last(), Some(BenchmarkEvent::Finished)));
+
+        let written = std::fs::read_to_string(&csv).unwrap();
+        std::fs::remove_file(&csv).unwrap();
+        assert_eq!(written.lines().count(), 3);
+    }
+}
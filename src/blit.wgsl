@@ -0,0 +1,32 @@
+// Copies the linear colors of the accumulated image to the target.
+// `encode_srgb` is appended from srgb.wgsl.
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct VertexIn {
+	@builtin(vertex_index) vertex_index: u32,
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4f,
+	@location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
+	let position = vec4f(uv * 2. - 1., 0., 1.);
+	return VertexOut(position, vec2f(uv.x, 1. - uv.y));
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+	return textureSample(source, source_sampler, in.uv);
+}
+
+@fragment
+fn fs_encode_srgb(in: VertexOut) -> @location(0) vec4f {
+	let color = textureSample(source, source_sampler, in.uv);
+	return vec4f(encode_srgb(color.rgb), color.a);
+}
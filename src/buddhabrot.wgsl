@@ -0,0 +1,98 @@
+// Buddhabrot accumulation: iterates random points of the Mandelbrot set's neighbourhood and
+// counts, for every pixel, how many escaping orbits pass through it. Counts add up across
+// frames until the view changes, see buddhabrot_display.wgsl for showing them.
+
+struct Uniforms {
+	// Size of the accumulation grid, the view size in physical pixels unless that didn't fit.
+	size: vec2u,
+	center: vec2f,
+	// Counterclockwise view rotation about its center, in radians.
+	rotation: f32,
+	// Size of an accumulation pixel on the complex plane.
+	scale: f32,
+	max_iter: u32,
+	escape_radius: f32,
+	// Varies by frame, so that every batch draws new points.
+	seed: u32,
+}
+
+struct Accumulation {
+	// Highest count of any pixel, to normalize by.
+	max_count: atomic<u32>,
+	counts: array<atomic<u32>>,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read_write> accumulation: Accumulation;
+
+// Points are drawn from the square around the origin the set lies in.
+const SAMPLE_RADIUS: f32 = 2.;
+
+fn pcg(value: u32) -> u32 {
+	let state = value * 747796405u + 2891336453u;
+	let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+	return (word >> 22u) ^ word;
+}
+
+fn random(state: ptr<function, u32>) -> f32 {
+	*state = pcg(*state);
+	return f32(*state >> 8u) / f32(1u << 24u);
+}
+
+// The main cardioid and the period-2 bulb never escape, skipping them saves most of the
+// iterations.
+fn in_bulbs(c: vec2f) -> bool {
+	let x = c.x - .25;
+	let q = x * x + c.y * c.y;
+	let bulb = c + vec2f(1., 0.);
+	return q * (q + x) <= .25 * c.y * c.y || dot(bulb, bulb) <= 1. / 16.;
+}
+
+fn square_add(z: vec2f, c: vec2f) -> vec2f {
+	return vec2f(z.x * z.x - z.y * z.y, 2. * z.x * z.y) + c;
+}
+
+fn plot(z: vec2f) {
+	let rotation = mat2x2f(cos(uniforms.rotation), sin(uniforms.rotation), -sin(uniforms.rotation), cos(uniforms.rotation));
+	// Inverse of the rotation applied by shader.wgsl.
+	let pixel = (z - uniforms.center) * rotation / uniforms.scale + vec2f(uniforms.size) * .5;
+	if any(pixel < vec2f(0.)) || any(pixel >= vec2f(uniforms.size)) {
+		return;
+	}
+	let index = u32(pixel.y) * uniforms.size.x + u32(pixel.x);
+	let count = atomicAdd(&accumulation.counts[index], 1u) + 1u;
+	atomicMax(&accumulation.max_count, count);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+	var state = pcg(id.x ^ pcg(uniforms.seed));
+	let c = (vec2f(random(&state), random(&state)) * 2. - 1.) * SAMPLE_RADIUS;
+	if in_bulbs(c) {
+		return;
+	}
+
+	let bailout = uniforms.escape_radius * uniforms.escape_radius;
+	var z = vec2f(0.);
+	var escaped = false;
+	for (var i = 0u; i < uniforms.max_iter; i++) {
+		z = square_add(z, c);
+		if dot(z, z) > bailout {
+			escaped = true;
+			break;
+		}
+	}
+	if !escaped {
+		return;
+	}
+
+	// Only escaping orbits are plotted, which is known once they did, so they are iterated again.
+	z = vec2f(0.);
+	for (var i = 0u; i < uniforms.max_iter; i++) {
+		z = square_add(z, c);
+		if dot(z, z) > bailout {
+			break;
+		}
+		plot(z);
+	}
+}
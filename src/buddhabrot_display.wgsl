@@ -0,0 +1,56 @@
+// Shows the orbit counts accumulated by buddhabrot.wgsl, on a log scale.
+
+struct Uniforms {
+	// Size of the accumulation grid, which covers the whole viewport.
+	size: vec2u,
+	// Brightness multiplier, 1 maps the highest count to white.
+	exposure: f32,
+}
+
+// Must match the declaration in buddhabrot.wgsl, without the atomics.
+struct Accumulation {
+	max_count: u32,
+	counts: array<u32>,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> accumulation: Accumulation;
+
+struct VertexIn {
+	@builtin(vertex_index) vertex_index: u32,
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4f,
+	@location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
+	let position = vec4f(uv * 2. - 1., 0., 1.);
+	return VertexOut(position, vec2f(uv.x, 1. - uv.y));
+}
+
+// Log-scaled brightness, which is already perceptually even, so it is used as an sRGB value.
+fn brightness(uv: vec2f) -> f32 {
+	let pixel = min(vec2u(uv * vec2f(uniforms.size)), uniforms.size - 1u);
+	let count = accumulation.counts[pixel.y * uniforms.size.x + pixel.x];
+	let max_count = max(accumulation.max_count, 1u);
+	return saturate(uniforms.exposure * log(1. + f32(count)) / log(1. + f32(max_count)));
+}
+
+fn decode_srgb(encoded: f32) -> f32 {
+	return select(pow((encoded + .055) / 1.055, 2.4), encoded / 12.92, encoded <= .04045);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+	return vec4f(vec3f(decode_srgb(brightness(in.uv))), 1.);
+}
+
+// Targets without an sRGB format store colors as they are.
+@fragment
+fn fs_encode_srgb(in: VertexOut) -> @location(0) vec4f {
+	return vec4f(vec3f(brightness(in.uv)), 1.);
+}
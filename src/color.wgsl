@@ -0,0 +1,239 @@
+// Coloring stage: maps the samples written by shader.wgsl to colors, averaging supersamples.
+
+struct Uniforms {
+	// Widget size in pixels.
+	size: vec2u,
+	// Supersampling grid size per axis.
+	aa_samples: u32,
+	max_iter: u32,
+	smooth_coloring: u32,
+	coloring: u32,
+	// Map escaped samples to the palette by their stripe average instead of the iteration count.
+	stripe: u32,
+	escape_radius: f32,
+	power: f32,
+	// Boundary line width in physical pixels.
+	line_width: f32,
+	interior: u32,
+	// Map iteration counts through cdf to spread them evenly over the palette.
+	histogram: u32,
+	gamma: f32,
+	// Rectangle to outline, in pixels, e.g. the main view on the minimap.
+	marker: u32,
+	marker_center: vec2f,
+	marker_half_size: vec2f,
+	// Direction of the marker's horizontal edges.
+	marker_axis: vec2f,
+	// Coordinate grid, in pixels relative to the center and along the plane axes: the distance
+	// between lines, a crossing of two lines and the crossing of the real and imaginary axes.
+	grid: u32,
+	grid_spacing: f32,
+	grid_offset: vec2f,
+	axes_offset: vec2f,
+	// Direction of the real axis.
+	grid_axis: vec2f,
+	// Maps positions in the iteration range to the palette, see shade.
+	color_offset: f32,
+	color_frequency: f32,
+	// Animation time in seconds, for shaders that animate.
+	elapsed: f32,
+}
+
+struct Sample {
+	z: vec2f,
+	iter: u32,
+	distance: f32,
+	trap: f32,
+	period: u32,
+	stripe: f32,
+}
+
+const COLORING_DISTANCE = 1u;
+const COLORING_DISTANCE_LINES = 2u;
+// This and the following modes are orbit traps.
+const COLORING_TRAP = 3u;
+
+const INTERIOR_MAGNITUDE = 1u;
+const INTERIOR_ANGLE = 2u;
+const INTERIOR_PERIOD = 3u;
+
+const PI = 3.14159265;
+// Spreads consecutive periods far apart in brightness.
+const GOLDEN_RATIO_CONJUGATE = .618034;
+
+// Distance from the set boundary, in pixels, at which distance coloring fades out.
+const DISTANCE_GLOW_WIDTH = 4.;
+// Orbit trap distance at which trap coloring fades out.
+const TRAP_GLOW_WIDTH = .1;
+
+const MARKER_COLOR = vec3f(1., .4, 0.);
+// Outline width, and the size below which the marker is drawn as a dot instead, in pixels.
+const MARKER_WIDTH = 1.5;
+const MARKER_DOT_RADIUS = 3.;
+
+const GRID_COLOR = vec3f(1.);
+// Line widths in pixels, and how opaque lines are.
+const GRID_WIDTH = 1.;
+const GRID_OPACITY = .35;
+const AXIS_WIDTH = 2.;
+const AXIS_OPACITY = .7;
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var<storage, read> samples: array<Sample>;
+
+// Fraction of escaped samples that escaped at or before each iteration, see shader.wgsl.
+@group(1) @binding(0) var<storage, read> cdf: array<f32>;
+
+// Gradient escaped samples are colored by, sampled as linear colors.
+@group(2) @binding(0) var palette: texture_2d<f32>;
+@group(2) @binding(1) var palette_sampler: sampler;
+
+struct VertexIn {
+	@builtin(vertex_index) vertex_index: u32,
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4f,
+	// Widget-relative coordinates, (0, 0) at the top-left corner and (1, 1) at the bottom-right.
+	@location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
+	let position = vec4f(uv * 2. - 1., 0., 1.);
+	return VertexOut(position, vec2f(uv.x, 1. - uv.y));
+}
+
+fn shade_interior(sample: Sample) -> vec3f {
+	switch uniforms.interior {
+		case INTERIOR_MAGNITUDE: {
+			// Interior orbits stay within |z| <= 2.
+			return vec3f(saturate(length(sample.z) * .5));
+		}
+		case INTERIOR_ANGLE: {
+			return vec3f(atan2(sample.z.y, sample.z.x) / (2. * PI) + .5);
+		}
+		case INTERIOR_PERIOD: {
+			if (sample.period == 0) {
+				return vec3f(0.);
+			}
+			return vec3f(fract(f32(sample.period) * GOLDEN_RATIO_CONJUGATE));
+		}
+		default: {
+			return vec3f(0.);
+		}
+	}
+}
+
+// Position of a (smoothed) iteration count in the distribution of escaped samples.
+fn percentile(iter: f32) -> f32 {
+	let i = u32(clamp(iter, 0., f32(uniforms.max_iter - 1u)));
+	var below = 0.;
+	if (i > 0u) {
+		below = cdf[i - 1u];
+	}
+	return mix(below, cdf[i], saturate(iter - f32(i)));
+}
+
+// Linear color of a sample, encoded by the target or blit.wgsl.
+fn shade(sample: Sample) -> vec3f {
+	// Unlike the other modes, orbit traps also color the interior.
+	if (uniforms.coloring >= COLORING_TRAP) {
+		return vec3f(exp(-sample.trap / TRAP_GLOW_WIDTH));
+	}
+
+	if (sample.iter >= uniforms.max_iter) {
+		return shade_interior(sample);
+	}
+
+	if (uniforms.coloring == COLORING_DISTANCE) {
+		return vec3f(exp(-sample.distance / DISTANCE_GLOW_WIDTH));
+	}
+
+	if (uniforms.coloring == COLORING_DISTANCE_LINES) {
+		// The distance is in pixels, so lines keep their width at any zoom level.
+		return vec3f(smoothstep(0., uniforms.line_width, sample.distance));
+	}
+
+	var iter = f32(sample.iter);
+	if (uniforms.smooth_coloring != 0) {
+		// Continuous escape count, normalized so that it doesn't jump at the escape radius.
+		let log_ratio = log(length(sample.z)) / log(uniforms.escape_radius);
+		iter += 1. - log(log_ratio) / log(uniforms.power);
+	}
+
+	var position = iter / f32(uniforms.max_iter);
+	if (uniforms.stripe != 0) {
+		// Already smooth, and unlike the iteration count independent of max_iter.
+		position = sample.stripe;
+	} else if (uniforms.histogram != 0) {
+		position = percentile(iter);
+	}
+
+	let value = fract(position * uniforms.color_frequency + uniforms.color_offset);
+	// Texel centers hold the gradient at 0 and 1.
+	let size = f32(textureDimensions(palette).x);
+	let u = (value * (size - 1.) + .5) / size;
+	return textureSampleLevel(palette, palette_sampler, vec2f(u, .5), 0.).rgb;
+}
+
+fn on_marker(pixel: vec2u) -> bool {
+	let d = vec2f(pixel) + .5 - uniforms.marker_center;
+	if (max(uniforms.marker_half_size.x, uniforms.marker_half_size.y) < MARKER_DOT_RADIUS) {
+		return length(d) < MARKER_DOT_RADIUS;
+	}
+
+	let axis = uniforms.marker_axis;
+	let local = abs(vec2f(dot(d, axis), dot(d, vec2f(-axis.y, axis.x))));
+	let outside = local - uniforms.marker_half_size;
+	// Inside the rectangle, but close to one of its edges.
+	return all(outside < vec2f(0.)) && any(outside > vec2f(-MARKER_WIDTH));
+}
+
+// Coverage of a pixel by an antialiased line `width` pixels wide, `d` pixels away.
+fn line_coverage(d: f32, width: f32) -> f32 {
+	return saturate(width * .5 + .5 - d);
+}
+
+fn grid_opacity(pixel: vec2u) -> f32 {
+	let d = vec2f(pixel) + .5 - vec2f(uniforms.size) * .5;
+	let axis = uniforms.grid_axis;
+	let local = vec2f(dot(d, axis), dot(d, vec2f(-axis.y, axis.x)));
+
+	let spacing = uniforms.grid_spacing;
+	let to_line = abs(fract((local - uniforms.grid_offset) / spacing + .5) - .5) * spacing;
+	let to_axis = abs(local - uniforms.axes_offset);
+	return max(
+		line_coverage(min(to_line.x, to_line.y), GRID_WIDTH) * GRID_OPACITY,
+		line_coverage(min(to_axis.x, to_axis.y), AXIS_WIDTH) * AXIS_OPACITY,
+	);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+	// Builtin position is relative to the whole target, not to the widget viewport.
+	let pixel = min(vec2u(in.uv * vec2f(uniforms.size)), uniforms.size - 1u);
+
+	// Average the pixel's n * n samples.
+	let n = uniforms.aa_samples;
+	let grid_width = uniforms.size.x * n;
+	var color = vec3f(0.);
+	for (var j = 0u; j < n; j = j + 1) {
+		for (var i = 0u; i < n; i = i + 1) {
+			let sample = pixel * n + vec2u(i, j);
+			color += shade(samples[sample.y * grid_width + sample.x]);
+		}
+	}
+
+	if (uniforms.marker != 0 && on_marker(pixel)) {
+		return vec4f(MARKER_COLOR, 1.);
+	}
+
+	// Black stays black at any gamma.
+	var gamma_corrected = pow(color / f32(n * n), vec3f(1. / uniforms.gamma));
+	if (uniforms.grid != 0) {
+		gamma_corrected = mix(gamma_corrected, GRID_COLOR, grid_opacity(pixel));
+	}
+	return vec4f(gamma_corrected, 1.);
+}
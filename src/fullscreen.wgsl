@@ -0,0 +1,14 @@
+// Vertex stage of FragmentShaderWidget: a single triangle covering the viewport.
+
+struct VertexOut {
+	@builtin(position) position: vec4f,
+	// Widget-relative coordinates, (0, 0) at the top-left corner and (1, 1) at the bottom-right.
+	@location(0) uv: vec2f,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOut {
+	let uv = vec2f(vec2u((vertex_index << 1) & 2, vertex_index & 2));
+	let position = vec4f(uv * 2. - 1., 0., 1.);
+	return VertexOut(position, vec2f(uv.x, 1. - uv.y));
+}
@@ -0,0 +1,793 @@
+//! A widget drawing a single fragment shader, with uniforms of the application's choosing.
+//!
+//! [`FragmentShaderWidget`] takes care of the pipeline, the uniform buffer and the vertex stage,
+//! a triangle covering the widget, so that the WGSL source only needs a fragment stage:
+//!
+//! ```wgsl
+//! struct Uniforms {
+//!     time: f32,
+//! }
+//!
+//! @fragment
+//! fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+//!     return vec4f(uv, sin(uniforms.time) * .5 + .5, 1.);
+//! }
+//! ```
+//!
+//! The widget declares `uniforms` of type `Uniforms` itself: as a push constant where the device
+//! supports them, saving a buffer write per frame, and in a uniform buffer at
+//! `@group(0) @binding(0)` otherwise. The source only defines the struct, which it has to even if
+//! it doesn't use any uniforms.
+//!
+//! `uv` goes from (0, 0) at the top-left corner of the widget to (1, 1) at the bottom-right. The
+//! entry point has to take it even if it doesn't use it, as wgpu requires every vertex output to
+//! be consumed.
+//!
+//! A [`Texture`], e.g. a noise image or a palette, can be sampled from group 1:
+//!
+//! ```wgsl
+//! @group(1) @binding(0) var texture: texture_2d<f32>;
+//! @group(1) @binding(1) var texture_sampler: sampler;
+//! ```
+//!
+//! Without one, a single white pixel is bound there, so multiplying by it changes nothing.
+//!
+//! `examples/plasma.rs` animates a shader like this one, and `examples/mandelbrot.rs` pans and
+//! zooms with [`FragmentShaderWidget::on_event`], coloring by a palette texture.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use iced::advanced::Shell;
+use iced::event::Status;
+use iced::futures;
+use iced::mouse::Cursor;
+use iced::widget::shader;
+use iced::widget::shader::wgpu;
+use iced::widget::shader::Event;
+use iced::{Rectangle, Size};
+
+use crate::{begin_pass, set_viewport, PreparedIds};
+
+static NEXT_WIDGET_ID: AtomicU64 = AtomicU64::new(0);
+static NEXT_TEXTURE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Sampled by shaders without a texture of their own: opaque white.
+const BLANK_PIXEL: [u8; 4] = [255; 4];
+
+type BoundsHandler<U> = Box<dyn Fn(&mut U, Size)>;
+type EventHandler<Message> = Box<dyn Fn(Event, Rectangle, Cursor) -> Option<Message>>;
+
+/// A [`shader::Program`] filling its bounds with a WGSL fragment shader. `U` is the shader's
+/// `Uniforms` struct, with the same layout as in WGSL.
+pub struct FragmentShaderWidget<U, Message = ()> {
+    id: u64,
+    source: Arc<str>,
+    /// Uniforms the next frame is drawn with.
+    pub uniforms: U,
+    on_bounds: Option<BoundsHandler<U>>,
+    on_event: Option<EventHandler<Message>>,
+    texture: Option<Texture>,
+    /// Set by the pipeline once it tried the source.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl<U: bytemuck::Pod, Message> FragmentShaderWidget<U, Message> {
+    /// A widget drawing the `fs_main` entry point of `source` with `uniforms`.
+    pub fn new(source: impl Into<Arc<str>>, uniforms: U) -> Self {
+        Self {
+            id: NEXT_WIDGET_ID.fetch_add(1, Ordering::Relaxed),
+            source: source.into(),
+            uniforms,
+            on_bounds: None,
+            on_event: None,
+            texture: None,
+            error: Arc::default(),
+        }
+    }
+
+    /// Fills in the uniforms that depend on the widget size, in logical pixels, before each
+    /// frame, e.g. its aspect ratio.
+    pub fn on_bounds(mut self, on_bounds: impl Fn(&mut U, Size) + 'static) -> Self {
+        self.on_bounds = Some(Box::new(on_bounds));
+        self
+    }
+
+    /// Turns events into messages, e.g. to pan and zoom. It sees all events, also those outside
+    /// the widget bounds, and the ones it turns into a message are captured.
+    pub fn on_event(
+        mut self,
+        on_event: impl Fn(Event, Rectangle, Cursor) -> Option<Message> + 'static,
+    ) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Binds `texture` for the shader to sample, see the [module docs](self).
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Swaps the texture the shader samples, leaving the pipeline as it is.
+    pub fn set_texture(&mut self, texture: Option<Texture>) {
+        self.texture = texture;
+    }
+
+    /// Why the shader can't be drawn, `None` if it can or until it has been tried.
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+impl<U: bytemuck::Pod, Message> shader::Program<Message> for FragmentShaderWidget<U, Message> {
+    type State = ();
+    type Primitive = FragmentShaderWidgetPrimitive;
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+        _shell: &mut Shell<'_, Message>,
+    ) -> (Status, Option<Message>) {
+        match self
+            .on_event
+            .as_ref()
+            .and_then(|on_event| on_event(event, bounds, cursor))
+        {
+            Some(message) => (Status::Captured, Some(message)),
+            None => (Status::Ignored, None),
+        }
+    }
+
+    fn draw(&self, _state: &Self::State, _cursor: Cursor, bounds: Rectangle) -> Self::Primitive {
+        let mut uniforms = self.uniforms;
+        if let Some(on_bounds) = &self.on_bounds {
+            on_bounds(&mut uniforms, bounds.size());
+        }
+
+        let mut bytes = bytemuck::bytes_of(&uniforms).to_vec();
+        bytes.resize(uniform_buffer_size(bytes.len()), 0);
+        FragmentShaderWidgetPrimitive {
+            id: self.id,
+            source: self.source.clone(),
+            uniforms: bytes,
+            texture: self.texture.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// Size of the uniform buffer for a `len` bytes long uniform struct. WGSL rounds struct sizes up
+/// to their alignment, which is at most 16 bytes, and the Rust struct may lack that padding.
+/// Buffer writes also have to be a multiple of 4 bytes, bindings can't be empty, and some drivers
+/// expect uniform buffers to be a multiple of 16 bytes.
+pub(crate) fn uniform_buffer_size(len: usize) -> usize {
+    len.max(1).next_multiple_of(16)
+}
+
+/// An RGBA image for a [`FragmentShaderWidget`] to sample, in sRGB. Samples outside it repeat
+/// it, and the ones in between are interpolated linearly.
+#[derive(Clone)]
+pub struct Texture {
+    /// Tells textures apart, so that the pipeline only uploads new ones.
+    id: u64,
+    size: Size<u32>,
+    pixels: Arc<[u8]>,
+}
+
+impl Texture {
+    /// A texture from rows of RGBA pixels, without padding.
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err(String::from("texture is empty"));
+        }
+        if pixels.len() as u64 != u64::from(width) * u64::from(height) * 4 {
+            return Err(format!(
+                "{} bytes of pixels for a {width}x{height} texture",
+                pixels.len()
+            ));
+        }
+        Ok(Self {
+            id: NEXT_TEXTURE_ID.fetch_add(1, Ordering::Relaxed),
+            size: Size::new(width, height),
+            pixels: pixels.into(),
+        })
+    }
+
+    /// Decodes a PNG image of any color type into a texture.
+    #[cfg(any(test, feature = "headless"))]
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|err| err.to_string())?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => return Err(String::from("unexpanded indexed colors")),
+        };
+        Self::from_rgba(info.width, info.height, pixels)
+    }
+
+    pub fn size(&self) -> Size<u32> {
+        self.size
+    }
+}
+
+impl std::fmt::Debug for Texture {
+    // Leaves out the pixels.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Texture")
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether `uniforms_size` bytes of uniforms can be pushed as constants instead of written to a
+/// buffer. iced doesn't request push constants for its device, so this is for devices created
+/// elsewhere, e.g. by [`crate::HeadlessRenderer`].
+fn fits_push_constants(
+    features: wgpu::Features,
+    limits: &wgpu::Limits,
+    uniforms_size: u64,
+) -> bool {
+    features.contains(wgpu::Features::PUSH_CONSTANTS)
+        && u64::from(limits.max_push_constant_size) >= uniforms_size
+}
+
+/// A frame of a [`FragmentShaderWidget`].
+#[derive(Debug)]
+pub struct FragmentShaderWidgetPrimitive {
+    id: u64,
+    source: Arc<str>,
+    /// Padded to the uniform buffer size.
+    uniforms: Vec<u8>,
+    texture: Option<Texture>,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+/// Pipelines of every [`FragmentShaderWidget`], by widget id.
+#[derive(Default)]
+struct Pipelines {
+    instances: HashMap<u64, Instance>,
+    prepared: PreparedIds,
+}
+
+struct Instance {
+    format: wgpu::TextureFormat,
+    /// The error instead if the source didn't compile.
+    pipeline: Result<wgpu::RenderPipeline, String>,
+    /// Whether the uniforms are push constants. The uniform buffer stays bound regardless, so
+    /// that the layout is the same either way.
+    push_constants: bool,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Id of the bound texture, `None` for the blank one.
+    texture_id: Option<u64>,
+    texture_bind_group: wgpu::BindGroup,
+}
+
+impl Instance {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        source: &str,
+        uniforms_size: u64,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("FragmentShaderWidget bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("FragmentShaderWidget uniform buffer"),
+            size: uniforms_size,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("FragmentShaderWidget bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        // Explicit, unlike the viewer's layouts, so that it doesn't depend on whether the shader
+        // samples the texture.
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("FragmentShaderWidget texture bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("FragmentShaderWidget sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_bind_group = create_texture_bind_group(
+            device,
+            queue,
+            &texture_layout,
+            &sampler,
+            Size::new(1, 1),
+            &BLANK_PIXEL,
+        );
+
+        let push_constants =
+            fits_push_constants(device.features(), &device.limits(), uniforms_size);
+        let (declaration, push_constant_ranges) = if push_constants {
+            let range = wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::FRAGMENT,
+                range: 0..uniforms_size as u32,
+            };
+            ("var<push_constant> uniforms: Uniforms;", vec![range])
+        } else {
+            (
+                "@group(0) @binding(0) var<uniform> uniforms: Uniforms;",
+                vec![],
+            )
+        };
+        // Appended, so that line numbers in errors match the source.
+        let source = format!("{source}\n{declaration}\n");
+
+        let pipeline = Self::create_pipeline(
+            device,
+            format,
+            &source,
+            &[&bind_group_layout, &texture_layout],
+            &push_constant_ranges,
+        );
+
+        Self {
+            format,
+            pipeline,
+            push_constants,
+            uniform_buffer,
+            bind_group,
+            texture_layout,
+            sampler,
+            texture_id: None,
+            texture_bind_group,
+        }
+    }
+
+    /// Uploads `texture` unless it's already bound, without touching the pipeline. Returns why
+    /// it can't be used, if so, binding the blank texture instead.
+    fn bind_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: Option<&Texture>,
+    ) -> Option<String> {
+        let max_size = device.limits().max_texture_dimension_2d;
+        let (texture, error) = match texture {
+            Some(texture) if texture.size.width.max(texture.size.height) > max_size => {
+                let size = texture.size;
+                let error = format!(
+                    "{}x{} texture is larger than the GPU limit of {max_size}",
+                    size.width, size.height
+                );
+                (None, Some(error))
+            }
+            texture => (texture, None),
+        };
+
+        let id = texture.map(|texture| texture.id);
+        if id != self.texture_id {
+            let (size, pixels) = match texture {
+                Some(texture) => (texture.size, &texture.pixels[..]),
+                None => (Size::new(1, 1), &BLANK_PIXEL[..]),
+            };
+            self.texture_bind_group = create_texture_bind_group(
+                device,
+                queue,
+                &self.texture_layout,
+                &self.sampler,
+                size,
+                pixels,
+            );
+            self.texture_id = id;
+        }
+        error
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        source: &str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        push_constant_ranges: &[wgpu::PushConstantRange],
+    ) -> Result<wgpu::RenderPipeline, String> {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderWidget vertex shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "fullscreen.wgsl"
+            ))),
+        });
+
+        let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderWidget fragment shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderWidget layout"),
+            bind_group_layouts,
+            push_constant_ranges,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("FragmentShaderWidget"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &vertex_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &fragment_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        match futures::executor::block_on(device.pop_error_scope()) {
+            Some(err) => Err(err.to_string()),
+            None => Ok(pipeline),
+        }
+    }
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    size: Size<u32>,
+    pixels: &[u8],
+) -> wgpu::BindGroup {
+    let extent = wgpu::Extent3d {
+        width: size.width,
+        height: size.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("FragmentShaderWidget texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(size.width * 4),
+            rows_per_image: None,
+        },
+        extent,
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("FragmentShaderWidget texture bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+impl shader::Primitive for FragmentShaderWidgetPrimitive {
+    fn prepare(
+        &self,
+        format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        _bounds: Rectangle,
+        _target_size: Size<u32>,
+        _scale_factor: f32,
+        storage: &mut shader::Storage,
+    ) {
+        if !storage.has::<Pipelines>() {
+            storage.store(Pipelines::default());
+        }
+        let Some(pipelines) = storage.get_mut::<Pipelines>() else {
+            return;
+        };
+        pipelines
+            .prepared
+            .prepare(self.id, &mut pipelines.instances);
+
+        // Like the viewer's pipeline, rebuilt when the surface format changes.
+        let stale = pipelines
+            .instances
+            .get(&self.id)
+            .is_none_or(|instance| instance.format != format);
+        if stale {
+            let size = self.uniforms.len() as u64;
+            let instance = Instance::new(device, queue, format, &self.source, size);
+            pipelines.instances.insert(self.id, instance);
+        }
+
+        let Some(instance) = pipelines.instances.get_mut(&self.id) else {
+            return;
+        };
+        let texture_error = instance.bind_texture(device, queue, self.texture.as_ref());
+        let pipeline_error = instance.pipeline.as_ref().err().cloned();
+        *self.error.lock().unwrap() = pipeline_error.or(texture_error);
+        if !instance.push_constants {
+            queue.write_buffer(&instance.uniform_buffer, 0, &self.uniforms);
+        }
+    }
+
+    fn render(
+        &self,
+        storage: &shader::Storage,
+        target: &wgpu::TextureView,
+        _target_size: Size<u32>,
+        viewport: Rectangle<u32>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let Some(instance) = storage
+            .get::<Pipelines>()
+            .and_then(|pipelines| pipelines.instances.get(&self.id))
+        else {
+            return;
+        };
+        let Ok(pipeline) = &instance.pipeline else {
+            return;
+        };
+
+        let mut pass = begin_pass(encoder, target, "FragmentShaderWidget");
+        set_viewport(&mut pass, viewport);
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &instance.bind_group, &[]);
+        pass.set_bind_group(1, &instance.texture_bind_group, &[]);
+        if instance.push_constants {
+            pass.set_push_constants(wgpu::ShaderStages::FRAGMENT, 0, &self.uniforms);
+        }
+        pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeadlessRenderer;
+    use iced::widget::shader::Program as _;
+
+    const SOLID_COLOR: &str = "
+        struct Uniforms {
+            color: vec3f,
+        }
+
+        @fragment
+        fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+            return vec4f(uniforms.color, 1.);
+        }
+    ";
+
+    #[test]
+    fn uniform_buffers_cover_the_wgsl_struct() {
+        assert_eq!(uniform_buffer_size(0), 16);
+        assert_eq!(uniform_buffer_size(4), 16);
+        // vec3f is 16 byte aligned in WGSL, [f32; 3] only 4 in Rust.
+        assert_eq!(uniform_buffer_size(12), 16);
+        assert_eq!(uniform_buffer_size(20), 32);
+        assert_eq!(uniform_buffer_size(32), 32);
+    }
+
+    #[test]
+    fn uniforms_are_pushed_where_they_fit() {
+        let limits = wgpu::Limits {
+            max_push_constant_size: 128,
+            ..wgpu::Limits::default()
+        };
+        let features = wgpu::Features::PUSH_CONSTANTS;
+        assert!(fits_push_constants(features, &limits, 128));
+        assert!(!fits_push_constants(features, &limits, 144));
+        assert!(!fits_push_constants(wgpu::Features::empty(), &limits, 16));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn draws_the_shader_with_its_uniforms() {
+        let mut renderer = HeadlessRenderer::new(Size::new(8, 8)).unwrap();
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(8.0, 8.0));
+        let widget: FragmentShaderWidget<[f32; 3]> =
+            FragmentShaderWidget::new(SOLID_COLOR, [0.0, 1.0, 0.0]);
+
+        let primitive = widget.draw(&(), Cursor::Unavailable, bounds);
+        renderer.draw_primitive(&primitive, bounds.snap()).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+        assert!(pixels.chunks(4).all(|pixel| pixel == [0, 255, 0, 255]));
+        assert_eq!(widget.error(), None);
+
+        let device = &renderer.device;
+        let pushed =
+            renderer.storage.get::<Pipelines>().unwrap().instances[&widget.id].push_constants;
+        assert_eq!(
+            pushed,
+            fits_push_constants(device.features(), &device.limits(), 16)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn reports_shaders_that_dont_compile() {
+        let mut renderer = HeadlessRenderer::new(Size::new(8, 8)).unwrap();
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(8.0, 8.0));
+        let widget: FragmentShaderWidget<f32> = FragmentShaderWidget::new("fn fs_main(", 0.0);
+
+        let primitive = widget.draw(&(), Cursor::Unavailable, bounds);
+        renderer.draw_primitive(&primitive, bounds.snap()).unwrap();
+        assert!(widget.error().is_some());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn removed_widgets_have_their_pipelines_dropped() {
+        let mut renderer = HeadlessRenderer::new(Size::new(8, 8)).unwrap();
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(8.0, 8.0));
+        let kept: FragmentShaderWidget<[f32; 3]> = FragmentShaderWidget::new(SOLID_COLOR, [1.0; 3]);
+        let removed: FragmentShaderWidget<[f32; 3]> =
+            FragmentShaderWidget::new(SOLID_COLOR, [0.0; 3]);
+
+        let mut draw = |widget: &FragmentShaderWidget<[f32; 3]>| {
+            let primitive = widget.draw(&(), Cursor::Unavailable, bounds);
+            renderer.draw_primitive(&primitive, bounds.snap()).unwrap();
+            let instances = &renderer.storage.get::<Pipelines>().unwrap().instances;
+            instances.contains_key(&removed.id)
+        };
+
+        draw(&kept);
+        assert!(draw(&removed));
+        // Next frame, `removed` is no longer drawn.
+        assert!(draw(&kept));
+        // The frame after that finds it wasn't.
+        assert!(!draw(&kept));
+    }
+
+    #[test]
+    fn textures_decode_from_any_png_color_type() {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 200]).unwrap();
+        writer.finish().unwrap();
+
+        let texture = Texture::from_png(&png).unwrap();
+        assert_eq!(texture.size(), Size::new(2, 1));
+        assert_eq!(texture.pixels[..], [0, 0, 0, 255, 200, 200, 200, 255]);
+        assert!(Texture::from_rgba(2, 2, vec![0; 4]).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn swapping_textures_keeps_the_pipeline() {
+        let mut renderer = HeadlessRenderer::new(Size::new(8, 8)).unwrap();
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(8.0, 8.0));
+        let source = "
+            struct Uniforms {
+                unused: f32,
+            }
+
+            @group(1) @binding(0) var texture: texture_2d<f32>;
+            @group(1) @binding(1) var texture_sampler: sampler;
+
+            @fragment
+            fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+                return textureSample(texture, texture_sampler, uv);
+            }
+        ";
+        let green = Texture::from_rgba(1, 1, vec![0, 255, 0, 255]).unwrap();
+        let mut widget: FragmentShaderWidget<f32> =
+            FragmentShaderWidget::new(source, 0.0).with_texture(green);
+
+        let mut draw = |widget: &FragmentShaderWidget<f32>| {
+            let primitive = widget.draw(&(), Cursor::Unavailable, bounds);
+            renderer.draw_primitive(&primitive, bounds.snap()).unwrap();
+            let pixels = renderer.read_pixels().unwrap();
+            assert!(pixels.chunks(4).all(|pixel| *pixel == pixels[..4]));
+            let pipeline = renderer.storage.get::<Pipelines>().unwrap().instances[&widget.id]
+                .pipeline
+                .as_ref()
+                .map(|pipeline| pipeline.global_id());
+            (pixels[..4].to_vec(), pipeline.unwrap())
+        };
+
+        let (pixel, pipeline) = draw(&widget);
+        assert_eq!(pixel, [0, 255, 0, 255]);
+        widget.set_texture(Some(
+            Texture::from_rgba(1, 1, vec![255, 0, 0, 255]).unwrap(),
+        ));
+        assert_eq!(draw(&widget), (vec![255, 0, 0, 255], pipeline));
+        // Without a texture, the shader samples white.
+        widget.set_texture(None);
+        assert_eq!(draw(&widget), (vec![255; 4], pipeline));
+        assert_eq!(widget.error(), None);
+    }
+}
@@ -0,0 +1,7307 @@
+//! Mandelbrot viewer drawn by a fragment shader, as a widget to embed in iced applications.
+//!
+//! [`FragmentShaderProgram`] is a [`shader::Program`] rendering the view described by its
+//! [`Controls`]. It reports panning, zooming and hovering as [`Message`]s rather than applying
+//! them, leaving it to the application how the view follows:
+//!
+//! ```no_run
+//! use fragment_shader_widget::{apply_zoom, Controls, FragmentShaderProgram, Message};
+//! use glam::Vec2;
+//! use iced::widget::shader;
+//! use iced::{Element, Length, Sandbox, Settings};
+//!
+//! struct Viewer {
+//!     program: FragmentShaderProgram,
+//! }
+//!
+//! impl Sandbox for Viewer {
+//!     type Message = Message;
+//!
+//!     fn new() -> Self {
+//!         let controls = Controls {
+//!             zoom: 4.0,
+//!             ..Controls::default()
+//!         };
+//!         let program = FragmentShaderProgram::new(controls)
+//!             .with_shader(include_str!("shader.wgsl").to_string());
+//!         Self { program }
+//!     }
+//!
+//!     fn title(&self) -> String {
+//!         String::from("Viewer")
+//!     }
+//!
+//!     fn update(&mut self, message: Message) {
+//!         let controls = &mut self.program.controls;
+//!         match message {
+//!             Message::PanningDelta(delta, bounds) => {
+//!                 controls.pan(delta, bounds);
+//!             }
+//!             Message::ZoomDelta(pos, bounds, delta) | Message::PinchZoom(pos, bounds, delta) => {
+//!                 *controls = apply_zoom(*controls, pos, bounds, delta, &self.program.config);
+//!             }
+//!             _ => {}
+//!         }
+//!         self.program.update_reference_orbit();
+//!     }
+//!
+//!     fn view(&self) -> Element<'_, Message> {
+//!         shader(&self.program)
+//!             .width(Length::Fill)
+//!             .height(Length::Fill)
+//!             .into()
+//!     }
+//! }
+//!
+//! fn main() -> iced::Result {
+//!     Viewer::run(Settings::default())
+//! }
+//! ```
+//!
+//! [`FragmentShaderProgram::with_config`] narrows the zoom levels and iteration counts users can
+//! reach, see [`Config`].
+//!
+//! For other fragment shaders, [`FragmentShaderWidget`] draws any WGSL source with uniforms of
+//! the application's choosing, see the [`generic`] module.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f32::consts::{PI, TAU};
+use std::ops::{Range, RangeInclusive};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use glam::{DVec2, UVec2, Vec2};
+use iced::advanced::Shell;
+use iced::event::Status;
+use iced::futures;
+use iced::keyboard;
+use iced::mouse;
+use iced::mouse::Cursor;
+use iced::time::{Duration, Instant};
+use iced::touch;
+use iced::widget::shader;
+use iced::widget::shader::wgpu;
+use iced::widget::shader::Event;
+use iced::{Rectangle, Size, Subscription};
+use serde::{Deserialize, Serialize};
+
+use parse::{parse_finite, parse_in_range, parse_zoom};
+
+pub mod generic;
+mod parse;
+
+pub use generic::FragmentShaderWidget;
+
+pub const ZOOM_MIN: f32 = 1.0;
+pub const ZOOM_DEFAULT: f32 = 2.0;
+pub const ZOOM_MAX: f32 = 40.0;
+
+/// Above this zoom level single-precision floats can no longer tell neighbouring pixels
+/// apart, so the shader switches to perturbation or double-float arithmetic.
+const ZOOM_DEEP_THRESHOLD: f32 = 15.0;
+
+/// Height of the view on the complex plane at zoom level 0, each level halves it.
+const EXTENT_UNZOOMED: f64 = 3.0;
+/// Coordinates are shown precise enough to tell apart the pixels of views up to this tall.
+const PRECISION_MAX_HEIGHT: f64 = 4096.0;
+const ZOOM_WHEEL_SCALE: f32 = 0.2;
+/// Scrolling by this many pixels, e.g. on a trackpad, counts as one wheel notch.
+const WHEEL_PIXELS_PER_LINE: f32 = 20.0;
+/// Ctrl+wheel multiplies the iteration count by this per notch.
+const WHEEL_ITERS_FACTOR: f32 = 1.1;
+/// Shift+wheel pans by this fraction of the view width per notch.
+const WHEEL_PAN_FRACTION: f32 = 0.1;
+/// `+` and `-` change the iteration count by this much.
+const KEY_ITERS_STEP: u32 = 10;
+/// `]` and `[` zoom in and out by this many levels.
+const KEY_ZOOM_STEP: f32 = 0.25;
+/// Alt+drag ignores the cursor within this many pixels of the view center.
+const ROTATION_DEAD_ZONE: f32 = 4.0;
+/// Distance in pixels from the split line of a comparison within which it can be dragged.
+const SPLIT_GRAB_TOLERANCE: f32 = 4.0;
+/// Width to height ratio of the minimap, and its height as a fraction of the view's.
+const MINIMAP_ASPECT: f32 = 4.0 / 3.0;
+const MINIMAP_HEIGHT_FRACTION: f32 = 0.2;
+/// Gap between the minimap and the view edges, as a fraction of the minimap height.
+const MINIMAP_MARGIN_FRACTION: f32 = 0.08;
+const MINIMAP_ITERS: u32 = 200;
+/// Orbit points drawn for [`FragmentShaderProgram::orbit_point`].
+const TRACE_LEN: usize = 200;
+/// Trace points further out than this in clip space, where the view edges are at 1, are left
+/// out.
+const TRACE_CLIP: f32 = 2.0;
+/// Smallest distance between coordinate grid lines, in logical pixels. Spacings are 1, 2 or 5
+/// times a power of ten, so lines are less than 2.5 times that apart.
+const GRID_MIN_SPACING: f32 = 100.0;
+/// Distance of the grid labels from the view edges, in logical pixels.
+const GRID_LABEL_MARGIN: f32 = 4.0;
+
+pub const ITERS_MIN: u32 = 20;
+const ITERS_DEFAULT: u32 = 20;
+pub const ITERS_MAX: u32 = 5000;
+
+/// Automatic iteration counts grow with the square of the zoom level, times this.
+const ITERS_AUTO_FACTOR: f32 = 5.0;
+/// Automatic iteration counts stop growing here, as more stall the GPU for little detail.
+const ITERS_AUTO_MAX: u32 = 2000;
+
+pub const MULTIBROT_POWER_MIN: f32 = 2.0;
+pub const MULTIBROT_POWER_DEFAULT: f32 = 3.0;
+pub const MULTIBROT_POWER_MAX: f32 = 8.0;
+
+pub const ESCAPE_RADIUS_MIN: f32 = 2.0;
+pub const ESCAPE_RADIUS_MAX: f32 = 256.0;
+
+pub const GAMMA_MIN: f32 = 0.4;
+const GAMMA_DEFAULT: f32 = 1.0;
+pub const GAMMA_MAX: f32 = 2.5;
+
+/// Times the palette repeats over the iteration range.
+pub const COLOR_FREQUENCY_MIN: f32 = 0.1;
+const COLOR_FREQUENCY_DEFAULT: f32 = 1.0;
+pub const COLOR_FREQUENCY_MAX: f32 = 10.0;
+
+/// Palette cycles per second while colors are animated.
+const COLOR_CYCLE_SPEED_DEFAULT: f32 = 0.1;
+pub const COLOR_CYCLE_SPEED_MAX: f32 = 1.0;
+
+pub const LINE_WIDTH_MIN: f32 = 0.5;
+const LINE_WIDTH_DEFAULT: f32 = 1.5;
+pub const LINE_WIDTH_MAX: f32 = 4.0;
+
+/// Stripes per turn of the orbit angle in stripe average coloring.
+pub const STRIPE_FREQUENCY_MIN: f32 = 1.0;
+const STRIPE_FREQUENCY_DEFAULT: f32 = 5.0;
+pub const STRIPE_FREQUENCY_MAX: f32 = 12.0;
+
+pub const TRAP_RADIUS_MIN: f32 = 0.05;
+const TRAP_RADIUS_DEFAULT: f32 = 0.5;
+pub const TRAP_RADIUS_MAX: f32 = 2.0;
+
+pub const CENTER_DEFAULT: DVec2 = DVec2::new(-1.5, 0.0);
+
+pub const JULIA_CENTER: DVec2 = DVec2::new(0.0, 0.0);
+pub const JULIA_SEED_DEFAULT: Vec2 = Vec2::new(-0.8, 0.156);
+/// Supersampling grid sizes, each pixel is iterated `n * n` times. Larger grids than the
+/// device's buffer limits allow are reduced.
+pub const AA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+
+/// Numbers of tiles per axis the iteration can be split into.
+pub const TILES: [u32; 4] = [1, 2, 4, 8];
+/// Tiles are iterated for about this long per frame, the rest in the following frames.
+const TILE_FRAME_BUDGET: Duration = Duration::from_millis(50);
+/// Position of the split line of a comparison, as a fraction of the view width.
+const SPLIT_DEFAULT: f32 = 0.5;
+/// Iterations per Buddhabrot frame at most, split into as many points as the iteration count
+/// allows.
+const BUDDHABROT_BATCH_ITERATIONS: u32 = 1 << 26;
+const BUDDHABROT_WORKGROUP_SIZE: u32 = 64;
+/// Points after which a Buddhabrot stops accumulating, as more hardly change the image.
+const BUDDHABROT_SAMPLES_MAX: u64 = 1 << 30;
+pub const EXPOSURE_MIN: f32 = 0.25;
+const EXPOSURE_DEFAULT: f32 = 1.0;
+pub const EXPOSURE_MAX: f32 = 4.0;
+
+/// Resolution is divided by this while panning or zooming.
+const LOW_RES_DIVISOR: u32 = 2;
+/// Iteration count is divided by this while panning or zooming, but not below `ITERS_MIN`.
+const INTERACTION_ITERS_DIVISOR: u32 = 4;
+/// Weight of the latest frame in the rolling frame time average.
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+/// Frames are counted over this long for the frame rate.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Two presses at most this far apart in time and in pixels make a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+/// Gliding stops below this speed, in pixels per second.
+pub(crate) const MOMENTUM_MIN_SPEED: f32 = 20.0;
+/// Releasing a pan doesn't glide if the cursor was held still for this long before.
+const MOMENTUM_RELEASE_WINDOW: Duration = Duration::from_millis(80);
+/// Weight of the latest cursor movement in the panning velocity estimate.
+const PAN_VELOCITY_SMOOTHING: f32 = 0.5;
+/// Parameters of the iteration stage, see `shader.wgsl`. A change requires iterating again.
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct Uniforms {
+    /// Widget size in physical pixels.
+    resolution: Vec2,
+    /// Center as a double-float: the f64 value is `center_hi + center_lo`.
+    center_hi: Vec2,
+    center_lo: Vec2,
+    seed: Vec2,
+    /// Sub-pixel offset of the sample, in pixels.
+    jitter: Vec2,
+    /// Number of samples per axis.
+    grid_size: UVec2,
+    trap_point: Vec2,
+    /// Height of the view on the complex plane.
+    extent: f32,
+    max_iter: u32,
+    julia: u32,
+    formula: u32,
+    deep: u32,
+    power: f32,
+    escape_radius: f32,
+    perturbation: u32,
+    orbit_len: u32,
+    /// Orbit trap shape, see [`ColoringMode::trap`].
+    trap: u32,
+    trap_radius: f32,
+    /// Stop iterating once the orbit is found to be periodic, see [`InteriorMode::Period`].
+    period_detection: u32,
+    /// First sample of the tile being iterated, see [`FragmentShaderPipeline::iterate_tiles`].
+    tile_offset: UVec2,
+    /// View rotation about its center, in radians.
+    rotation: f32,
+    /// Stripe average coloring frequency, 0 when it's off to skip averaging.
+    stripe_frequency: f32,
+    /// Whether to track the derivative for the distance estimate, only used by some colorings.
+    distance_estimate: u32,
+    /// The WGSL struct is aligned to its `vec2f`.
+    _padding: u32,
+}
+
+/// Parameters of the coloring stage, see `color.wgsl`. Changing these only recolors the samples.
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct ColorUniforms {
+    /// Widget size in pixels.
+    size: UVec2,
+    /// Supersampling grid size per axis.
+    aa_samples: u32,
+    max_iter: u32,
+    smooth_coloring: u32,
+    coloring: u32,
+    /// Map escaped samples to the palette by their stripe average, see [`Controls::stripe`].
+    stripe: u32,
+    escape_radius: f32,
+    power: f32,
+    /// Boundary line width in physical pixels.
+    line_width: f32,
+    interior: u32,
+    /// Map iteration counts through the histogram's cumulative distribution.
+    histogram: u32,
+    gamma: f32,
+    /// Whether to outline `marker_center`, `marker_half_size` and `marker_axis`, in pixels.
+    marker: u32,
+    marker_center: Vec2,
+    marker_half_size: Vec2,
+    /// Direction of the marker's horizontal edges.
+    marker_axis: Vec2,
+    /// Whether to draw the coordinate grid described by the following fields, see [`GridLines`].
+    grid: u32,
+    grid_spacing: f32,
+    grid_offset: Vec2,
+    axes_offset: Vec2,
+    grid_axis: Vec2,
+    /// Palette position is `fract(position * color_frequency + color_offset)`.
+    color_offset: f32,
+    color_frequency: f32,
+    /// Animation time in seconds, see [`FragmentShaderProgram::elapsed`].
+    elapsed: f32,
+    /// The WGSL struct is aligned to its `vec2f`.
+    _padding: u32,
+}
+
+/// Escape data of a sample, `Sample` in the WGSL sources.
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct Sample {
+    z: Vec2,
+    iter: u32,
+    distance: f32,
+    trap: f32,
+    period: u32,
+    stripe: f32,
+    /// The WGSL struct is aligned to its `vec2f`.
+    _padding: u32,
+}
+
+const SAMPLE_SIZE: u64 = std::mem::size_of::<Sample>() as u64;
+
+/// Workgroup size of the iteration stage along each axis.
+const WORKGROUP_SIZE: u32 = 8;
+
+static NEXT_ORBIT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Orbit of the view center computed on the CPU in double precision. With it the shader only
+/// needs to iterate each pixel's small difference from the reference in single precision.
+#[derive(Debug)]
+struct ReferenceOrbit {
+    id: u64,
+    center: DVec2,
+    max_iter: u32,
+    escape_radius: f32,
+    /// `Z_0 = 0, Z_1 = c, ...` up to `max_iter` or the first escaped value.
+    points: Vec<Vec2>,
+}
+
+impl ReferenceOrbit {
+    fn new(controls: &Controls) -> Self {
+        let c = controls.center;
+        let max_iter = controls.iterations();
+        let bailout = (controls.escape_radius as f64).powi(2);
+
+        let mut z = DVec2::ZERO;
+        let mut points = vec![z.as_vec2()];
+        for _ in 0..max_iter {
+            z = DVec2::new(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+            points.push(z.as_vec2());
+            if z.length_squared() > bailout {
+                break;
+            }
+        }
+
+        Self {
+            id: NEXT_ORBIT_ID.fetch_add(1, Ordering::Relaxed),
+            center: c,
+            max_iter,
+            escape_radius: controls.escape_radius,
+            points,
+        }
+    }
+
+    fn matches(&self, controls: &Controls) -> bool {
+        self.center == controls.center
+            && self.max_iter == controls.iterations()
+            && self.escape_radius == controls.escape_radius
+    }
+}
+
+/// Last value written to a uniform buffer, so that identical values aren't written again.
+struct Uploaded<T> {
+    value: Option<T>,
+}
+
+impl<T: bytemuck::Pod> Uploaded<T> {
+    fn new() -> Self {
+        Self { value: None }
+    }
+
+    /// Records `value` as uploaded, returning whether it differs from the last one and has to
+    /// be written.
+    fn update(&mut self, value: &T) -> bool {
+        let unchanged = self
+            .value
+            .is_some_and(|last| bytemuck::bytes_of(&last) == bytemuck::bytes_of(value));
+        self.value = Some(*value);
+        !unchanged
+    }
+
+    /// Makes the next `update` report a change, e.g. after the buffers it describes were lost.
+    fn invalidate(&mut self) {
+        self.value = None;
+    }
+}
+
+/// Measures the GPU time of the iteration pass with timestamp queries. Each measurement is
+/// written in `render`, then mapped and read back in the following `prepare` calls.
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    state: TimerState,
+    /// Set once `readback_buffer` is mapped.
+    mapped: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimerState {
+    Idle,
+    /// Timestamps are to be written by the next iteration pass.
+    Armed,
+    Mapping,
+}
+
+impl GpuTimer {
+    /// `None` if the device lacks timestamp queries.
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let size = 2 * std::mem::size_of::<u64>() as u64;
+        Some(Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("shader_quad timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("shader_quad timestamp resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("shader_quad timestamp readback"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+            state: TimerState::Idle,
+            mapped: Arc::default(),
+        })
+    }
+
+    /// Advances the measurement, returning the time in seconds once one was read back.
+    /// `measure` tells whether the iteration pass runs this frame.
+    fn update(&mut self, device: &wgpu::Device, measure: bool) -> Option<f32> {
+        match self.state {
+            TimerState::Idle => {}
+            TimerState::Armed => {
+                let mapped = self.mapped.clone();
+                self.readback_buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        mapped.store(result.is_ok(), Ordering::Release);
+                    });
+                self.state = TimerState::Mapping;
+                return None;
+            }
+            TimerState::Mapping => {
+                device.poll(wgpu::Maintain::Poll);
+                if !self.mapped.swap(false, Ordering::Acquire) {
+                    return None;
+                }
+            }
+        }
+
+        let time = (self.state == TimerState::Mapping).then(|| self.read_mapped());
+        self.state = if measure {
+            TimerState::Armed
+        } else {
+            TimerState::Idle
+        };
+        time
+    }
+
+    /// Timestamp writes for the iteration pass, if armed.
+    fn pass_writes(&self) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        (self.state == TimerState::Armed).then_some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        })
+    }
+
+    /// Copies the timestamps written by the iteration pass where they can be read back.
+    fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.state == TimerState::Armed {
+            self.copy_timestamps(encoder);
+        }
+    }
+
+    fn copy_timestamps(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.resolve_buffer.size(),
+        );
+    }
+
+    /// Time between the two timestamps in the mapped `readback_buffer`, in seconds. Unmaps it.
+    fn read_mapped(&self) -> f32 {
+        let timestamps: [u64; 2] =
+            bytemuck::pod_read_unaligned(&self.readback_buffer.slice(..).get_mapped_range());
+        self.readback_buffer.unmap();
+        timestamps[1].saturating_sub(timestamps[0]) as f32 * self.period / 1e9
+    }
+
+    /// Waits for timestamps copied by a submitted [`GpuTimer::copy_timestamps`], for headless
+    /// rendering outside the `Idle`, `Armed`, `Mapping` cycle.
+    #[cfg(any(test, feature = "headless"))]
+    fn read_blocking(&self, device: &wgpu::Device) -> Result<f32, String> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|err| err.to_string())?
+            .map_err(|err| err.to_string())?;
+        Ok(self.read_mapped())
+    }
+}
+
+/// Escape data of a pixel, read back from the iteration stage by
+/// [`FragmentShaderProgram::inspect`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelInfo {
+    /// Point on the complex plane under the cursor when it was inspected.
+    pub point: DVec2,
+    /// Continuous escape count, as used by smooth coloring, `None` if the orbit didn't escape.
+    pub iterations: Option<f32>,
+    /// `|z|` when the iteration stopped.
+    pub magnitude: f32,
+}
+
+/// Request to read back the escape data of a pixel, answered in `prepare`.
+#[derive(Debug)]
+struct Inspection {
+    /// Position within the view bounds, in logical pixels.
+    pos: Vec2,
+    point: DVec2,
+    max_iter: u32,
+    escape_radius: f32,
+    power: f32,
+    /// The error instead if the sample couldn't be read back.
+    result: Mutex<Option<Result<PixelInfo, String>>>,
+}
+
+impl Inspection {
+    fn resolve(&self, sample: Sample) -> PixelInfo {
+        let magnitude = sample.z.length();
+        let iterations = (sample.iter < self.max_iter).then(|| {
+            let log_ratio = magnitude.ln() / self.escape_radius.ln();
+            sample.iter as f32 + 1.0 - log_ratio.ln() / self.power.ln()
+        });
+        PixelInfo {
+            point: self.point,
+            iterations,
+            magnitude,
+        }
+    }
+}
+
+/// Copies one sample of the iteration stage where it can be read back for an [`Inspection`].
+/// Copied in `render`, then mapped and read in the following `prepare` calls, like
+/// [`GpuTimer`].
+struct Readback {
+    buffer: wgpu::Buffer,
+    inspection: Arc<Inspection>,
+    /// Offset of the sample in the samples buffer.
+    offset: u64,
+    /// Whether the copy was submitted and the buffer is being mapped.
+    mapping: bool,
+    /// Set once mapping `buffer` finished, with the error if it failed.
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+}
+
+impl Readback {
+    fn new(device: &wgpu::Device, inspection: Arc<Inspection>, offset: u64) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("shader_quad inspection readback"),
+                size: SAMPLE_SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            inspection,
+            offset,
+            mapping: false,
+            mapped: Arc::default(),
+        }
+    }
+
+    /// Advances the readback, returning whether it is done and the result was stored in the
+    /// inspection.
+    fn update(&mut self, device: &wgpu::Device) -> bool {
+        if !self.mapping {
+            let mapped = self.mapped.clone();
+            self.buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    *mapped.lock().unwrap() = Some(result);
+                });
+            self.mapping = true;
+            return false;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        let Some(mapped) = self.mapped.lock().unwrap().take() else {
+            return false;
+        };
+
+        let result = mapped
+            .map(|()| {
+                let sample: Sample =
+                    bytemuck::pod_read_unaligned(&self.buffer.slice(..).get_mapped_range());
+                self.buffer.unmap();
+                self.inspection.resolve(sample)
+            })
+            .map_err(|err| format!("Failed to read back the inspected pixel: {err}"));
+        *self.inspection.result.lock().unwrap() = Some(result);
+        true
+    }
+}
+
+struct FragmentShaderInstance {
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    orbit_buffer: wgpu::Buffer,
+    orbit_bind_group: wgpu::BindGroup,
+    orbit_id: Option<u64>,
+    color_uniform_buffer: wgpu::Buffer,
+    /// Escape data of every sample, written by the iteration stage.
+    samples_buffer: wgpu::Buffer,
+    samples_bind_group: wgpu::BindGroup,
+    color_bind_group: wgpu::BindGroup,
+    grid_size: UVec2,
+    /// Escape iteration histogram, cleared before it is counted.
+    histogram_buffer: wgpu::Buffer,
+    histogram_bind_group: wgpu::BindGroup,
+    cdf_bind_group: wgpu::BindGroup,
+    /// Number of iterations the histogram has buckets for.
+    histogram_len: u32,
+    /// Whether the histogram was counted from the samples currently in `samples_buffer`.
+    histogram_current: bool,
+    /// Whether the histogram has to be counted this frame.
+    histogram_pending: bool,
+    /// Uniforms of the samples currently in `samples_buffer`.
+    computed: Uploaded<Uniforms>,
+    /// Whether the iteration stage has to run this frame.
+    compute_pending: bool,
+    /// Tiled iteration still in progress, see [`FragmentShaderPipeline::iterate_tiles`].
+    tiling: Option<Tiling>,
+    /// Time a tile took to iterate, measured on the last submitted batch of tiles.
+    tile_time: Arc<Mutex<Option<Duration>>>,
+    /// Color uniforms of the image currently in `cache`.
+    colored: Uploaded<ColorUniforms>,
+    /// Whether `cache` has to be colored again this frame.
+    color_pending: bool,
+    /// Last rendered image, blitted as is while nothing changes. Reduced resolution while the
+    /// view is being interacted with.
+    cache: Option<Offscreen>,
+    accumulation: Option<Accumulation>,
+    /// `None` without timestamp query support.
+    timer: Option<GpuTimer>,
+    /// Vertices of the orbit trace in clip space, grown as needed.
+    trace_buffer: Option<wgpu::Buffer>,
+    /// Parts of `trace_buffer` to draw as separate line strips, see [`trace_runs`].
+    trace_runs: Vec<Range<u32>>,
+    readback: Option<Readback>,
+}
+
+/// Progress of an iteration split into tiles, see [`FragmentShaderPipeline::iterate_tiles`].
+#[derive(Debug, Clone, Copy)]
+struct Tiling {
+    tiles: u32,
+    /// Index of the next tile to iterate, row by row.
+    next: u32,
+}
+
+/// Tiles of the view iterated so far, while the iteration is spread over several frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileProgress {
+    pub done: u32,
+    pub total: u32,
+}
+
+impl TileProgress {
+    /// Whether tiles are left for the next frames.
+    pub fn pending(&self) -> bool {
+        self.done < self.total
+    }
+}
+
+impl FragmentShaderInstance {
+    /// Schedules what depends on the samples once the iteration stage wrote them outside of
+    /// `update`.
+    fn samples_changed(&mut self) {
+        self.color_pending = true;
+        self.histogram_pending = self.histogram_current;
+    }
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue, layouts: &BindGroupLayouts) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad uniform buffer"),
+            size: generic::uniform_buffer_size(std::mem::size_of::<Uniforms>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad uniform bind group"),
+            layout: &layouts.uniforms,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let (orbit_buffer, orbit_bind_group) = Self::create_orbit_buffer(device, &layouts.orbit, 1);
+
+        let color_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad color uniform buffer"),
+            size: generic::uniform_buffer_size(std::mem::size_of::<ColorUniforms>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let grid_size = UVec2::ONE;
+        let (samples_buffer, samples_bind_group, color_bind_group) =
+            Self::create_samples_buffer(device, layouts, &color_uniform_buffer, grid_size);
+
+        let histogram_len = 1;
+        let (histogram_buffer, histogram_bind_group, cdf_bind_group) =
+            Self::create_histogram_buffers(device, layouts, histogram_len);
+
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            orbit_buffer,
+            orbit_bind_group,
+            orbit_id: None,
+            color_uniform_buffer,
+            samples_buffer,
+            samples_bind_group,
+            color_bind_group,
+            grid_size,
+            histogram_buffer,
+            histogram_bind_group,
+            cdf_bind_group,
+            histogram_len,
+            histogram_current: false,
+            histogram_pending: false,
+            computed: Uploaded::new(),
+            compute_pending: false,
+            tiling: None,
+            tile_time: Arc::default(),
+            colored: Uploaded::new(),
+            color_pending: false,
+            cache: None,
+            accumulation: None,
+            timer: GpuTimer::new(device, queue),
+            trace_buffer: None,
+            trace_runs: Vec::new(),
+            readback: None,
+        }
+    }
+
+    fn create_samples_buffer(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        color_uniform_buffer: &wgpu::Buffer,
+        grid_size: UVec2,
+    ) -> (wgpu::Buffer, wgpu::BindGroup, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad samples buffer"),
+            size: grid_size.element_product() as u64 * SAMPLE_SIZE,
+            // Copied from for inspection.
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let samples_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad samples bind group"),
+            layout: &layouts.samples,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad color bind group"),
+            layout: &layouts.color,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: color_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        (buffer, samples_bind_group, color_bind_group)
+    }
+
+    /// Reallocates the samples buffer if the sample grid changed size.
+    fn update_grid(&mut self, device: &wgpu::Device, layouts: &BindGroupLayouts, grid_size: UVec2) {
+        if self.grid_size == grid_size {
+            return;
+        }
+
+        (
+            self.samples_buffer,
+            self.samples_bind_group,
+            self.color_bind_group,
+        ) = Self::create_samples_buffer(device, layouts, &self.color_uniform_buffer, grid_size);
+        self.grid_size = grid_size;
+        self.computed.invalidate();
+    }
+
+    fn create_histogram_buffers(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        len: u32,
+    ) -> (wgpu::Buffer, wgpu::BindGroup, wgpu::BindGroup) {
+        let size = len as u64 * std::mem::size_of::<u32>() as u64;
+        let histogram = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad histogram buffer"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let cdf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad cdf buffer"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let histogram_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad histogram bind group"),
+            layout: &layouts.histogram,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: histogram.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: cdf.as_entire_binding(),
+                },
+            ],
+        });
+
+        let cdf_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad cdf bind group"),
+            layout: &layouts.cdf,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: cdf.as_entire_binding(),
+            }],
+        });
+
+        (histogram, histogram_bind_group, cdf_bind_group)
+    }
+
+    /// Reallocates the histogram if it has fewer than `max_iter` buckets.
+    fn update_histogram(
+        &mut self,
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        max_iter: u32,
+    ) {
+        if max_iter <= self.histogram_len {
+            return;
+        }
+
+        (
+            self.histogram_buffer,
+            self.histogram_bind_group,
+            self.cdf_bind_group,
+        ) = Self::create_histogram_buffers(device, layouts, max_iter);
+        self.histogram_len = max_iter;
+        self.histogram_current = false;
+    }
+
+    fn create_orbit_buffer(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        len: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shader_quad reference orbit buffer"),
+            size: (len * std::mem::size_of::<Vec2>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad reference orbit bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        (buffer, bind_group)
+    }
+
+    fn update_orbit(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        orbit: &ReferenceOrbit,
+    ) {
+        if self.orbit_id == Some(orbit.id) {
+            return;
+        }
+
+        let size = std::mem::size_of_val(orbit.points.as_slice()) as u64;
+        if size > self.orbit_buffer.size() {
+            (self.orbit_buffer, self.orbit_bind_group) =
+                Self::create_orbit_buffer(device, layout, orbit.points.len());
+        }
+
+        queue.write_buffer(&self.orbit_buffer, 0, bytemuck::cast_slice(&orbit.points));
+        self.orbit_id = Some(orbit.id);
+        self.computed.invalidate();
+    }
+}
+
+/// Format of the texture samples are accumulated into in progressive mode.
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Offscreen color texture a widget's view is rendered to before being copied to the target.
+struct Offscreen {
+    size: Size<u32>,
+    view: wgpu::TextureView,
+    blit_bind_group: wgpu::BindGroup,
+}
+
+impl Offscreen {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        size: Size<u32>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shader_quad offscreen texture"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ACCUMULATION_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let blit_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad offscreen bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        Self {
+            size,
+            view,
+            blit_bind_group,
+        }
+    }
+}
+
+/// Texture collecting jittered samples of a widget's view, averaged over frames.
+struct Accumulation {
+    target: Offscreen,
+    /// Frame index of the last accumulated sample.
+    frame_index: u32,
+    /// Number of samples accumulated so far.
+    samples: u32,
+    /// Whether a new sample has to be rendered this frame.
+    pending: bool,
+}
+
+impl Accumulation {
+    fn new(target: Offscreen) -> Self {
+        Self {
+            target,
+            frame_index: 0,
+            samples: 0,
+            pending: true,
+        }
+    }
+
+    /// Blend factor averaging the pending sample with the ones already accumulated.
+    fn blend_factor(&self) -> f64 {
+        1.0 / self.samples as f64
+    }
+}
+
+/// Bind group layouts of [`FragmentShaderPipeline`], needed to create per-instance bind groups.
+struct BindGroupLayouts {
+    uniforms: wgpu::BindGroupLayout,
+    orbit: wgpu::BindGroupLayout,
+    samples: wgpu::BindGroupLayout,
+    histogram: wgpu::BindGroupLayout,
+    color: wgpu::BindGroupLayout,
+    cdf: wgpu::BindGroupLayout,
+    blit: wgpu::BindGroupLayout,
+}
+
+impl BindGroupLayouts {
+    fn new(device: &wgpu::Device) -> Self {
+        let layout = |label, entries: &[wgpu::BindGroupLayoutEntry]| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries,
+            })
+        };
+        let buffer = |binding, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let fragment = wgpu::ShaderStages::FRAGMENT;
+
+        Self {
+            uniforms: layout(
+                "shader_quad uniform bind group layout",
+                &[buffer(0, compute, wgpu::BufferBindingType::Uniform)],
+            ),
+            orbit: layout(
+                "shader_quad reference orbit bind group layout",
+                &[buffer(
+                    0,
+                    compute,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                )],
+            ),
+            samples: layout(
+                "shader_quad samples bind group layout",
+                &[buffer(
+                    0,
+                    compute,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                )],
+            ),
+            histogram: layout(
+                "shader_quad histogram bind group layout",
+                &[
+                    buffer(
+                        0,
+                        compute,
+                        wgpu::BufferBindingType::Storage { read_only: false },
+                    ),
+                    buffer(
+                        1,
+                        compute,
+                        wgpu::BufferBindingType::Storage { read_only: false },
+                    ),
+                ],
+            ),
+            color: layout(
+                "shader_quad color bind group layout",
+                &[
+                    buffer(0, fragment, wgpu::BufferBindingType::Uniform),
+                    buffer(
+                        1,
+                        fragment,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                    ),
+                ],
+            ),
+            cdf: layout(
+                "shader_quad cdf bind group layout",
+                &[buffer(
+                    0,
+                    fragment,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                )],
+            ),
+            blit: layout(
+                "shader_quad blit bind group layout",
+                &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: fragment,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: fragment,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            ),
+        }
+    }
+}
+
+/// Pipelines shared by all shader widgets, with buffers kept per widget instance.
+///
+/// Rendering is split into an iteration stage, a compute pass writing the escape data of every
+/// sample to a storage buffer, and a coloring stage mapping those to colors. The iteration stage
+/// only runs when its [`Uniforms`] change, so recoloring is cheap. So do the histogram passes
+/// for histogram coloring, following it.
+struct FragmentShaderPipeline {
+    format: wgpu::TextureFormat,
+    compute_pipeline: wgpu::ComputePipeline,
+    /// Counts samples by escape iteration.
+    histogram_pipeline: wgpu::ComputePipeline,
+    /// Turns the histogram into a cumulative distribution.
+    cdf_pipeline: wgpu::ComputePipeline,
+    /// Colors samples into an [`Offscreen`] texture, blending with its contents by the blend
+    /// constant.
+    pipeline: wgpu::RenderPipeline,
+    /// Copies an [`Offscreen`] texture to the target.
+    blit_pipeline: wgpu::RenderPipeline,
+    /// Draws orbit traces over the view.
+    trace_pipeline: wgpu::RenderPipeline,
+    layouts: BindGroupLayouts,
+    sampler: wgpu::Sampler,
+    /// [`Palette`] the coloring stage samples, shared by all widgets.
+    palette_texture: wgpu::Texture,
+    palette_bind_group: wgpu::BindGroup,
+    /// Palette last written to `palette_texture`.
+    palette: Option<Palette>,
+    instances: HashMap<u64, FragmentShaderInstance>,
+    prepared: PreparedIds,
+    /// [`ShaderSource`] the iteration stage was last loaded from, `None` for `shader.wgsl`.
+    shader_id: Option<u64>,
+    /// Device everything was created on, to tell when the renderer moved to a new one.
+    device: wgpu::Id<wgpu::Device>,
+    /// Time to spend iterating tiles per frame, at least one tile is iterated regardless.
+    tile_budget: Duration,
+}
+
+/// Iteration stage source loaded at runtime, replacing `shader.wgsl`.
+#[derive(Debug)]
+struct ShaderSource {
+    id: u64,
+    code: String,
+    /// Set by the pipeline once it tried the source, with the error if it didn't compile.
+    result: Mutex<Option<Result<(), String>>>,
+}
+
+static NEXT_SHADER_ID: AtomicU64 = AtomicU64::new(0);
+
+impl ShaderSource {
+    fn new(code: String) -> Self {
+        Self {
+            id: NEXT_SHADER_ID.fetch_add(1, Ordering::Relaxed),
+            code,
+            result: Mutex::new(None),
+        }
+    }
+}
+
+/// Rendering failures, reported by `prepare` and the device's error handler, and forwarded to
+/// the application by [`render_errors`].
+#[derive(Debug, Default)]
+pub struct RenderErrors {
+    /// Last reported error, `None` once rendering works again.
+    last: Mutex<Option<String>>,
+    /// Set by a runtime error to have the pipeline rebuilt.
+    failed: AtomicBool,
+    /// Set when the device is lost, e.g. by a GPU reset, as nothing built on it works anymore.
+    lost: AtomicBool,
+    listener: Mutex<Option<iced::futures::channel::mpsc::UnboundedSender<Option<String>>>>,
+}
+
+impl RenderErrors {
+    fn report(&self, error: Option<String>) {
+        let mut last = self.last.lock().unwrap();
+        if *last == error {
+            return;
+        }
+        if let Some(listener) = &*self.listener.lock().unwrap() {
+            let _ = listener.unbounded_send(error.clone());
+        }
+        *last = error;
+    }
+
+    fn fail(&self, error: String) {
+        self.failed.store(true, Ordering::Relaxed);
+        self.report(Some(error));
+    }
+
+    fn take_failure(&self) -> bool {
+        self.failed.swap(false, Ordering::Relaxed)
+    }
+
+    fn lose(&self, message: String) {
+        self.lost.store(true, Ordering::Relaxed);
+        self.report(Some(format!("GPU device lost: {message}")));
+    }
+
+    fn take_loss(&self) -> bool {
+        self.lost.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Sends [`RenderErrors`] as they are reported, and `None` when rendering recovers.
+pub fn render_errors(errors: Arc<RenderErrors>) -> Subscription<Option<String>> {
+    use iced::futures::{SinkExt as _, StreamExt as _};
+
+    struct RenderErrorsId;
+
+    iced::subscription::channel(
+        std::any::TypeId::of::<RenderErrorsId>(),
+        1,
+        |mut output| async move {
+            let (sender, mut receiver) = iced::futures::channel::mpsc::unbounded();
+            // Holding the lock, no error is reported between reading the last one and
+            // listening for the next.
+            let last = {
+                let last = errors.last.lock().unwrap();
+                *errors.listener.lock().unwrap() = Some(sender);
+                last.clone()
+            };
+
+            if last.is_some() {
+                let _ = output.send(last).await;
+            }
+            while let Some(error) = receiver.next().await {
+                let _ = output.send(error).await;
+            }
+
+            std::future::pending().await
+        },
+    )
+}
+
+/// Runs `create` in a validation error scope, so that pipelines that don't build, e.g. from a
+/// shader that doesn't compile, are reported to `errors` rather than bringing the application
+/// down.
+fn create_in_error_scope<T>(
+    device: &wgpu::Device,
+    errors: &RenderErrors,
+    create: impl FnOnce() -> T,
+) -> Result<T, String> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+    match futures::executor::block_on(device.pop_error_scope()) {
+        Some(err) => {
+            let err = format!("Failed to create the pipeline: {err}");
+            errors.report(Some(err.clone()));
+            Err(err)
+        }
+        None => Ok(created),
+    }
+}
+
+/// Device and surface format a [`FragmentShaderPipeline`] last failed to build for.
+#[derive(Debug, PartialEq)]
+struct FailedBuild {
+    device: wgpu::Id<wgpu::Device>,
+    format: wgpu::TextureFormat,
+}
+
+/// Stores a rebuilt pipeline, unless it failed to build while the last one still works, so
+/// that views keep being drawn until a new one builds.
+fn store_pipeline<T: Send + 'static>(storage: &mut shader::Storage, pipeline: Result<T, String>) {
+    let working = matches!(storage.get::<Result<T, String>>(), Some(Ok(_)));
+    if pipeline.is_ok() || !working {
+        storage.store(pipeline);
+    }
+}
+
+/// Sources of the passes drawing to the target, with the sRGB encoding they share.
+const BLIT_SHADER: &str = concat!(include_str!("blit.wgsl"), include_str!("srgb.wgsl"));
+const TRACE_SHADER: &str = concat!(include_str!("trace.wgsl"), include_str!("srgb.wgsl"));
+
+impl FragmentShaderPipeline {
+    /// Builds the pipeline, reporting whether that worked to `errors`. Errors at render time
+    /// are reported there as well from then on.
+    fn create(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        errors: &Arc<RenderErrors>,
+    ) -> Result<Self, String> {
+        let pipeline = create_in_error_scope(device, errors, || Self::new(device, format))?;
+
+        let handler_errors = errors.clone();
+        device.on_uncaptured_error(Box::new(move |err| handler_errors.fail(err.to_string())));
+        let lost_errors = errors.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // Also called when the device is dropped or the callback replaced by a rebuild.
+            if matches!(
+                reason,
+                wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::Destroyed
+            ) {
+                lost_errors.lose(message);
+            }
+        });
+        errors.report(None);
+        Ok(pipeline)
+    }
+
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let layouts = BindGroupLayouts::new(device);
+
+        let (compute_pipeline, histogram_pipeline, cdf_pipeline) =
+            Self::create_iteration_pipelines(device, &layouts, include_str!("shader.wgsl"));
+
+        let color_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderPipeline color shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "color.wgsl"
+            ))),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderPipeline layout"),
+            bind_group_layouts: &[&layouts.color, &layouts.cdf, &layouts.blit],
+            push_constant_ranges: &[],
+        });
+
+        // Running average: dst = src * c + dst * (1 - c), with c = 1 / samples.
+        // A constant of 1 simply overwrites the texture.
+        let average = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("FragmentShaderPipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &color_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &color_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: ACCUMULATION_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: average,
+                        alpha: average,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let (blit_pipeline, trace_pipeline) =
+            Self::create_target_pipelines(device, &layouts, format);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shader_quad blit sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shader_quad palette texture"),
+            size: wgpu::Extent3d {
+                width: PALETTE_SIZE,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Sampled as linear colors, so that the gradient is interpolated in linear space.
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Shaped like an offscreen texture's, so it shares their layout.
+        let palette_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shader_quad palette bind group"),
+            layout: &layouts.blit,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            format,
+            compute_pipeline,
+            histogram_pipeline,
+            cdf_pipeline,
+            pipeline,
+            blit_pipeline,
+            trace_pipeline,
+            layouts,
+            sampler,
+            palette_texture,
+            palette_bind_group,
+            palette: None,
+            instances: HashMap::new(),
+            prepared: PreparedIds::default(),
+            shader_id: None,
+            device: device.global_id(),
+            tile_budget: TILE_FRAME_BUDGET,
+        }
+    }
+
+    /// Render pipelines drawing to the target, which depend on its format: blit and trace.
+    fn create_target_pipelines(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        format: wgpu::TextureFormat,
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+        // Colors are linear up to the target, which encodes them itself if it is sRGB.
+        let target_entry_point = if format.is_srgb() {
+            "fs_main"
+        } else {
+            "fs_encode_srgb"
+        };
+
+        let blit_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderPipeline blit shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(BLIT_SHADER)),
+        });
+
+        let blit_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderPipeline blit layout"),
+            bind_group_layouts: &[&layouts.blit],
+            push_constant_ranges: &[],
+        });
+
+        let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("FragmentShaderPipeline blit"),
+            layout: Some(&blit_layout),
+            vertex: wgpu::VertexState {
+                module: &blit_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &blit_shader,
+                entry_point: target_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let trace_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderPipeline trace shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(TRACE_SHADER)),
+        });
+
+        let trace_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderPipeline trace layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let trace_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("FragmentShaderPipeline trace"),
+            layout: Some(&trace_layout),
+            vertex: wgpu::VertexState {
+                module: &trace_shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vec2>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &trace_shader,
+                entry_point: target_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        (blit_pipeline, trace_pipeline)
+    }
+
+    /// Writes `palette` to the palette texture if it changed. Colors don't depend on the
+    /// iteration, so only the coloring stage has to run again.
+    fn set_palette(&mut self, queue: &wgpu::Queue, palette: &Palette) {
+        if self.palette.as_ref() == Some(palette) {
+            return;
+        }
+        queue.write_texture(
+            self.palette_texture.as_image_copy(),
+            &palette.pixels(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(PALETTE_SIZE * 4),
+                rows_per_image: None,
+            },
+            self.palette_texture.size(),
+        );
+        self.palette = Some(palette.clone());
+        for instance in self.instances.values_mut() {
+            instance.colored = Uploaded::new();
+        }
+    }
+
+    /// Rebuilds the pipelines drawing to the target for a new `format`, keeping the widget
+    /// instances and their images.
+    fn set_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        (self.blit_pipeline, self.trace_pipeline) =
+            Self::create_target_pipelines(device, &self.layouts, format);
+        self.format = format;
+    }
+
+    /// Compute pipelines of the iteration stage: iteration, histogram and cumulative
+    /// distribution.
+    fn create_iteration_pipelines(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        source: &str,
+    ) -> (
+        wgpu::ComputePipeline,
+        wgpu::ComputePipeline,
+        wgpu::ComputePipeline,
+    ) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FragmentShaderPipeline shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(source)),
+        });
+
+        let compute_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderPipeline compute layout"),
+            bind_group_layouts: &[&layouts.uniforms, &layouts.orbit, &layouts.samples],
+            push_constant_ranges: &[],
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("FragmentShaderPipeline compute"),
+            layout: Some(&compute_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let histogram_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FragmentShaderPipeline histogram layout"),
+            bind_group_layouts: &[
+                &layouts.uniforms,
+                &layouts.orbit,
+                &layouts.samples,
+                &layouts.histogram,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let histogram_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("FragmentShaderPipeline histogram"),
+            layout: Some(&histogram_layout),
+            module: &shader,
+            entry_point: "histogram_main",
+        });
+
+        let cdf_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("FragmentShaderPipeline cdf"),
+            layout: Some(&histogram_layout),
+            module: &shader,
+            entry_point: "cdf_main",
+        });
+
+        (compute_pipeline, histogram_pipeline, cdf_pipeline)
+    }
+
+    /// Rebuilds the iteration stage from `shader`, keeping the current one if it doesn't
+    /// compile. The outcome is reported in [`ShaderSource::result`].
+    fn load_shader(&mut self, device: &wgpu::Device, shader: &ShaderSource) {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipelines = Self::create_iteration_pipelines(device, &self.layouts, &shader.code);
+        let result = match futures::executor::block_on(device.pop_error_scope()) {
+            Some(err) => Err(err.to_string()),
+            None => {
+                (
+                    self.compute_pipeline,
+                    self.histogram_pipeline,
+                    self.cdf_pipeline,
+                ) = pipelines;
+                // The samples were computed by the previous shader.
+                self.invalidate();
+                Ok(())
+            }
+        };
+
+        self.shader_id = Some(shader.id);
+        *shader.result.lock().unwrap() = Some(result);
+    }
+
+    /// Makes every instance render again on its next frame, even if nothing changed.
+    fn invalidate(&mut self) {
+        for instance in self.instances.values_mut() {
+            instance.computed.invalidate();
+            instance.colored.invalidate();
+        }
+    }
+
+    /// Uploads the uniforms, scheduling the iteration and coloring stages if their parameters
+    /// changed.
+    fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: u64,
+        uniforms: &Uniforms,
+        color_uniforms: &ColorUniforms,
+        orbit: Option<&ReferenceOrbit>,
+    ) {
+        self.prepared.prepare(id, &mut self.instances);
+        let instance = self
+            .instances
+            .entry(id)
+            .or_insert_with(|| FragmentShaderInstance::new(device, queue, &self.layouts));
+
+        if let Some(orbit) = orbit {
+            instance.update_orbit(device, queue, &self.layouts.orbit, orbit);
+        }
+        instance.update_grid(device, &self.layouts, uniforms.grid_size);
+        instance.update_histogram(device, &self.layouts, uniforms.max_iter);
+
+        instance.compute_pending = instance.computed.update(uniforms);
+        if instance.compute_pending {
+            queue.write_buffer(&instance.uniform_buffer, 0, bytemuck::bytes_of(uniforms));
+        }
+
+        // Only counted again when the samples change or after it was disabled.
+        let histogram = color_uniforms.histogram != 0;
+        instance.histogram_pending =
+            histogram && (instance.compute_pending || !instance.histogram_current);
+        instance.histogram_current =
+            histogram || (instance.histogram_current && !instance.compute_pending);
+
+        let recolor = instance.colored.update(color_uniforms);
+        instance.color_pending = instance.compute_pending || recolor;
+        if recolor {
+            queue.write_buffer(
+                &instance.color_uniform_buffer,
+                0,
+                bytemuck::bytes_of(color_uniforms),
+            );
+        }
+    }
+
+    /// Advances the readback of an inspected sample of `id`, starting one for `request`, the
+    /// inspection and the sample's index, if none is in flight.
+    fn update_readback(
+        &mut self,
+        device: &wgpu::Device,
+        id: u64,
+        request: Option<(&Arc<Inspection>, u64)>,
+    ) {
+        let Some(instance) = self.instances.get_mut(&id) else {
+            return;
+        };
+
+        match &mut instance.readback {
+            Some(readback) => {
+                if readback.update(device) {
+                    instance.readback = None;
+                }
+            }
+            None => {
+                if let Some((inspection, index)) = request {
+                    let offset = index.min(instance.grid_size.element_product() as u64 - 1);
+                    instance.readback = Some(Readback::new(
+                        device,
+                        inspection.clone(),
+                        offset * SAMPLE_SIZE,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Uploads the orbit trace of `id`, given in clip space.
+    fn update_trace(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: u64,
+        points: &[Vec2],
+    ) {
+        let Some(instance) = self.instances.get_mut(&id) else {
+            return;
+        };
+        instance.trace_runs = trace_runs(points);
+        if instance.trace_runs.is_empty() {
+            return;
+        }
+
+        let size = std::mem::size_of_val(points) as u64;
+        let buffer = match instance.trace_buffer.take() {
+            Some(buffer) if buffer.size() >= size => buffer,
+            _ => device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("shader_quad trace buffer"),
+                size,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        };
+        queue.write_buffer(&buffer, 0, bytemuck::cast_slice(points));
+        instance.trace_buffer = Some(buffer);
+    }
+
+    /// Runs a pending iteration stage right away in `tiles * tiles` separate submissions, rather
+    /// than in a single pass in `render`. Each tile is a whole number of workgroups. As many tiles
+    /// as fit in `tile_budget` by the time earlier ones took are submitted per frame, the rest
+    /// are iterated in the next frames, over the samples of the previous view, unless the
+    /// uniforms change and the iteration starts over.
+    fn iterate_tiles(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: u64,
+        tiles: u32,
+    ) -> TileProgress {
+        let Some(instance) = self.instances.get_mut(&id) else {
+            return TileProgress::default();
+        };
+        let restart =
+            instance.compute_pending || instance.tiling.is_some_and(|tiling| tiling.tiles != tiles);
+        if restart {
+            instance.tiling = (tiles > 1).then_some(Tiling { tiles, next: 0 });
+            // A single pass in `render` finishes a tiling that was switched off.
+            instance.compute_pending = tiles <= 1;
+            instance.samples_changed();
+        }
+        let Some(tiling) = &mut instance.tiling else {
+            return TileProgress::default();
+        };
+
+        let grid_size = instance.grid_size;
+        let tile_size = ((grid_size + tiles - 1) / tiles).max(UVec2::ONE);
+        let workgroups = (tile_size + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        let tile_size = workgroups * WORKGROUP_SIZE;
+        let columns = grid_size.x.div_ceil(tile_size.x);
+        let total = columns * grid_size.y.div_ceil(tile_size.y);
+        // A single tile is iterated until there is a time to go by.
+        let batch = instance.tile_time.lock().unwrap().map_or(1, |time| {
+            (self.tile_budget.as_secs_f64() / time.as_secs_f64().max(f64::EPSILON)) as u32
+        });
+        let end = total.min(tiling.next + batch.max(1));
+        let batch = end - tiling.next;
+        let submitted = Instant::now();
+        while tiling.next < end {
+            let offset = UVec2::new(tiling.next % columns, tiling.next / columns) * tile_size;
+            queue.write_buffer(
+                &instance.uniform_buffer,
+                std::mem::offset_of!(Uniforms, tile_offset) as u64,
+                bytemuck::bytes_of(&offset),
+            );
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("shader_quad iterate tile"),
+            });
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("shader_quad iterate tile"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.compute_pipeline);
+            pass.set_bind_group(0, &instance.uniform_bind_group, &[]);
+            pass.set_bind_group(1, &instance.orbit_bind_group, &[]);
+            pass.set_bind_group(2, &instance.samples_bind_group, &[]);
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+            drop(pass);
+            queue.submit([encoder.finish()]);
+            tiling.next += 1;
+        }
+        // Done once the next frames' submissions look for finished work, so tiles that take
+        // less than a frame are overestimated, which only makes the batches grow slower.
+        let tile_time = instance.tile_time.clone();
+        queue.on_submitted_work_done(move || {
+            *tile_time.lock().unwrap() = Some(submitted.elapsed() / batch);
+        });
+
+        // The uploaded uniforms are only the same as before up to the tile offset.
+        queue.write_buffer(
+            &instance.uniform_buffer,
+            std::mem::offset_of!(Uniforms, tile_offset) as u64,
+            bytemuck::bytes_of(&UVec2::ZERO),
+        );
+        let progress = TileProgress {
+            done: tiling.next,
+            total,
+        };
+        if !progress.pending() {
+            instance.tiling = None;
+        }
+        instance.samples_changed();
+        progress
+    }
+
+    /// Prepares progressive accumulation of `frame_index`-th sample, or disables it for `None`.
+    fn update_accumulation(
+        &mut self,
+        device: &wgpu::Device,
+        id: u64,
+        size: Size<u32>,
+        frame_index: Option<u32>,
+    ) {
+        let Some(frame_index) = frame_index else {
+            if let Some(instance) = self.instances.get_mut(&id) {
+                instance.accumulation = None;
+            }
+            return;
+        };
+
+        let layout = &self.layouts.blit;
+        let sampler = &self.sampler;
+        let instance = self
+            .instances
+            .get_mut(&id)
+            .expect("instance is updated first");
+
+        let accumulation = match &mut instance.accumulation {
+            Some(accumulation) if accumulation.target.size == size => accumulation,
+            accumulation => accumulation.insert(Accumulation::new(Offscreen::new(
+                device, layout, sampler, size,
+            ))),
+        };
+
+        if frame_index == 0 {
+            accumulation.samples = 0;
+        }
+
+        accumulation.pending = accumulation.samples == 0 || accumulation.frame_index != frame_index;
+        if accumulation.pending {
+            accumulation.frame_index = frame_index;
+            accumulation.samples += 1;
+        }
+    }
+
+    /// Renders into a cache texture of the given `size`, or drops it for `None`.
+    fn update_cache(&mut self, device: &wgpu::Device, id: u64, size: Option<Size<u32>>) {
+        let layout = &self.layouts.blit;
+        let sampler = &self.sampler;
+        let Some(instance) = self.instances.get_mut(&id) else {
+            return;
+        };
+
+        instance.cache = size.map(|size| match instance.cache.take() {
+            Some(cache) if cache.size == size => cache,
+            _ => {
+                instance.color_pending = true;
+                Offscreen::new(device, layout, sampler, size)
+            }
+        });
+    }
+
+    /// GPU time of the last measured iteration pass, in seconds, when one was just read back.
+    fn gpu_time(&mut self, device: &wgpu::Device, id: u64) -> Option<f32> {
+        let instance = self.instances.get_mut(&id)?;
+        let measure = instance.compute_pending;
+        instance.timer.as_mut()?.update(device, measure)
+    }
+
+    /// Whether the next `render` draws a new image rather than blitting a cached one.
+    fn renders(&self, id: u64) -> bool {
+        self.instances.get(&id).is_some_and(|instance| {
+            instance.compute_pending
+                || match &instance.accumulation {
+                    Some(accumulation) => accumulation.pending,
+                    None => instance.color_pending,
+                }
+        })
+    }
+
+    /// Renders the view of `id` into `viewport`, showing only the part of it within `clip`.
+    fn render(
+        &self,
+        id: u64,
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        viewport: Rectangle<u32>,
+        clip: Rectangle<u32>,
+    ) {
+        let Some(instance) = self.instances.get(&id) else {
+            return;
+        };
+
+        if instance.compute_pending {
+            let timer = instance.timer.as_ref();
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("shader_quad iterate"),
+                timestamp_writes: timer.and_then(GpuTimer::pass_writes),
+            });
+            pass.set_pipeline(&self.compute_pipeline);
+            pass.set_bind_group(0, &instance.uniform_bind_group, &[]);
+            pass.set_bind_group(1, &instance.orbit_bind_group, &[]);
+            pass.set_bind_group(2, &instance.samples_bind_group, &[]);
+            pass.dispatch_workgroups(
+                instance.grid_size.x.div_ceil(WORKGROUP_SIZE),
+                instance.grid_size.y.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+            drop(pass);
+
+            if let Some(timer) = timer {
+                timer.resolve(encoder);
+            }
+        }
+
+        if instance.histogram_pending {
+            encoder.clear_buffer(&instance.histogram_buffer, 0, None);
+
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("shader_quad histogram"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.histogram_pipeline);
+            pass.set_bind_group(0, &instance.uniform_bind_group, &[]);
+            pass.set_bind_group(1, &instance.orbit_bind_group, &[]);
+            pass.set_bind_group(2, &instance.samples_bind_group, &[]);
+            pass.set_bind_group(3, &instance.histogram_bind_group, &[]);
+            pass.dispatch_workgroups(
+                instance.grid_size.x.div_ceil(WORKGROUP_SIZE),
+                instance.grid_size.y.div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+            pass.set_pipeline(&self.cdf_pipeline);
+            pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        if let Some(readback) = instance
+            .readback
+            .as_ref()
+            .filter(|readback| !readback.mapping)
+        {
+            encoder.copy_buffer_to_buffer(
+                &instance.samples_buffer,
+                readback.offset,
+                &readback.buffer,
+                0,
+                SAMPLE_SIZE,
+            );
+        }
+
+        let Some(accumulation) = &instance.accumulation else {
+            let Some(cache) = &instance.cache else {
+                return;
+            };
+
+            if instance.color_pending {
+                let mut pass = begin_pass(encoder, &cache.view, "shader_quad color");
+                pass.set_blend_constant(wgpu::Color::WHITE);
+                self.draw_colors(&mut pass, &self.pipeline, instance);
+            }
+
+            self.blit(encoder, target, viewport, clip, cache);
+            return;
+        };
+
+        if accumulation.pending {
+            let mut pass = begin_pass(encoder, &accumulation.target.view, "shader_quad accumulate");
+            let blend = accumulation.blend_factor();
+            pass.set_blend_constant(wgpu::Color {
+                r: blend,
+                g: blend,
+                b: blend,
+                a: blend,
+            });
+            self.draw_colors(&mut pass, &self.pipeline, instance);
+        }
+
+        self.blit(encoder, target, viewport, clip, &accumulation.target);
+    }
+
+    /// Draws the orbit trace of `id` over the `viewport` of `target`.
+    fn render_trace(
+        &self,
+        id: u64,
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        viewport: Rectangle<u32>,
+    ) {
+        let Some(instance) = self.instances.get(&id) else {
+            return;
+        };
+        let Some(buffer) = &instance.trace_buffer else {
+            return;
+        };
+        if instance.trace_runs.is_empty() {
+            return;
+        }
+
+        let mut pass = begin_pass(encoder, target, "shader_quad trace");
+        set_viewport(&mut pass, viewport);
+        pass.set_pipeline(&self.trace_pipeline);
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        for run in &instance.trace_runs {
+            pass.draw(run.clone(), 0..1);
+        }
+    }
+
+    /// Copies `source` to the `viewport` of `target`, scaling it to fit, within `clip`.
+    fn blit(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        viewport: Rectangle<u32>,
+        clip: Rectangle<u32>,
+        source: &Offscreen,
+    ) {
+        if clip.width == 0 || clip.height == 0 {
+            return;
+        }
+        let mut pass = begin_pass(encoder, target, "shader_quad blit");
+        set_viewport(&mut pass, viewport);
+        pass.set_scissor_rect(clip.x, clip.y, clip.width, clip.height);
+        pass.set_pipeline(&self.blit_pipeline);
+        pass.set_bind_group(0, &source.blit_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    fn draw_colors<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        instance: &'a FragmentShaderInstance,
+    ) {
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &instance.color_bind_group, &[]);
+        pass.set_bind_group(1, &instance.cdf_bind_group, &[]);
+        pass.set_bind_group(2, &self.palette_bind_group, &[]);
+
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Runs of at least two consecutive trace points within [`TRACE_CLIP`], as vertex ranges. The
+/// lines to points further out would streak across the view.
+fn trace_runs(points: &[Vec2]) -> Vec<Range<u32>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (index, point) in points.iter().enumerate() {
+        let inside = point.abs().cmple(Vec2::splat(TRACE_CLIP)).all();
+        match (inside, start) {
+            (true, None) => start = Some(index as u32),
+            (false, Some(first)) => {
+                runs.push(first..index as u32);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(first) = start {
+        runs.push(first..points.len() as u32);
+    }
+
+    runs.retain(|run| run.len() >= 2);
+    runs
+}
+
+/// Starts a pass keeping the contents of `target`. Passes drawing into the widget's target can't
+/// clear it: it is the whole window, with the widgets drawn before, and a clear ignores the
+/// viewport. Instead, the blit covers every pixel of the viewport, also right after a resize,
+/// when the cached image is recreated at the new size and drawn again first.
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    target: &'a wgpu::TextureView,
+    label: &'static str,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    })
+}
+
+/// Splits `viewport` into the parts left and right of a vertical line at `split`, a fraction of
+/// its width.
+fn split_viewport(viewport: Rectangle<u32>, split: f32) -> (Rectangle<u32>, Rectangle<u32>) {
+    let left = (viewport.width as f32 * split.clamp(0.0, 1.0)).round() as u32;
+    (
+        Rectangle {
+            width: left,
+            ..viewport
+        },
+        Rectangle {
+            x: viewport.x + left,
+            width: viewport.width - left,
+            ..viewport
+        },
+    )
+}
+
+fn set_viewport(pass: &mut wgpu::RenderPass<'_>, viewport: Rectangle<u32>) {
+    pass.set_viewport(
+        viewport.x as f32,
+        viewport.y as f32,
+        viewport.width as f32,
+        viewport.height as f32,
+        0.0,
+        1.0,
+    );
+}
+
+/// Ids of the widgets prepared in the current frame, to drop the resources of widgets that are
+/// gone. Each widget is prepared once a frame, so one prepared again starts the next frame.
+#[derive(Default)]
+struct PreparedIds(HashSet<u64>);
+
+impl PreparedIds {
+    /// Records that `id` is being prepared, removing the `instances` of widgets that weren't
+    /// prepared in the last frame if this starts a new one.
+    fn prepare<T>(&mut self, id: u64, instances: &mut HashMap<u64, T>) {
+        if !self.0.insert(id) {
+            instances.retain(|id, _| self.0.contains(id));
+            self.0.clear();
+            self.0.insert(id);
+        }
+    }
+}
+
+/// Parameters of the Buddhabrot accumulation, see `buddhabrot.wgsl`. A change other than the
+/// seed starts over.
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct BuddhabrotUniforms {
+    /// Size of the accumulation grid.
+    size: UVec2,
+    center: Vec2,
+    rotation: f32,
+    /// Size of an accumulation pixel on the complex plane.
+    scale: f32,
+    max_iter: u32,
+    escape_radius: f32,
+    seed: u32,
+    _padding: u32,
+}
+
+/// Parameters of the Buddhabrot display pass, see `buddhabrot_display.wgsl`.
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct BuddhabrotDisplayUniforms {
+    size: UVec2,
+    exposure: f32,
+    _padding: u32,
+}
+
+/// Orbit counts of a widget in [`RenderMode::Buddhabrot`], added to every frame until the view
+/// changes.
+struct BuddhabrotInstance {
+    uniform_buffer: wgpu::Buffer,
+    display_uniform_buffer: wgpu::Buffer,
+    /// The highest count followed by one count per pixel of the accumulation grid.
+    accumulation_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    display_bind_group: wgpu::BindGroup,
+    /// Size of the accumulation grid, the viewport size unless its counts didn't fit the
+    /// buffer size limits.
+    size: UVec2,
+    /// View the counts were accumulated for, with a seed of 0.
+    accumulated: Uploaded<BuddhabrotUniforms>,
+    displayed: Uploaded<BuddhabrotDisplayUniforms>,
+    /// Points iterated since the counts were last cleared.
+    samples: u64,
+    /// Workgroups to dispatch this frame, 0 once [`BUDDHABROT_SAMPLES_MAX`] was reached.
+    workgroups: u32,
+    /// Whether the counts have to be cleared before this frame's batch.
+    clear_pending: bool,
+}
+
+impl BuddhabrotInstance {
+    fn new(device: &wgpu::Device, layouts: &BuddhabrotLayouts) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("buddhabrot uniform buffer"),
+            size: generic::uniform_buffer_size(std::mem::size_of::<BuddhabrotUniforms>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let display_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("buddhabrot display uniform buffer"),
+            size: generic::uniform_buffer_size(std::mem::size_of::<BuddhabrotDisplayUniforms>())
+                as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let size = UVec2::ONE;
+        let (accumulation_buffer, bind_group, display_bind_group) = Self::create_accumulation(
+            device,
+            layouts,
+            &uniform_buffer,
+            &display_uniform_buffer,
+            size,
+        );
+
+        Self {
+            uniform_buffer,
+            display_uniform_buffer,
+            accumulation_buffer,
+            bind_group,
+            display_bind_group,
+            size,
+            accumulated: Uploaded::new(),
+            displayed: Uploaded::new(),
+            samples: 0,
+            workgroups: 0,
+            clear_pending: true,
+        }
+    }
+
+    fn create_accumulation(
+        device: &wgpu::Device,
+        layouts: &BuddhabrotLayouts,
+        uniform_buffer: &wgpu::Buffer,
+        display_uniform_buffer: &wgpu::Buffer,
+        size: UVec2,
+    ) -> (wgpu::Buffer, wgpu::BindGroup, wgpu::BindGroup) {
+        let accumulation_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("buddhabrot accumulation buffer"),
+            size: accumulation_bytes(size),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Bound separately for accumulating and displaying, as a buffer can't be both
+        // writable and read-only within one bind group.
+        let bind_group = |label, layout, uniforms: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniforms.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: accumulation_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let accumulate_bind_group = bind_group(
+            "buddhabrot accumulate bind group",
+            &layouts.accumulate,
+            uniform_buffer,
+        );
+        let display_bind_group = bind_group(
+            "buddhabrot display bind group",
+            &layouts.display,
+            display_uniform_buffer,
+        );
+
+        (
+            accumulation_buffer,
+            accumulate_bind_group,
+            display_bind_group,
+        )
+    }
+}
+
+/// Bytes of the highest count followed by the counts of a `size` accumulation grid.
+fn accumulation_bytes(size: UVec2) -> u64 {
+    (size.x as u64 * size.y as u64 + 1) * std::mem::size_of::<u32>() as u64
+}
+
+/// Accumulation grid for a `viewport` sized view, halved until its counts fit a buffer.
+fn accumulation_size(limits: &wgpu::Limits, viewport: UVec2) -> UVec2 {
+    let max_bytes = limits
+        .max_buffer_size
+        .min(limits.max_storage_buffer_binding_size as u64);
+    let mut size = viewport.max(UVec2::ONE);
+    while accumulation_bytes(size) > max_bytes && size != UVec2::ONE {
+        size = (size / 2).max(UVec2::ONE);
+    }
+    size
+}
+
+struct BuddhabrotLayouts {
+    accumulate: wgpu::BindGroupLayout,
+    display: wgpu::BindGroupLayout,
+}
+
+/// Renders views in [`RenderMode::Buddhabrot`], stored next to the [`FragmentShaderPipeline`].
+///
+/// Every frame a compute pass iterates a batch of random points and, for those that escape,
+/// counts the pixels their orbits pass through. A render pass then shows the counts on a log
+/// scale. Counts add up until the view or its size changes.
+struct BuddhabrotPipeline {
+    format: wgpu::TextureFormat,
+    layouts: BuddhabrotLayouts,
+    accumulate_pipeline: wgpu::ComputePipeline,
+    /// Draws the counts to the target.
+    display_pipeline: wgpu::RenderPipeline,
+    instances: HashMap<u64, BuddhabrotInstance>,
+    prepared: PreparedIds,
+}
+
+impl BuddhabrotPipeline {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = |label, visibility, read_only| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[
+                    entry(0, visibility, wgpu::BufferBindingType::Uniform),
+                    entry(
+                        1,
+                        visibility,
+                        wgpu::BufferBindingType::Storage { read_only },
+                    ),
+                ],
+            })
+        };
+        let layouts = BuddhabrotLayouts {
+            accumulate: layout(
+                "buddhabrot accumulate bind group layout",
+                wgpu::ShaderStages::COMPUTE,
+                false,
+            ),
+            display: layout(
+                "buddhabrot display bind group layout",
+                wgpu::ShaderStages::FRAGMENT,
+                true,
+            ),
+        };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("buddhabrot shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "buddhabrot.wgsl"
+            ))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("buddhabrot accumulate layout"),
+            bind_group_layouts: &[&layouts.accumulate],
+            push_constant_ranges: &[],
+        });
+        let accumulate_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("buddhabrot accumulate"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: "cs_main",
+            });
+
+        let display_pipeline = Self::create_display_pipeline(device, &layouts, format);
+
+        Self {
+            format,
+            layouts,
+            accumulate_pipeline,
+            display_pipeline,
+            instances: HashMap::new(),
+            prepared: PreparedIds::default(),
+        }
+    }
+
+    fn create_display_pipeline(
+        device: &wgpu::Device,
+        layouts: &BuddhabrotLayouts,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("buddhabrot display shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(include_str!(
+                "buddhabrot_display.wgsl"
+            ))),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("buddhabrot display layout"),
+            bind_group_layouts: &[&layouts.display],
+            push_constant_ranges: &[],
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("buddhabrot display"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                // Like the blit in `FragmentShaderPipeline::create_target_pipelines`.
+                entry_point: if format.is_srgb() {
+                    "fs_main"
+                } else {
+                    "fs_encode_srgb"
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+    }
+
+    fn set_format(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.display_pipeline = Self::create_display_pipeline(device, &self.layouts, format);
+        self.format = format;
+    }
+
+    /// Uploads the next batch of `controls` for the widget `id`, starting over if the view or
+    /// the `viewport` size changed. Returns the points iterated so far, including this batch.
+    fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: u64,
+        controls: &Controls,
+        viewport: Rectangle<u32>,
+    ) -> u64 {
+        self.prepared.prepare(id, &mut self.instances);
+        let layouts = &self.layouts;
+        let instance = self
+            .instances
+            .entry(id)
+            .or_insert_with(|| BuddhabrotInstance::new(device, layouts));
+
+        let size = accumulation_size(
+            &device.limits(),
+            UVec2::new(viewport.width, viewport.height),
+        );
+        if size != instance.size {
+            let (accumulation_buffer, bind_group, display_bind_group) =
+                BuddhabrotInstance::create_accumulation(
+                    device,
+                    layouts,
+                    &instance.uniform_buffer,
+                    &instance.display_uniform_buffer,
+                    size,
+                );
+            instance.accumulation_buffer = accumulation_buffer;
+            instance.bind_group = bind_group;
+            instance.display_bind_group = display_bind_group;
+            instance.size = size;
+            instance.accumulated.invalidate();
+        }
+
+        let max_iter = controls.iterations();
+        let mut uniforms = BuddhabrotUniforms {
+            size,
+            center: controls.center.as_vec2(),
+            rotation: controls.rotation,
+            scale: controls.scale(size.y as f32) as f32,
+            max_iter,
+            escape_radius: controls.escape_radius,
+            seed: 0,
+            _padding: 0,
+        };
+        instance.clear_pending = false;
+        if instance.accumulated.update(&uniforms) {
+            instance.samples = 0;
+            instance.clear_pending = true;
+        }
+
+        // Batches cost about the same whatever the iteration count, to keep frames responsive.
+        let batch = (BUDDHABROT_BATCH_ITERATIONS / max_iter.max(1))
+            .next_multiple_of(BUDDHABROT_WORKGROUP_SIZE);
+        instance.workgroups = if instance.samples < BUDDHABROT_SAMPLES_MAX {
+            batch / BUDDHABROT_WORKGROUP_SIZE
+        } else {
+            0
+        };
+        if instance.workgroups > 0 {
+            uniforms.seed = (instance.samples / batch as u64) as u32;
+            queue.write_buffer(&instance.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+            instance.samples += batch as u64;
+        }
+
+        let display = BuddhabrotDisplayUniforms {
+            size,
+            exposure: controls.exposure,
+            _padding: 0,
+        };
+        if instance.displayed.update(&display) {
+            queue.write_buffer(
+                &instance.display_uniform_buffer,
+                0,
+                bytemuck::bytes_of(&display),
+            );
+        }
+
+        instance.samples
+    }
+
+    fn render(
+        &self,
+        id: u64,
+        target: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+        viewport: Rectangle<u32>,
+    ) {
+        let Some(instance) = self.instances.get(&id) else {
+            return;
+        };
+
+        if instance.clear_pending {
+            encoder.clear_buffer(&instance.accumulation_buffer, 0, None);
+        }
+        if instance.workgroups > 0 {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("buddhabrot accumulate"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.accumulate_pipeline);
+            pass.set_bind_group(0, &instance.bind_group, &[]);
+            pass.dispatch_workgroups(instance.workgroups, 1, 1);
+        }
+
+        let mut pass = begin_pass(encoder, target, "buddhabrot display");
+        set_viewport(&mut pass, viewport);
+        pass.set_pipeline(&self.display_pipeline);
+        pass.set_bind_group(0, &instance.display_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Iterated escape-time formula.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Formula {
+    /// `z^2 + c`
+    #[default]
+    Mandelbrot,
+    /// `(|re z| + i|im z|)^2 + c`
+    BurningShip,
+    /// `conj(z)^2 + c`
+    Tricorn,
+    /// `z^power + c`
+    Multibrot { power: f32 },
+}
+
+impl Formula {
+    /// Formulas selectable in the UI. `multibrot_power` is used for the Multibrot entry so that
+    /// it matches the current selection.
+    pub fn all(multibrot_power: f32) -> [Formula; 4] {
+        [
+            Formula::Mandelbrot,
+            Formula::BurningShip,
+            Formula::Tricorn,
+            Formula::Multibrot {
+                power: multibrot_power,
+            },
+        ]
+    }
+
+    /// Formula identifier used by the shader.
+    fn index(&self) -> u32 {
+        match self {
+            Formula::Mandelbrot => 0,
+            Formula::BurningShip => 1,
+            Formula::Tricorn => 2,
+            Formula::Multibrot { .. } => 3,
+        }
+    }
+
+    fn power(&self) -> f32 {
+        match self {
+            Formula::Multibrot { power } => *power,
+            _ => 2.0,
+        }
+    }
+
+    /// One iteration on the CPU, the same as in `shader.wgsl`.
+    fn step(&self, z: DVec2, c: DVec2) -> DVec2 {
+        let z = match self {
+            Formula::BurningShip => z.abs(),
+            Formula::Tricorn => DVec2::new(z.x, -z.y),
+            _ => z,
+        };
+
+        let power = self.power() as f64;
+        let zn = if power.fract() == 0.0 {
+            // Complex multiplication is a rotation and scaling.
+            (0..power as u32).fold(DVec2::X, |zn, _| zn.rotate(z))
+        } else if z == DVec2::ZERO {
+            z
+        } else {
+            DVec2::from_angle(z.to_angle() * power) * z.length().powf(power)
+        };
+        zn + c
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColoringMode {
+    /// Shade by the escape iteration count.
+    #[default]
+    Iterations,
+    /// Shade by the estimated distance to the set boundary.
+    Distance,
+    /// Thin lines along the set boundary, from the distance estimate.
+    DistanceLines,
+    /// Shade by the orbit's closest approach to the trap point.
+    OrbitTrapPoint,
+    /// Shade by the orbit's closest approach to the horizontal line through the trap point.
+    OrbitTrapLine,
+    /// Shade by the orbit's closest approach to the circle around the trap point.
+    OrbitTrapCircle,
+}
+
+impl ColoringMode {
+    pub const ALL: [ColoringMode; 6] = [
+        ColoringMode::Iterations,
+        ColoringMode::Distance,
+        ColoringMode::DistanceLines,
+        ColoringMode::OrbitTrapPoint,
+        ColoringMode::OrbitTrapLine,
+        ColoringMode::OrbitTrapCircle,
+    ];
+
+    /// Whether the coloring needs the distance estimate.
+    fn uses_distance(&self) -> bool {
+        matches!(self, ColoringMode::Distance | ColoringMode::DistanceLines)
+    }
+
+    /// Orbit trap shape tracked by the shader, 0 for none.
+    pub fn trap(&self) -> u32 {
+        match self {
+            ColoringMode::Iterations | ColoringMode::Distance | ColoringMode::DistanceLines => 0,
+            ColoringMode::OrbitTrapPoint => 1,
+            ColoringMode::OrbitTrapLine => 2,
+            ColoringMode::OrbitTrapCircle => 3,
+        }
+    }
+}
+
+impl std::fmt::Display for ColoringMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ColoringMode::Iterations => "Iterations",
+            ColoringMode::Distance => "Distance estimate",
+            ColoringMode::DistanceLines => "Boundary lines",
+            ColoringMode::OrbitTrapPoint => "Point trap",
+            ColoringMode::OrbitTrapLine => "Line trap",
+            ColoringMode::OrbitTrapCircle => "Circle trap",
+        })
+    }
+}
+
+/// What a view shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RenderMode {
+    /// Points colored by how their orbits escape.
+    #[default]
+    EscapeTime,
+    /// Density of the escaping orbits of the Mandelbrot set, accumulated over frames. Ignores
+    /// the formula, Julia seed and coloring settings.
+    Buddhabrot,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 2] = [RenderMode::EscapeTime, RenderMode::Buddhabrot];
+}
+
+impl std::fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RenderMode::EscapeTime => "Escape time",
+            RenderMode::Buddhabrot => "Buddhabrot",
+        })
+    }
+}
+
+/// How points that never escape are shaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InteriorMode {
+    #[default]
+    Black,
+    /// Shade by the magnitude of the final z.
+    Magnitude,
+    /// Shade by the angle of the final z.
+    Angle,
+    /// Shade by the period of the attracting cycle, found with Brent's cycle detection.
+    Period,
+}
+
+impl InteriorMode {
+    pub const ALL: [InteriorMode; 4] = [
+        InteriorMode::Black,
+        InteriorMode::Magnitude,
+        InteriorMode::Angle,
+        InteriorMode::Period,
+    ];
+}
+
+impl std::fmt::Display for InteriorMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InteriorMode::Black => "Black interior",
+            InteriorMode::Magnitude => "Interior |z|",
+            InteriorMode::Angle => "Interior angle",
+            InteriorMode::Period => "Interior period",
+        })
+    }
+}
+
+impl std::fmt::Display for Formula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Formula::Mandelbrot => "Mandelbrot",
+            Formula::BurningShip => "Burning Ship",
+            Formula::Tricorn => "Tricorn",
+            Formula::Multibrot { .. } => "Multibrot",
+        })
+    }
+}
+
+/// Shape the view is letterboxed to within the widget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AspectRatio {
+    /// Fill the widget.
+    #[default]
+    Free,
+    Square,
+    Widescreen,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 3] = [
+        AspectRatio::Free,
+        AspectRatio::Square,
+        AspectRatio::Widescreen,
+    ];
+
+    /// Width divided by height, `None` if it follows the widget.
+    pub fn ratio(&self) -> Option<f32> {
+        match self {
+            AspectRatio::Free => None,
+            AspectRatio::Square => Some(1.0),
+            AspectRatio::Widescreen => Some(16.0 / 9.0),
+        }
+    }
+}
+
+impl std::fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AspectRatio::Free => "Fill",
+            AspectRatio::Square => "1:1",
+            AspectRatio::Widescreen => "16:9",
+        })
+    }
+}
+
+/// Width of the palette texture the iteration counts are colored by.
+const PALETTE_SIZE: u32 = 256;
+
+/// Point of a [`Palette`] gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    /// Position along the palette, from 0 to 1.
+    pub position: f32,
+    /// sRGB color.
+    pub color: [u8; 3],
+}
+
+impl ColorStop {
+    /// The color as `#rrggbb`.
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.color;
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Gradient escaped samples are colored by, from few iterations at 0 to many at 1. Always has
+/// at least two stops, sorted by position, and is interpolated in linear space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<ColorStop>", into = "Vec<ColorStop>")]
+pub struct Palette {
+    stops: Vec<ColorStop>,
+}
+
+impl Palette {
+    pub fn new(mut stops: Vec<ColorStop>) -> Result<Self, String> {
+        if stops.len() < 2 {
+            return Err("a palette needs at least two stops".to_owned());
+        }
+        if stops.iter().any(|stop| !stop.position.is_finite()) {
+            return Err("stop positions must be finite".to_owned());
+        }
+        for stop in &mut stops {
+            stop.position = stop.position.clamp(0.0, 1.0);
+        }
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Ok(Self { stops })
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Adds a stop in the middle of the widest gap between stops, without changing the
+    /// gradient, and returns its index.
+    pub fn add_stop(&mut self) -> usize {
+        let index = (1..self.stops.len())
+            .max_by(|&a, &b| {
+                let gap = |i: usize| self.stops[i].position - self.stops[i - 1].position;
+                gap(a).total_cmp(&gap(b))
+            })
+            .unwrap_or(1);
+        let position = (self.stops[index - 1].position + self.stops[index].position) / 2.0;
+        let color = self.color_at(position);
+        self.stops.insert(index, ColorStop { position, color });
+        index
+    }
+
+    /// Removes a stop unless only two are left.
+    pub fn remove_stop(&mut self, index: usize) {
+        if self.stops.len() > 2 && index < self.stops.len() {
+            self.stops.remove(index);
+        }
+    }
+
+    /// Moves a stop, but not past its neighbors, so that stops keep their order and indices.
+    pub fn set_position(&mut self, index: usize, position: f32) {
+        if !position.is_finite() || index >= self.stops.len() {
+            return;
+        }
+        let min = index.checked_sub(1).map_or(0.0, |i| self.stops[i].position);
+        let max = self.stops.get(index + 1).map_or(1.0, |stop| stop.position);
+        self.stops[index].position = position.clamp(min, max);
+    }
+
+    pub fn set_color(&mut self, index: usize, color: [u8; 3]) {
+        if let Some(stop) = self.stops.get_mut(index) {
+            stop.color = color;
+        }
+    }
+
+    /// Color of the gradient at `position`, clamped to the first and last stop.
+    pub fn color_at(&self, position: f32) -> [u8; 3] {
+        let next = self
+            .stops
+            .iter()
+            .position(|stop| stop.position > position)
+            .unwrap_or(self.stops.len() - 1)
+            .max(1);
+        let (a, b) = (self.stops[next - 1], self.stops[next]);
+        let t = if b.position > a.position {
+            ((position - a.position) / (b.position - a.position)).clamp(0.0, 1.0)
+        } else {
+            (position >= b.position) as u8 as f32
+        };
+        std::array::from_fn(|i| {
+            let (a, b) = (srgb_to_linear(a.color[i]), srgb_to_linear(b.color[i]));
+            linear_to_srgb(a + (b - a) * t)
+        })
+    }
+
+    /// RGBA pixels of the palette texture, sampled at texel centers.
+    fn pixels(&self) -> Vec<u8> {
+        (0..PALETTE_SIZE)
+            .flat_map(|x| {
+                let [r, g, b] = self.color_at(x as f32 / (PALETTE_SIZE - 1) as f32);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+}
+
+/// From black to white, like coloring without a palette.
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                ColorStop {
+                    position: 0.0,
+                    color: [0; 3],
+                },
+                ColorStop {
+                    position: 1.0,
+                    color: [255; 3],
+                },
+            ],
+        }
+    }
+}
+
+impl TryFrom<Vec<ColorStop>> for Palette {
+    type Error = String;
+
+    fn try_from(stops: Vec<ColorStop>) -> Result<Self, String> {
+        Self::new(stops)
+    }
+}
+
+impl From<Palette> for Vec<ColorStop> {
+    fn from(palette: Palette) -> Self {
+        palette.stops
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+/// View and fractal settings, persisted between runs. Fields missing from an older state file
+/// take their default values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    pub max_iter: u32,
+    pub zoom: f32,
+    pub center: DVec2,
+    /// Julia set seed; `None` renders the Mandelbrot set.
+    pub julia: Option<Vec2>,
+    /// Derive the iteration count from the zoom level instead of `max_iter`.
+    pub auto_iter: bool,
+    pub formula: Formula,
+    pub escape_radius: f32,
+    pub smooth: bool,
+    /// Color escaped points by the average direction their orbit takes instead of the iteration
+    /// count, see `shader.wgsl`.
+    pub stripe: bool,
+    /// Stripes per turn of the orbit direction.
+    pub stripe_frequency: f32,
+    /// Spread iteration counts evenly over the palette, see `color.wgsl`.
+    pub histogram: bool,
+    pub coloring: ColoringMode,
+    /// Width of boundary lines in pixels.
+    pub line_width: f32,
+    /// Center of the orbit trap shapes, in fractal coordinates.
+    pub trap_point: Vec2,
+    pub trap_radius: f32,
+    pub interior: InteriorMode,
+    /// Applied to the final colors, values above 1 brighten midtones.
+    pub gamma: f32,
+    /// Shifts the palette, in palette lengths.
+    pub color_offset: f32,
+    /// Times the palette repeats over the iteration range.
+    pub color_frequency: f32,
+    /// Palette lengths per second the colors shift by while animated.
+    pub color_cycle_speed: f32,
+    pub aa_samples: u32,
+    /// Tiles per axis the iteration is split into, each submitted on its own so that deep
+    /// views don't exceed the GPU watchdog timeout.
+    pub tiles: u32,
+    /// Counterclockwise rotation of the view about its center, in radians within `[-π, π)`.
+    pub rotation: f32,
+    pub aspect: AspectRatio,
+    pub mode: RenderMode,
+    /// Brightness of [`RenderMode::Buddhabrot`] views.
+    pub exposure: f32,
+    /// Point on the complex plane last clicked, which zooming can keep in place instead of the
+    /// center.
+    pub zoom_anchor: Option<DVec2>,
+}
+
+impl Controls {
+    /// Up to `len` values of the orbit of `point`, starting from `point` itself and ending with
+    /// the first one that escaped, if any.
+    pub fn orbit(&self, point: DVec2, len: usize) -> Vec<DVec2> {
+        let c = self.julia.map_or(point, |seed| seed.as_dvec2());
+        let bailout = (self.escape_radius as f64).powi(2);
+
+        let mut z = point;
+        let mut orbit = vec![z];
+        while orbit.len() < len && z.length_squared() <= bailout {
+            z = self.formula.step(z, c);
+            orbit.push(z);
+        }
+        orbit
+    }
+
+    /// Whether the view can be rendered with perturbation against a [`ReferenceOrbit`].
+    fn supports_perturbation(&self) -> bool {
+        self.zoom > ZOOM_DEEP_THRESHOLD
+            && self.formula == Formula::Mandelbrot
+            && self.julia.is_none()
+    }
+
+    /// Default view framing the interesting region of the given formula.
+    pub fn for_formula(formula: Formula) -> Self {
+        let (center, zoom) = match formula {
+            Formula::Mandelbrot => (CENTER_DEFAULT, ZOOM_DEFAULT),
+            Formula::BurningShip => (DVec2::new(-1.75, -0.03), 5.0),
+            Formula::Tricorn => (DVec2::new(-0.25, 0.0), ZOOM_MIN),
+            Formula::Multibrot { .. } => (DVec2::ZERO, ZOOM_MIN),
+        };
+
+        Self {
+            center,
+            zoom,
+            formula,
+            ..Self::default()
+        }
+    }
+
+    /// Height of the view on the complex plane, its width follows from the aspect ratio.
+    pub fn extent(&self) -> f64 {
+        EXTENT_UNZOOMED / 2.0_f64.powf(self.zoom as f64)
+    }
+
+    /// How many times larger the plane appears than at zoom level 1, i.e. `2^(zoom - 1)`.
+    pub fn magnification(&self) -> f64 {
+        Controls { zoom: 1.0, ..*self }.extent() / self.extent()
+    }
+
+    /// Zoom level at which the plane appears `magnification` times larger than at level 1.
+    pub fn zoom_for_magnification(magnification: f64) -> f32 {
+        (magnification.log2() + 1.0) as f32
+    }
+
+    /// Size of a pixel on the complex plane, in a view `height` pixels tall.
+    pub fn scale(&self, height: f32) -> f64 {
+        self.extent() / height.max(1.0) as f64
+    }
+
+    /// Iteration count actually used for rendering.
+    pub fn iterations(&self) -> u32 {
+        if !self.auto_iter {
+            return self.max_iter;
+        }
+
+        let iters = ITERS_DEFAULT as f32 + ITERS_AUTO_FACTOR * self.zoom * self.zoom;
+        (iters as u32).clamp(ITERS_MIN, ITERS_AUTO_MAX)
+    }
+
+    /// Decimal places needed to tell adjacent pixels' coordinates apart.
+    pub fn coordinate_precision(&self) -> usize {
+        let scale = self.extent() / PRECISION_MAX_HEIGHT;
+        (-scale.log10()).ceil().max(0.0) as usize + 1
+    }
+
+    /// Part of `bounds` the view is shown in, letterboxed to the aspect ratio if it's fixed.
+    pub fn view_bounds(&self, bounds: Rectangle) -> Rectangle {
+        let Some(ratio) = self.aspect.ratio() else {
+            return bounds;
+        };
+
+        let size = if bounds.width > bounds.height * ratio {
+            Size::new(bounds.height * ratio, bounds.height)
+        } else {
+            Size::new(bounds.width, bounds.width / ratio)
+        };
+        let top_left = bounds.center() - iced::Vector::new(size.width, size.height) * 0.5;
+        Rectangle::new(top_left, size)
+    }
+
+    /// Rotates an on-screen offset into the direction it has on the complex plane.
+    pub fn plane_offset(&self, vec: DVec2) -> DVec2 {
+        DVec2::from_angle(self.rotation as f64).rotate(vec)
+    }
+
+    /// Inverse of [`Controls::plane_offset`].
+    pub fn screen_offset(&self, vec: DVec2) -> DVec2 {
+        DVec2::from_angle(-self.rotation as f64).rotate(vec)
+    }
+
+    /// Spacing of the coordinate grid on the complex plane in a view `height` logical pixels
+    /// tall: the smallest of 1, 2 or 5 times a power of ten that keeps lines at least
+    /// [`GRID_MIN_SPACING`] pixels apart.
+    pub fn grid_spacing(&self, height: f32) -> f64 {
+        let min = self.scale(height) * GRID_MIN_SPACING as f64;
+        let power = 10.0_f64.powf(min.log10().floor());
+        [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|multiple| multiple * power)
+            .find(|&spacing| spacing >= min)
+            .unwrap_or(power * 10.0)
+    }
+
+    /// Converts a position relative to the view `bounds` into a point on the complex plane.
+    pub fn fractal_point(&self, pos: Vec2, bounds: Rectangle) -> DVec2 {
+        let vec = pos - Vec2::new(bounds.width, bounds.height) * 0.5;
+        self.center + self.plane_offset(vec.as_dvec2()) * self.scale(bounds.height)
+    }
+
+    /// These settings, showing the part of the complex plane `view` shows: the same center,
+    /// zoom, rotation, aspect ratio, formula and render mode.
+    pub fn with_view_of(self, view: &Controls) -> Controls {
+        Controls {
+            center: view.center,
+            zoom: view.zoom,
+            rotation: view.rotation,
+            aspect: view.aspect,
+            julia: view.julia,
+            formula: view.formula,
+            mode: view.mode,
+            ..self
+        }
+    }
+
+    /// Moves the view so that the image follows a drag by `delta` pixels within `bounds`.
+    pub fn pan(&mut self, delta: Vec2, bounds: Rectangle) {
+        self.center -= self.plane_offset(delta.as_dvec2()) * self.scale(bounds.height);
+    }
+
+    /// Rotates the view by `angle` radians, keeping the rotation within `[-π, π)`.
+    pub fn rotate(&mut self, angle: f32) {
+        self.rotation = (self.rotation + angle + PI).rem_euclid(TAU) - PI;
+    }
+
+    /// Sets zoom, clamped to the `config` range, while keeping `point` on the complex plane fixed
+    /// on screen.
+    pub fn zoom_around(&mut self, point: DVec2, zoom: f32, config: &Config) {
+        let prev_extent = self.extent();
+        self.zoom = config.clamp_zoom(zoom);
+
+        self.center = point + (self.center - point) * (self.extent() / prev_extent);
+    }
+
+    /// Brings the view back into the `config` limits after arithmetic or input went astray:
+    /// non-finite values are reset to their defaults and the rest clamped, so the shader never
+    /// sees NaN.
+    pub fn sanitize(&mut self, config: &Config) {
+        self.zoom = config.clamp_zoom(if self.zoom.is_nan() {
+            ZOOM_DEFAULT
+        } else {
+            self.zoom
+        });
+        if !self.center.x.is_finite() {
+            self.center.x = CENTER_DEFAULT.x;
+        }
+        if !self.center.y.is_finite() {
+            self.center.y = CENTER_DEFAULT.y;
+        }
+        self.max_iter = config.clamp_iterations(self.max_iter);
+        if !self.rotation.is_finite() {
+            self.rotation = 0.0;
+        }
+        if !self.color_offset.is_finite() {
+            self.color_offset = 0.0;
+        }
+        self.color_frequency = if self.color_frequency.is_finite() {
+            self.color_frequency
+                .clamp(COLOR_FREQUENCY_MIN, COLOR_FREQUENCY_MAX)
+        } else {
+            COLOR_FREQUENCY_DEFAULT
+        };
+        self.color_cycle_speed = if self.color_cycle_speed.is_finite() {
+            self.color_cycle_speed.clamp(0.0, COLOR_CYCLE_SPEED_MAX)
+        } else {
+            COLOR_CYCLE_SPEED_DEFAULT
+        };
+        self.stripe_frequency = if self.stripe_frequency.is_finite() {
+            self.stripe_frequency
+                .clamp(STRIPE_FREQUENCY_MIN, STRIPE_FREQUENCY_MAX)
+        } else {
+            STRIPE_FREQUENCY_DEFAULT
+        };
+        self.exposure = if self.exposure.is_finite() {
+            self.exposure.clamp(EXPOSURE_MIN, EXPOSURE_MAX)
+        } else {
+            EXPOSURE_DEFAULT
+        };
+    }
+}
+
+/// Leading field of view strings, identifying their format version.
+const VIEW_STRING_PREFIX: &str = "mandel:v1";
+
+impl Controls {
+    /// Compact single-line description of the view and coloring, for sharing through the
+    /// clipboard. Numbers are written with round-trip precision.
+    pub fn view_string(&self) -> String {
+        format!(
+            "{VIEW_STRING_PREFIX};cx={};cy={};z={};it={};auto={};f={};p={};er={};sm={};col={};in={};hist={};rot={};co={};cf={};st={};sf={}",
+            self.center.x,
+            self.center.y,
+            self.zoom,
+            self.max_iter,
+            self.auto_iter as u8,
+            self.formula.index(),
+            self.formula.power(),
+            self.escape_radius,
+            self.smooth as u8,
+            self.coloring as u8,
+            self.interior as u8,
+            self.histogram as u8,
+            self.rotation,
+            self.color_offset,
+            self.color_frequency,
+            self.stripe as u8,
+            self.stripe_frequency,
+        )
+    }
+
+    /// Applies a string made by [`Controls::view_string`]. Missing and unknown keys are
+    /// skipped; on error the controls are left unchanged.
+    pub fn apply_view_string(&mut self, view: &str) -> Result<(), String> {
+        let mut fields = view.trim().split(';');
+        if fields.next() != Some(VIEW_STRING_PREFIX) {
+            return Err(format!("expected a {VIEW_STRING_PREFIX} view"));
+        }
+
+        let mut controls = *self;
+        let mut formula = controls.formula.index() as usize;
+        let mut power = controls.formula.power();
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("malformed field {field:?}"))?;
+            let invalid = |err| format!("invalid {key}: {err}");
+            match key {
+                "cx" => controls.center.x = parse_finite(value).map_err(invalid)?,
+                "cy" => controls.center.y = parse_finite(value).map_err(invalid)?,
+                "z" => controls.zoom = parse_zoom(value).map_err(invalid)?,
+                "it" => {
+                    controls.max_iter =
+                        parse_in_range(value, ITERS_MIN..=ITERS_MAX).map_err(invalid)?
+                }
+                "auto" => controls.auto_iter = parse_flag(value).map_err(invalid)?,
+                "f" => formula = parse_in_range(value, 0..=3).map_err(invalid)?,
+                "p" => {
+                    power = parse_in_range(value, MULTIBROT_POWER_MIN..=MULTIBROT_POWER_MAX)
+                        .map_err(invalid)?
+                }
+                "er" => {
+                    controls.escape_radius =
+                        parse_in_range(value, ESCAPE_RADIUS_MIN..=ESCAPE_RADIUS_MAX)
+                            .map_err(invalid)?
+                }
+                "sm" => controls.smooth = parse_flag(value).map_err(invalid)?,
+                "col" => {
+                    let max = ColoringMode::ALL.len() - 1;
+                    controls.coloring =
+                        ColoringMode::ALL[parse_in_range(value, 0..=max).map_err(invalid)?]
+                }
+                "in" => {
+                    let max = InteriorMode::ALL.len() - 1;
+                    controls.interior =
+                        InteriorMode::ALL[parse_in_range(value, 0..=max).map_err(invalid)?]
+                }
+                "hist" => controls.histogram = parse_flag(value).map_err(invalid)?,
+                "rot" => controls.rotation = parse_in_range(value, -PI..=PI).map_err(invalid)?,
+                "co" => {
+                    controls.color_offset = parse_in_range(value, 0.0..=1.0).map_err(invalid)?
+                }
+                "cf" => {
+                    controls.color_frequency =
+                        parse_in_range(value, COLOR_FREQUENCY_MIN..=COLOR_FREQUENCY_MAX)
+                            .map_err(invalid)?
+                }
+                "st" => controls.stripe = parse_flag(value).map_err(invalid)?,
+                "sf" => {
+                    controls.stripe_frequency =
+                        parse_in_range(value, STRIPE_FREQUENCY_MIN..=STRIPE_FREQUENCY_MAX)
+                            .map_err(invalid)?
+                }
+                // Written by newer versions.
+                _ => {}
+            }
+        }
+
+        controls.formula = Formula::all(power)[formula];
+        *self = controls;
+        Ok(())
+    }
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            max_iter: ITERS_DEFAULT,
+            zoom: ZOOM_DEFAULT,
+            center: CENTER_DEFAULT,
+            julia: None,
+            auto_iter: false,
+            formula: Formula::Mandelbrot,
+            escape_radius: ESCAPE_RADIUS_MIN,
+            smooth: false,
+            stripe: false,
+            stripe_frequency: STRIPE_FREQUENCY_DEFAULT,
+            histogram: false,
+            coloring: ColoringMode::Iterations,
+            line_width: LINE_WIDTH_DEFAULT,
+            trap_point: Vec2::ZERO,
+            trap_radius: TRAP_RADIUS_DEFAULT,
+            interior: InteriorMode::Black,
+            gamma: GAMMA_DEFAULT,
+            color_offset: 0.0,
+            color_frequency: COLOR_FREQUENCY_DEFAULT,
+            color_cycle_speed: COLOR_CYCLE_SPEED_DEFAULT,
+            aa_samples: AA_SAMPLES[0],
+            tiles: TILES[0],
+            rotation: 0.0,
+            aspect: AspectRatio::Free,
+            mode: RenderMode::EscapeTime,
+            exposure: EXPOSURE_DEFAULT,
+            zoom_anchor: None,
+        }
+    }
+}
+
+/// Zoom level reached from `zoom` by a mouse wheel step, unclamped.
+pub fn wheel_zoom(zoom: f32, wheel_delta: f32) -> f32 {
+    zoom + wheel_delta * ZOOM_WHEEL_SCALE
+}
+
+/// Applies a mouse wheel step within the `config` zoom range, keeping the point under `cursor`
+/// (relative to the top-left corner of the view `bounds`) fixed on screen.
+pub fn apply_zoom(
+    mut controls: Controls,
+    cursor: Vec2,
+    bounds: Rectangle,
+    wheel_delta: f32,
+    config: &Config,
+) -> Controls {
+    let point = controls.fractal_point(cursor, bounds);
+    controls.zoom_around(point, wheel_zoom(controls.zoom, wheel_delta), config);
+    controls
+}
+
+#[derive(Debug)]
+pub struct FragmentShaderPrimitive {
+    id: u64,
+    controls: Controls,
+    elapsed: f32,
+    palette: Palette,
+    orbit: Option<Arc<ReferenceOrbit>>,
+    /// Index of the progressive sample to render, `None` when not accumulating.
+    frame_index: Option<u32>,
+    frame_stats: Option<Arc<Mutex<FrameStats>>>,
+    /// Render at reduced resolution and iterations while the view is being moved.
+    interacting: bool,
+    /// Replacement for the embedded iteration stage.
+    shader: Option<Arc<ShaderSource>>,
+    errors: Arc<RenderErrors>,
+    /// Overview drawn in a corner of the view, see [`minimap_bounds`].
+    minimap: Option<Box<FragmentShaderPrimitive>>,
+    /// View shown right of `split` instead, see [`FragmentShaderProgram::compare`].
+    compare: Option<Box<FragmentShaderPrimitive>>,
+    /// Position of the split line as a fraction of the view width.
+    split: f32,
+    /// Area to outline, set on the minimap.
+    marker: Option<Marker>,
+    /// Orbit drawn over the view, as points on the complex plane.
+    trace: Vec<DVec2>,
+    /// Spacing of the coordinate grid drawn over the view on the complex plane.
+    grid: Option<f64>,
+    /// Pixel to read back, until it was.
+    inspection: Option<Arc<Inspection>>,
+    /// Where the points iterated for a [`RenderMode::Buddhabrot`] view are counted.
+    buddhabrot_samples: Arc<AtomicU64>,
+    /// Where the tiles iterated so far of the view and its comparison are counted.
+    tile_progress: Arc<Mutex<TileProgress>>,
+}
+
+/// `orbit` if it is still the reference orbit for `controls`, else a new one if the view needs
+/// one.
+fn updated_orbit(
+    orbit: Option<Arc<ReferenceOrbit>>,
+    controls: &Controls,
+) -> Option<Arc<ReferenceOrbit>> {
+    if !controls.supports_perturbation() {
+        return None;
+    }
+    orbit
+        .filter(|orbit| orbit.matches(controls))
+        .or_else(|| Some(Arc::new(ReferenceOrbit::new(controls))))
+}
+
+/// Rectangle on the complex plane outlined on top of a rendered view.
+#[derive(Debug, Clone, Copy, Default)]
+struct Marker {
+    center: Vec2,
+    half_size: Vec2,
+    axis: Vec2,
+}
+
+impl Marker {
+    /// The area shown by `controls` in a view of `bounds`.
+    fn of_view(controls: &Controls, bounds: Rectangle) -> Self {
+        let extent = controls.extent() as f32;
+        Self {
+            center: controls.center.as_vec2(),
+            half_size: Vec2::new(extent * bounds.width / bounds.height.max(1.0), extent) * 0.5,
+            axis: Vec2::from_angle(controls.rotation),
+        }
+    }
+
+    /// Converts the marker into pixels of a `size` view rendered with `controls`.
+    fn to_pixels(self, controls: &Controls, size: UVec2) -> Self {
+        let scale = controls.scale(size.y as f32) as f32;
+        let offset = (self.center.as_dvec2() - controls.center).as_vec2();
+        Self {
+            center: controls.screen_offset(offset.as_dvec2()).as_vec2() / scale
+                + size.as_vec2() * 0.5,
+            half_size: self.half_size / scale,
+            axis: controls.screen_offset(self.axis.as_dvec2()).as_vec2(),
+        }
+    }
+}
+
+/// Coordinate grid lines in pixels of a rendered view, relative to its center and along the axes
+/// of the complex plane. Offsets are computed in double precision, so that the lines stay put at
+/// any zoom level.
+#[derive(Debug, Clone, Copy, Default)]
+struct GridLines {
+    spacing: f32,
+    /// Position of a grid line crossing, within half a spacing of the center.
+    offset: Vec2,
+    /// Position of the crossing of the real and imaginary axes, clamped far outside the view.
+    axes_offset: Vec2,
+    /// Direction of the real axis on screen.
+    axis: Vec2,
+}
+
+impl GridLines {
+    /// Lines `spacing` apart on the plane, in a `size` view rendered with `controls`.
+    fn new(controls: &Controls, spacing: f64, size: UVec2) -> Self {
+        let scale = controls.scale(size.y as f32);
+        let nearest = (controls.center / spacing).round() * spacing;
+        let far = DVec2::splat(1e6);
+        Self {
+            spacing: (spacing / scale) as f32,
+            offset: ((nearest - controls.center) / scale).as_vec2(),
+            axes_offset: (-controls.center / scale).clamp(-far, far).as_vec2(),
+            axis: controls.screen_offset(DVec2::X).as_vec2(),
+        }
+    }
+}
+
+/// Value of a coordinate grid line, where it crosses the bottom or left edge of the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridLabel {
+    /// Relative to the top-left corner of the widget, in logical pixels.
+    pub position: Vec2,
+    pub text: String,
+    /// Whether it labels a line of constant real part, on the bottom edge, rather than one of
+    /// constant imaginary part on the left edge.
+    pub real: bool,
+}
+
+/// Where the minimap goes within a `view`: its bottom-right corner, a fifth of its height.
+fn minimap_bounds(view: Rectangle) -> Rectangle {
+    let height = view.height.min(view.width * MINIMAP_ASPECT.recip()) * MINIMAP_HEIGHT_FRACTION;
+    let size = Size::new(height * MINIMAP_ASPECT, height);
+    let margin = height * MINIMAP_MARGIN_FRACTION;
+    Rectangle::new(
+        iced::Point::new(
+            view.x + view.width - size.width - margin,
+            view.y + view.height - size.height - margin,
+        ),
+        size,
+    )
+}
+
+/// Controls of the minimap of a view with `controls`: the whole set, with coloring unchanged.
+fn minimap_controls(controls: &Controls) -> Controls {
+    let center = match (controls.julia, controls.formula) {
+        (Some(_), _) => JULIA_CENTER,
+        (None, Formula::Mandelbrot) => DVec2::new(-0.75, 0.0),
+        (None, Formula::BurningShip) => DVec2::new(-0.5, 0.0),
+        (None, formula) => Controls::for_formula(formula).center,
+    };
+    Controls {
+        center,
+        zoom: 0.0,
+        max_iter: MINIMAP_ITERS,
+        auto_iter: false,
+        aa_samples: 1,
+        tiles: 1,
+        rotation: 0.0,
+        aspect: AspectRatio::Free,
+        ..*controls
+    }
+}
+
+impl FragmentShaderPrimitive {
+    /// A single still frame of `controls`.
+    fn new(id: u64, controls: Controls) -> Self {
+        Self {
+            id,
+            controls,
+            elapsed: 0.0,
+            palette: Palette::default(),
+            orbit: controls
+                .supports_perturbation()
+                .then(|| Arc::new(ReferenceOrbit::new(&controls))),
+            frame_index: None,
+            frame_stats: None,
+            interacting: false,
+            shader: None,
+            errors: Arc::default(),
+            minimap: None,
+            compare: None,
+            split: SPLIT_DEFAULT,
+            marker: None,
+            trace: Vec::new(),
+            grid: None,
+            inspection: None,
+            buddhabrot_samples: Arc::default(),
+            tile_progress: Arc::default(),
+        }
+    }
+}
+
+impl FragmentShaderPrimitive {
+    /// Part of the widget's `viewport`, in physical pixels, the view is rendered into. Computed
+    /// from the same snapped rectangle in `prepare` and `render`, so that they agree.
+    fn view_viewport(&self, viewport: Rectangle<u32>) -> Rectangle<u32> {
+        self.controls.view_bounds(viewport.into()).snap()
+    }
+}
+
+/// Element of the Halton low-discrepancy sequence, in `[0, 1)`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Rolling wall-clock time between frames of a widget and how many of them used the cached
+/// image, measured in `prepare`.
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    last_frame: Option<Instant>,
+    /// Times of the frames within the last `FPS_WINDOW`.
+    recent: VecDeque<Instant>,
+    /// Exponential moving average of the frame time, in seconds.
+    pub frame_time: f32,
+    /// Frames that drew a new image.
+    pub rendered: u64,
+    /// Frames that reused the cached image.
+    pub blitted: u64,
+    /// Size of the rendered image in physical pixels, and samples per pixel along each axis.
+    pub resolution: UVec2,
+    pub aa_samples: u32,
+    /// Exponential moving average of the iteration pass GPU time, in seconds, if the device
+    /// supports timestamp queries.
+    pub gpu_time: Option<f32>,
+}
+
+impl FrameStats {
+    fn record(&mut self, now: Instant, rendered: bool) {
+        if rendered {
+            self.rendered += 1;
+        } else {
+            self.blitted += 1;
+        }
+
+        if let Some(last_frame) = self.last_frame {
+            let frame_time = (now - last_frame).as_secs_f32();
+            self.frame_time = if self.frame_time == 0.0 {
+                frame_time
+            } else {
+                self.frame_time + (frame_time - self.frame_time) * FRAME_TIME_SMOOTHING
+            };
+        }
+        self.last_frame = Some(now);
+
+        self.recent.push_back(now);
+        self.forget_before(now);
+    }
+
+    fn forget_before(&mut self, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&frame| now.saturating_duration_since(frame) >= FPS_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    fn record_gpu_time(&mut self, gpu_time: f32) {
+        self.gpu_time = Some(match self.gpu_time {
+            Some(average) => average + (gpu_time - average) * FRAME_TIME_SMOOTHING,
+            None => gpu_time,
+        });
+    }
+
+    /// Frames drawn in the last second, which drops to zero while the view is idle.
+    pub fn fps(&mut self) -> f32 {
+        self.forget_before(Instant::now());
+        self.recent.len() as f32 / FPS_WINDOW.as_secs_f32()
+    }
+}
+
+impl FragmentShaderPrimitive {
+    /// Uploads what is needed to render the view into `viewport`, in physical pixels, iterating
+    /// as many tiles as fit this frame.
+    fn prepare_view(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pipeline: &mut FragmentShaderPipeline,
+        viewport: Rectangle<u32>,
+        scale_factor: f32,
+    ) -> TileProgress {
+        let center_hi = self.controls.center.as_vec2();
+        let center_lo = (self.controls.center - center_hi.as_dvec2()).as_vec2();
+
+        // The first sample is taken at pixel centers, so it matches non-progressive rendering.
+        let jitter = match self.frame_index {
+            Some(index) if index > 0 && !self.interacting => {
+                Vec2::new(halton(index, 2), halton(index, 3)) - 0.5
+            }
+            _ => Vec2::ZERO,
+        };
+
+        let full_size = Size::new(viewport.width, viewport.height);
+        let size = if self.interacting {
+            Size::new(
+                (full_size.width / LOW_RES_DIVISOR).max(1),
+                (full_size.height / LOW_RES_DIVISOR).max(1),
+            )
+        } else {
+            full_size
+        };
+        let size = UVec2::new(size.width, size.height).max(UVec2::ONE);
+
+        // Supersampling multiplies the samples buffer size, so it is reduced to fit the limits.
+        let limits = device.limits();
+        let max_samples = limits
+            .max_buffer_size
+            .min(limits.max_storage_buffer_binding_size as u64)
+            / SAMPLE_SIZE;
+        let aa_samples = (1..=self.controls.aa_samples.max(1))
+            .rev()
+            .find(|n| (size * *n).element_product() as u64 <= max_samples)
+            .unwrap_or(1);
+
+        let max_iter = self.controls.iterations();
+        let max_iter = if self.interacting {
+            (max_iter / INTERACTION_ITERS_DIVISOR).max(max_iter.min(ITERS_MIN))
+        } else {
+            max_iter
+        };
+        let power = self.controls.formula.power();
+        // Only escape-time coloring uses the average.
+        let stripe = self.controls.stripe && self.controls.coloring == ColoringMode::Iterations;
+        let marker = self
+            .marker
+            .map(|marker| marker.to_pixels(&self.controls, size))
+            .unwrap_or_default();
+        let grid = self
+            .grid
+            .map(|spacing| GridLines::new(&self.controls, spacing, size))
+            .unwrap_or_default();
+
+        pipeline.update(
+            device,
+            queue,
+            self.id,
+            &Uniforms {
+                resolution: Vec2::new(viewport.width as f32, viewport.height as f32),
+                center_hi,
+                center_lo,
+                seed: self.controls.julia.unwrap_or_default(),
+                jitter,
+                grid_size: size * aa_samples,
+                trap_point: self.controls.trap_point,
+                extent: self.controls.extent() as f32,
+                max_iter,
+                julia: self.controls.julia.is_some() as u32,
+                formula: self.controls.formula.index(),
+                deep: (self.controls.zoom > ZOOM_DEEP_THRESHOLD) as u32,
+                power,
+                escape_radius: self.controls.escape_radius,
+                perturbation: self.orbit.is_some() as u32,
+                orbit_len: self
+                    .orbit
+                    .as_ref()
+                    .map_or(0, |orbit| orbit.points.len() as u32),
+                trap: self.controls.coloring.trap(),
+                trap_radius: self.controls.trap_radius,
+                period_detection: (self.controls.interior == InteriorMode::Period) as u32,
+                tile_offset: UVec2::ZERO,
+                rotation: self.controls.rotation,
+                stripe_frequency: if stripe {
+                    self.controls.stripe_frequency
+                } else {
+                    0.0
+                },
+                distance_estimate: self.controls.coloring.uses_distance() as u32,
+                _padding: 0,
+            },
+            &ColorUniforms {
+                size,
+                aa_samples,
+                max_iter,
+                smooth_coloring: self.controls.smooth as u32,
+                coloring: self.controls.coloring as u32,
+                stripe: stripe as u32,
+                escape_radius: self.controls.escape_radius,
+                power,
+                line_width: self.controls.line_width * scale_factor,
+                interior: self.controls.interior as u32,
+                histogram: self.controls.histogram as u32,
+                gamma: self.controls.gamma,
+                marker: self.marker.is_some() as u32,
+                marker_center: marker.center,
+                marker_half_size: marker.half_size,
+                marker_axis: marker.axis,
+                grid: self.grid.is_some() as u32,
+                grid_spacing: grid.spacing,
+                grid_offset: grid.offset,
+                axes_offset: grid.axes_offset,
+                grid_axis: grid.axis,
+                color_offset: self.controls.color_offset,
+                color_frequency: self.controls.color_frequency,
+                elapsed: self.elapsed,
+                _padding: 0,
+            },
+            self.orbit.as_deref(),
+        );
+
+        let tile_progress = pipeline.iterate_tiles(device, queue, self.id, self.controls.tiles);
+
+        // Clip space has y up, the view has it down.
+        let scale = self.controls.scale(viewport.height as f32);
+        let half_size = DVec2::new(viewport.width as f64, -(viewport.height as f64)) * 0.5;
+        let trace: Vec<Vec2> = self
+            .trace
+            .iter()
+            .map(|&point| {
+                let pixels = self.controls.screen_offset(point - self.controls.center) / scale;
+                (pixels / half_size).as_vec2()
+            })
+            .collect();
+        pipeline.update_trace(device, queue, self.id, &trace);
+
+        let grid = size * aa_samples;
+        let request = self
+            .inspection
+            .as_ref()
+            .filter(|inspection| inspection.result.lock().unwrap().is_none())
+            .map(|inspection| {
+                let full_size = Vec2::new(full_size.width as f32, full_size.height as f32);
+                let sample = (inspection.pos * scale_factor * grid.as_vec2() / full_size)
+                    .as_uvec2()
+                    .min(grid - 1);
+                (inspection, (sample.y * grid.x + sample.x) as u64)
+            });
+        pipeline.update_readback(device, self.id, request);
+
+        match self.frame_index {
+            Some(frame_index) if !self.interacting => {
+                pipeline.update_cache(device, self.id, None);
+                pipeline.update_accumulation(device, self.id, full_size, Some(frame_index));
+            }
+            _ => {
+                pipeline.update_accumulation(device, self.id, full_size, None);
+                pipeline.update_cache(device, self.id, Some(Size::new(size.x, size.y)));
+            }
+        }
+
+        let gpu_time = pipeline.gpu_time(device, self.id);
+        if let Some(frame_stats) = &self.frame_stats {
+            let rendered = pipeline.renders(self.id);
+            let mut frame_stats = frame_stats.lock().unwrap();
+            frame_stats.record(Instant::now(), rendered);
+            frame_stats.resolution = size;
+            frame_stats.aa_samples = aa_samples;
+            if let Some(gpu_time) = gpu_time {
+                frame_stats.record_gpu_time(gpu_time);
+            }
+        }
+        tile_progress
+    }
+}
+
+impl shader::Primitive for FragmentShaderPrimitive {
+    fn prepare(
+        &self,
+        format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bounds: Rectangle,
+        _target_size: Size<u32>,
+        scale_factor: f32,
+        storage: &mut shader::Storage,
+    ) {
+        // Pipelines of a lost device are replaced, even by ones that don't build, as there is
+        // nothing left to draw with.
+        let lost = self.errors.take_loss()
+            || matches!(
+                storage.get::<Result<FragmentShaderPipeline, String>>(),
+                Some(Ok(pipeline)) if pipeline.device != device.global_id()
+            );
+        // The built-in sources don't change, so a pipeline that didn't build is only tried
+        // again on another device or surface format.
+        let failed = FailedBuild {
+            device: device.global_id(),
+            format,
+        };
+        let stale = match storage.get::<Result<FragmentShaderPipeline, String>>() {
+            Some(Ok(_)) => self.errors.take_failure(),
+            Some(Err(_)) => storage.get::<FailedBuild>() != Some(&failed),
+            None => true,
+        };
+        if lost || stale {
+            let pipeline = FragmentShaderPipeline::create(device, format, &self.errors);
+            if pipeline.is_err() {
+                storage.store(failed);
+            }
+            let buddhabrot = create_in_error_scope(device, &self.errors, || {
+                BuddhabrotPipeline::new(device, format)
+            });
+            if lost {
+                storage.store(pipeline);
+                storage.store(buddhabrot);
+            } else {
+                store_pipeline(storage, pipeline);
+                store_pipeline(storage, buddhabrot);
+            }
+        }
+
+        // Rendering is done in physical pixels, while `controls` and the cursor math in
+        // `update` work in logical ones.
+        let viewport = self.view_viewport((bounds * scale_factor).snap());
+
+        if self.controls.mode == RenderMode::Buddhabrot {
+            let Some(Ok(pipeline)) = storage.get_mut::<Result<BuddhabrotPipeline, String>>() else {
+                return;
+            };
+            if pipeline.format != format {
+                pipeline.set_format(device, format);
+            }
+            let samples = pipeline.prepare(device, queue, self.id, &self.controls, viewport);
+            self.buddhabrot_samples.store(samples, Ordering::Relaxed);
+            *self.tile_progress.lock().unwrap() = TileProgress::default();
+            return;
+        }
+
+        let Some(Ok(pipeline)) = storage.get_mut::<Result<FragmentShaderPipeline, String>>() else {
+            return;
+        };
+        // The surface format may change at runtime (e.g. when the window moves to another
+        // monitor), in which case only what draws to it has to be rebuilt.
+        if pipeline.format != format {
+            pipeline.set_format(device, format);
+        }
+        pipeline.set_palette(queue, &self.palette);
+        if let Some(shader) = &self.shader {
+            if pipeline.shader_id != Some(shader.id) {
+                pipeline.load_shader(device, shader);
+            }
+        }
+
+        let mut tile_progress = self.prepare_view(device, queue, pipeline, viewport, scale_factor);
+        if let Some(compare) = &self.compare {
+            let compare = compare.prepare_view(device, queue, pipeline, viewport, scale_factor);
+            tile_progress.done += compare.done;
+            tile_progress.total += compare.total;
+        }
+        *self.tile_progress.lock().unwrap() = tile_progress;
+        if let Some(minimap) = &self.minimap {
+            let viewport = minimap_bounds(viewport.into()).snap();
+            minimap.prepare_view(device, queue, pipeline, viewport, scale_factor);
+        }
+    }
+
+    fn render(
+        &self,
+        storage: &shader::Storage,
+        target: &wgpu::TextureView,
+        _target_size: Size<u32>,
+        // In physical pixels, like the sizes computed in `prepare`.
+        viewport: Rectangle<u32>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let viewport = self.view_viewport(viewport);
+        if self.controls.mode == RenderMode::Buddhabrot {
+            if let Some(Ok(pipeline)) = storage.get::<Result<BuddhabrotPipeline, String>>() {
+                pipeline.render(self.id, target, encoder, viewport);
+            }
+            return;
+        }
+
+        if let Some(Ok(pipeline)) = storage.get::<Result<FragmentShaderPipeline, String>>() {
+            match &self.compare {
+                Some(compare) => {
+                    let (left, right) = split_viewport(viewport, self.split);
+                    pipeline.render(self.id, target, encoder, viewport, left);
+                    pipeline.render(compare.id, target, encoder, viewport, right);
+                    // The split line.
+                    pipeline.render_trace(compare.id, target, encoder, viewport);
+                }
+                None => pipeline.render(self.id, target, encoder, viewport, viewport),
+            }
+            pipeline.render_trace(self.id, target, encoder, viewport);
+            if let Some(minimap) = &self.minimap {
+                let viewport = minimap_bounds(viewport.into()).snap();
+                pipeline.render(minimap.id, target, encoder, viewport, viewport);
+            }
+        }
+    }
+}
+
+/// Input on a [`FragmentShaderProgram`], for the application to apply to its [`Controls`].
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Drag by the given number of pixels within the view bounds.
+    PanningDelta(Vec2, Rectangle),
+    /// Keep gliding after a pan was released, with the given velocity in pixels per second.
+    StartMomentum(Vec2, Rectangle),
+    StopMomentum,
+    /// Zoom by wheel notches, keeping the point at a position relative to the top-left corner of
+    /// the view bounds in place.
+    ZoomDelta(Vec2, Rectangle, f32),
+    /// Like [`Message::ZoomDelta`], but from a pinch, which the zoom has to follow without delay.
+    PinchZoom(Vec2, Rectangle, f32),
+    /// Angle in radians the cursor swept clockwise around the view center while rotating.
+    RotationDelta(f32),
+    /// Iterate this many times, no longer deriving the count from the zoom level.
+    UpdateMaxIterations(u32),
+    MoveTrap(Vec2),
+    /// Point under the cursor, `None` once it left the view.
+    HoveredPoint(Option<DVec2>),
+    /// Keep zooming towards a position within the view bounds.
+    StartAutoZoom(Vec2, Rectangle),
+    /// Glide to center the view on a point, zooming in one level if set.
+    CenterOn(DVec2, bool),
+    /// Center the view on a point picked on the minimap at once.
+    JumpTo(DVec2),
+    /// Read back the escape data at a position within the view bounds, see
+    /// [`FragmentShaderProgram::inspect`].
+    Inspect(Vec2, Rectangle),
+    /// Sent instead of [`Message::HoveredPoint`] while [`FragmentShaderProgram::probe`] is set:
+    /// the cursor moved to a position within the view bounds, to be inspected.
+    Probe(Vec2, Rectangle),
+    /// Move the split line of a comparison to a fraction of the view width.
+    MoveSplit(f32),
+    /// The view was clicked without dragging it, at this point on the complex plane.
+    Clicked(DVec2),
+}
+
+#[derive(Default)]
+enum MouseInteraction {
+    #[default]
+    Idle,
+    /// Dragging with `button` held, last seen at `position` at `last_move`.
+    Panning {
+        button: mouse::Button,
+        position: Vec2,
+        last_move: Instant,
+        /// Recent cursor velocity in pixels per second, carried on as momentum on release.
+        velocity: Vec2,
+    },
+    MovingTrap,
+    /// Dragging the split line of a comparison.
+    MovingSplit,
+    /// Rotating with Alt held, the cursor last seen at the given offset from the view center.
+    Rotating {
+        offset: Vec2,
+    },
+    /// Panning with one finger, last seen at the given position.
+    Touching {
+        finger: (touch::Finger, Vec2),
+    },
+    /// Zooming and panning with two fingers, further fingers are ignored.
+    Pinching {
+        fingers: [(touch::Finger, Vec2); 2],
+    },
+}
+
+impl MouseInteraction {
+    fn panning(button: mouse::Button, position: Vec2) -> Self {
+        MouseInteraction::Panning {
+            button,
+            position,
+            last_move: Instant::now(),
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ProgramState {
+    interaction: MouseInteraction,
+    /// Time and position of the last left button press, for double-click detection.
+    last_press: Option<(Instant, Vec2)>,
+    modifiers: keyboard::Modifiers,
+    /// Whether the cursor was over the view when last seen.
+    hovering: bool,
+}
+
+/// Limits an application sets on the view, within the ones the renderer supports:
+/// [`ZOOM_MIN`]`..=`[`ZOOM_MAX`] and [`ITERS_MIN`]`..=`[`ITERS_MAX`], which are also the
+/// defaults.
+///
+/// ```
+/// # use fragment_shader_widget::Config;
+/// let config = Config::new().with_zoom_range(1.0..=25.0).with_iter_range(20..=2000);
+/// assert_eq!(config.zoom_range(), 1.0..=25.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    zoom_range: RangeInclusive<f32>,
+    iter_range: RangeInclusive<u32>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Zoom levels the view can reach, narrowed to the supported ones.
+    ///
+    /// # Panics
+    ///
+    /// If no supported zoom level is left, e.g. for an inverted range.
+    pub fn with_zoom_range(mut self, range: RangeInclusive<f32>) -> Self {
+        let (start, end) = (range.start().max(ZOOM_MIN), range.end().min(ZOOM_MAX));
+        assert!(
+            start <= end,
+            "zoom range {range:?} has no levels within {ZOOM_MIN}..={ZOOM_MAX}"
+        );
+        self.zoom_range = start..=end;
+        self
+    }
+
+    /// Iteration counts that can be set, narrowed to the supported ones. Automatic iteration
+    /// counts are clamped to them as well.
+    ///
+    /// # Panics
+    ///
+    /// If no supported iteration count is left, e.g. for an inverted range.
+    pub fn with_iter_range(mut self, range: RangeInclusive<u32>) -> Self {
+        let (start, end) = (*range.start().max(&ITERS_MIN), *range.end().min(&ITERS_MAX));
+        assert!(
+            start <= end,
+            "iteration range {range:?} has no counts within {ITERS_MIN}..={ITERS_MAX}"
+        );
+        self.iter_range = start..=end;
+        self
+    }
+
+    pub fn zoom_range(&self) -> RangeInclusive<f32> {
+        self.zoom_range.clone()
+    }
+
+    pub fn iter_range(&self) -> RangeInclusive<u32> {
+        self.iter_range.clone()
+    }
+
+    fn clamp_zoom(&self, zoom: f32) -> f32 {
+        zoom.clamp(*self.zoom_range.start(), *self.zoom_range.end())
+    }
+
+    fn clamp_iterations(&self, iterations: u32) -> u32 {
+        iterations.clamp(*self.iter_range.start(), *self.iter_range.end())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            zoom_range: ZOOM_MIN..=ZOOM_MAX,
+            iter_range: ITERS_MIN..=ITERS_MAX,
+        }
+    }
+}
+
+static NEXT_PROGRAM_ID: AtomicU64 = AtomicU64::new(0);
+
+pub struct FragmentShaderProgram {
+    id: u64,
+    pub controls: Controls,
+    pub config: Config,
+    /// Animation time in seconds, passed to the shader as is.
+    pub elapsed: f32,
+    /// Gradient escaped samples are colored by.
+    pub palette: Palette,
+    /// Set while the view is deep enough to be rendered with perturbation.
+    orbit: Option<Arc<ReferenceOrbit>>,
+    /// Settings the view is compared with, shown right of a split line. Only the coloring and
+    /// iteration settings are used, the rest follows `controls`, see [`Controls::with_view_of`].
+    /// Escape time views only.
+    pub compare: Option<Controls>,
+    /// Position of the split line as a fraction of the view width.
+    pub split: f32,
+    compare_id: u64,
+    /// Like `orbit`, for `compare`.
+    compare_orbit: Option<Arc<ReferenceOrbit>>,
+    /// Progressive sample index, `None` if progressive rendering is disabled.
+    pub frame_index: Option<u32>,
+    /// Frame time measurements, only collected while shown.
+    pub frame_stats: Option<Arc<Mutex<FrameStats>>>,
+    /// Whether the view is being panned or zoomed.
+    pub interacting: bool,
+    /// Show an overview of the whole set in a corner, marking the view on it. Clicking it
+    /// centers the view there.
+    pub minimap: bool,
+    minimap_id: u64,
+    /// Point whose orbit is drawn over the view, e.g. the hovered one.
+    pub orbit_point: Option<DVec2>,
+    /// Draw a coordinate grid over the view, see [`FragmentShaderProgram::grid_labels`].
+    pub grid: bool,
+    /// Inspect the pixel under the cursor as it moves, see [`Message::Probe`].
+    pub probe: bool,
+    /// Last pixel given to [`FragmentShaderProgram::inspect`].
+    inspection: Option<Arc<Inspection>>,
+    /// The one before, shown until the last one was read back.
+    previous_inspection: Option<Arc<Inspection>>,
+    interactive: bool,
+    /// Iteration stage replacing the built-in `shader.wgsl`.
+    shader: Option<Arc<ShaderSource>>,
+    /// Where rendering failures are reported, shared by all programs of the application.
+    pub errors: Arc<RenderErrors>,
+    /// Points iterated for the view in [`RenderMode::Buddhabrot`].
+    buddhabrot_samples: Arc<AtomicU64>,
+    /// Filled in by the primitive, see [`FragmentShaderProgram::tile_progress`].
+    tile_progress: Arc<Mutex<TileProgress>>,
+}
+
+impl FragmentShaderProgram {
+    /// Steps the iteration count or zooms around the view center for a key shortcut.
+    fn key_message(&self, key: &keyboard::Key, bounds: Rectangle) -> Option<Message> {
+        let iterations = self.controls.iterations();
+        match key.as_ref() {
+            // `=` is `+` without Shift on many layouts.
+            keyboard::Key::Character("+" | "=") => Some(Message::UpdateMaxIterations(
+                self.config
+                    .clamp_iterations(iterations.saturating_add(KEY_ITERS_STEP)),
+            )),
+            keyboard::Key::Character("-") => Some(Message::UpdateMaxIterations(
+                self.config
+                    .clamp_iterations(iterations.saturating_sub(KEY_ITERS_STEP)),
+            )),
+            keyboard::Key::Character(key @ ("[" | "]")) => {
+                let levels = if key == "]" {
+                    KEY_ZOOM_STEP
+                } else {
+                    -KEY_ZOOM_STEP
+                };
+                let center = Vec2::new(bounds.width, bounds.height) / 2.0;
+                Some(Message::ZoomDelta(
+                    center,
+                    bounds,
+                    levels / ZOOM_WHEEL_SCALE,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn new(controls: Controls) -> Self {
+        Self {
+            id: NEXT_PROGRAM_ID.fetch_add(1, Ordering::Relaxed),
+            controls,
+            config: Config::default(),
+            elapsed: 0.0,
+            palette: Palette::default(),
+            orbit: None,
+            compare: None,
+            split: SPLIT_DEFAULT,
+            compare_id: NEXT_PROGRAM_ID.fetch_add(1, Ordering::Relaxed),
+            compare_orbit: None,
+            frame_index: None,
+            frame_stats: None,
+            interacting: false,
+            minimap: false,
+            minimap_id: NEXT_PROGRAM_ID.fetch_add(1, Ordering::Relaxed),
+            orbit_point: None,
+            grid: false,
+            probe: false,
+            inspection: None,
+            previous_inspection: None,
+            interactive: true,
+            shader: None,
+            errors: Arc::default(),
+            buddhabrot_samples: Arc::default(),
+            tile_progress: Arc::default(),
+        }
+    }
+
+    /// A non-interactive program, used for previews next to the main view.
+    pub fn preview(controls: Controls) -> Self {
+        Self {
+            interactive: false,
+            ..Self::new(controls)
+        }
+    }
+
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Values of the grid lines drawn with [`FragmentShaderProgram::grid`] in a widget of
+    /// `bounds`: real parts along the bottom edge of the view and imaginary parts along the left
+    /// one. Labels that would be covered by the minimap are left out.
+    pub fn grid_labels(&self, bounds: Rectangle) -> Vec<GridLabel> {
+        let controls = &self.controls;
+        let view = controls.view_bounds(bounds);
+        let spacing = controls.grid_spacing(view.height);
+        let decimals = (-spacing.log10().floor()).max(0.0) as usize;
+        let minimap = self.minimap.then(|| minimap_bounds(view));
+
+        let (left, right) = (GRID_LABEL_MARGIN, view.width - GRID_LABEL_MARGIN);
+        let (top, bottom) = (GRID_LABEL_MARGIN, view.height - GRID_LABEL_MARGIN);
+        let edges = [
+            (Vec2::new(left, bottom), Vec2::new(right, bottom), true),
+            (Vec2::new(left, bottom), Vec2::new(left, top), false),
+        ];
+
+        let mut labels = Vec::new();
+        for (start, end, real) in edges {
+            let value = |pos| {
+                let point = controls.fractal_point(pos, view);
+                if real {
+                    point.x
+                } else {
+                    point.y
+                }
+            };
+            let (from, to) = (value(start), value(end));
+            // Lines parallel to the edge, when the view is rotated by about a right angle,
+            // barely cross it.
+            if (to - from).abs() < spacing {
+                continue;
+            }
+
+            let (low, high) = (from.min(to) / spacing, from.max(to) / spacing);
+            for line in (low.ceil() as i64)..=(high.floor() as i64) {
+                let line = line as f64 * spacing;
+                let t = ((line - from) / (to - from)) as f32;
+                let position =
+                    start + (end - start) * t + Vec2::new(view.x - bounds.x, view.y - bounds.y);
+                let covered = minimap.is_some_and(|minimap| {
+                    minimap.contains(iced::Point::new(
+                        bounds.x + position.x,
+                        bounds.y + position.y,
+                    ))
+                });
+                if covered {
+                    continue;
+                }
+
+                let text = if real {
+                    format!("{line:.decimals$}")
+                } else {
+                    format!("{line:.decimals$}i")
+                };
+                labels.push(GridLabel {
+                    position,
+                    text,
+                    real,
+                });
+            }
+        }
+        labels
+    }
+
+    /// Iterates with the given WGSL source instead of the built-in `shader.wgsl`. It must have
+    /// the same entry points and bindings.
+    pub fn with_shader(mut self, code: String) -> Self {
+        self.set_shader(code);
+        self
+    }
+
+    /// Replaces the iteration shader. If `code` doesn't compile, the previous shader stays in
+    /// use and [`FragmentShaderProgram::shader_result`] tells why.
+    pub fn set_shader(&mut self, code: String) {
+        self.shader = Some(Arc::new(ShaderSource::new(code)));
+    }
+
+    /// Whether the shader given with [`FragmentShaderProgram::set_shader`] compiled, `None`
+    /// until it has been tried or if there is none.
+    pub fn shader_result(&self) -> Option<Result<(), String>> {
+        self.shader.as_ref()?.result.lock().unwrap().clone()
+    }
+
+    /// Reads back the escape data of the pixel at `pos` within the view `bounds` as the next
+    /// frames are rendered, [`FragmentShaderProgram::inspected`] has it once it arrived.
+    pub fn inspect(&mut self, pos: Vec2, bounds: Rectangle) {
+        if self.inspected().is_some() {
+            self.previous_inspection = self.inspection.take();
+        }
+        self.inspection = Some(Arc::new(Inspection {
+            pos,
+            point: self.controls.fractal_point(pos, bounds),
+            max_iter: self.config.clamp_iterations(self.controls.iterations()),
+            escape_radius: self.controls.escape_radius,
+            power: self.controls.formula.power(),
+            result: Mutex::new(None),
+        }));
+    }
+
+    /// Escape data of the last inspected pixel that was read back, `None` until one was.
+    pub fn inspected(&self) -> Option<PixelInfo> {
+        [&self.inspection, &self.previous_inspection]
+            .into_iter()
+            .flatten()
+            .find_map(|inspection| inspection.result.lock().unwrap().clone()?.ok())
+    }
+
+    /// Why the last inspected pixel couldn't be read back, if it couldn't.
+    pub fn inspection_error(&self) -> Option<String> {
+        let inspection = self.inspection.as_ref()?;
+        inspection.result.lock().unwrap().clone()?.err()
+    }
+
+    /// Whether the inspected pixel is yet to be read back, keeping the view redrawn until then.
+    pub fn inspecting(&self) -> bool {
+        self.inspection
+            .as_ref()
+            .is_some_and(|inspection| inspection.result.lock().unwrap().is_none())
+    }
+
+    /// Points iterated so far for the view in [`RenderMode::Buddhabrot`].
+    pub fn buddhabrot_samples(&self) -> u64 {
+        self.buddhabrot_samples.load(Ordering::Relaxed)
+    }
+
+    /// Tiles of the view iterated so far, pending while the iteration is spread over several
+    /// frames, which need to be redrawn until it is done.
+    pub fn tile_progress(&self) -> TileProgress {
+        *self.tile_progress.lock().unwrap()
+    }
+
+    /// Whether the view is a Buddhabrot still accumulating, which needs to be redrawn every
+    /// frame until it reaches [`BUDDHABROT_SAMPLES_MAX`].
+    pub fn accumulating_buddhabrot(&self) -> bool {
+        self.controls.mode == RenderMode::Buddhabrot
+            && self.buddhabrot_samples() < BUDDHABROT_SAMPLES_MAX
+    }
+
+    /// Recomputes the reference orbit if the view center or iteration settings changed. Call
+    /// after changing `controls`.
+    pub fn update_reference_orbit(&mut self) {
+        self.orbit = updated_orbit(self.orbit.take(), &self.controls);
+        self.compare_orbit = match self.compared() {
+            Some(compare) => updated_orbit(self.compare_orbit.take(), &compare),
+            None => None,
+        };
+    }
+
+    /// The settings compared with, applied to the view, if comparing.
+    fn compared(&self) -> Option<Controls> {
+        self.compare
+            .filter(|_| self.controls.mode == RenderMode::EscapeTime)
+            .map(|compare| compare.with_view_of(&self.controls))
+    }
+
+    /// Horizontal position of the split line within the view `bounds`, if comparing.
+    fn split_x(&self, bounds: Rectangle) -> Option<f32> {
+        self.compared()
+            .map(|_| bounds.x + bounds.width * self.split.clamp(0.0, 1.0))
+    }
+
+    /// Whether `cursor` is close enough to the split line within `bounds` to drag it.
+    fn grabs_split(&self, cursor: Cursor, bounds: Rectangle) -> bool {
+        match (self.split_x(bounds), cursor.position_over(bounds)) {
+            (Some(x), Some(pos)) => (pos.x - x).abs() <= SPLIT_GRAB_TOLERANCE,
+            _ => false,
+        }
+    }
+}
+
+impl shader::Program<Message> for FragmentShaderProgram {
+    type State = ProgramState;
+    type Primitive = FragmentShaderPrimitive;
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        _cursor: mouse::Cursor,
+        bounds: Rectangle,
+    ) -> Self::Primitive {
+        let minimap = self.minimap.then(|| {
+            let marker = Marker::of_view(&self.controls, self.controls.view_bounds(bounds));
+            Box::new(FragmentShaderPrimitive {
+                marker: Some(marker),
+                palette: self.palette.clone(),
+                errors: self.errors.clone(),
+                ..FragmentShaderPrimitive::new(self.minimap_id, minimap_controls(&self.controls))
+            })
+        });
+
+        let view = self.controls.view_bounds(bounds);
+        let grid = self.grid.then(|| self.controls.grid_spacing(view.height));
+        let compare = self.compared().map(|compare| {
+            let x = view.width * self.split.clamp(0.0, 1.0);
+            Box::new(FragmentShaderPrimitive {
+                controls: Controls {
+                    max_iter: self.config.clamp_iterations(compare.iterations()),
+                    auto_iter: false,
+                    ..compare
+                },
+                elapsed: self.elapsed,
+                palette: self.palette.clone(),
+                orbit: self.compare_orbit.clone(),
+                frame_index: self.frame_index,
+                interacting: self.interacting,
+                shader: self.shader.clone(),
+                errors: self.errors.clone(),
+                // The split line, drawn like an orbit trace.
+                trace: vec![
+                    compare.fractal_point(Vec2::new(x, 0.0), view),
+                    compare.fractal_point(Vec2::new(x, view.height), view),
+                ],
+                grid,
+                ..FragmentShaderPrimitive::new(self.compare_id, compare)
+            })
+        });
+
+        FragmentShaderPrimitive {
+            id: self.id,
+            controls: Controls {
+                max_iter: self.config.clamp_iterations(self.controls.iterations()),
+                auto_iter: false,
+                ..self.controls
+            },
+            elapsed: self.elapsed,
+            palette: self.palette.clone(),
+            orbit: self.orbit.clone(),
+            frame_index: self.frame_index,
+            frame_stats: self.frame_stats.clone(),
+            interacting: self.interacting,
+            shader: self.shader.clone(),
+            errors: self.errors.clone(),
+            minimap,
+            compare,
+            split: self.split,
+            marker: None,
+            trace: self
+                .orbit_point
+                .map(|point| self.controls.orbit(point, TRACE_LEN))
+                .unwrap_or_default(),
+            grid,
+            inspection: self.inspection.clone(),
+            buddhabrot_samples: self.buddhabrot_samples.clone(),
+            tile_progress: self.tile_progress.clone(),
+        }
+    }
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+        shell: &mut Shell<'_, Message>,
+    ) -> (Status, Option<Message>) {
+        if !self.interactive {
+            return (Status::Ignored, None);
+        }
+
+        // Input in the letterbox bars is outside the view.
+        let bounds = self.controls.view_bounds(bounds);
+
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            state.modifiers = modifiers;
+        }
+
+        if let Event::Touch(event) = event {
+            return update_touch(&mut state.interaction, event, bounds, shell);
+        }
+
+        // Only while hovered, so that typing into text fields doesn't change the view, and
+        // without Ctrl, Alt or Logo, which belong to other shortcuts. Shift types `+`.
+        if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = &event {
+            if cursor.is_over(bounds)
+                && !(modifiers.control() || modifiers.alt() || modifiers.logo())
+            {
+                if let Some(message) = self.key_message(key, bounds) {
+                    return (Status::Captured, Some(message));
+                }
+            }
+        }
+
+        if let Event::Mouse(mouse::Event::WheelScrolled { delta }) = event {
+            if let Some(pos) = cursor.position_in(bounds) {
+                let pos = Vec2::new(pos.x, pos.y);
+                // In notches, trackpads scroll by pixels.
+                let delta = match delta {
+                    mouse::ScrollDelta::Lines { x, y } => Vec2::new(x, y),
+                    mouse::ScrollDelta::Pixels { x, y } => Vec2::new(x, y) / WHEEL_PIXELS_PER_LINE,
+                };
+                // Some platforms turn Shift+wheel into horizontal scrolling themselves.
+                let notches = if delta.y != 0.0 { delta.y } else { delta.x };
+
+                let message = if state.modifiers.control() {
+                    let max_iter =
+                        self.controls.iterations() as f32 * WHEEL_ITERS_FACTOR.powf(notches);
+                    Message::UpdateMaxIterations(
+                        self.config.clamp_iterations(max_iter.round() as u32),
+                    )
+                } else if state.modifiers.shift() || delta.y == 0.0 {
+                    Message::PanningDelta(
+                        Vec2::new(notches * bounds.width * WHEEL_PAN_FRACTION, 0.0),
+                        bounds,
+                    )
+                } else {
+                    Message::ZoomDelta(pos, bounds, delta.y)
+                };
+                return (Status::Captured, Some(message));
+            }
+        }
+
+        // A drag whose release went unseen, e.g. because it happened outside the window, ends
+        // once the cursor leaves the window or the button is pressed again.
+        match (&state.interaction, &event) {
+            (MouseInteraction::Panning { .. }, Event::Mouse(mouse::Event::CursorLeft)) => {
+                state.interaction = MouseInteraction::Idle;
+            }
+            (
+                MouseInteraction::Panning { button, .. },
+                Event::Mouse(mouse::Event::ButtonPressed(pressed)),
+            ) if pressed == button => {
+                state.interaction = MouseInteraction::Idle;
+            }
+            (
+                MouseInteraction::MovingTrap
+                | MouseInteraction::MovingSplit
+                | MouseInteraction::Rotating { .. },
+                Event::Mouse(mouse::Event::CursorLeft),
+            ) => {
+                state.interaction = MouseInteraction::Idle;
+            }
+            _ => {}
+        }
+
+        match &mut state.interaction {
+            MouseInteraction::Idle => {
+                if let Event::Mouse(mouse::Event::CursorMoved { .. } | mouse::Event::CursorLeft) =
+                    event
+                {
+                    if let Some(pos) = cursor.position_in(bounds) {
+                        state.hovering = true;
+                        if self.probe {
+                            let message = Message::Probe(Vec2::new(pos.x, pos.y), bounds);
+                            return (Status::Ignored, Some(message));
+                        }
+                        let point = self.controls.fractal_point(Vec2::new(pos.x, pos.y), bounds);
+                        return (Status::Ignored, Some(Message::HoveredPoint(Some(point))));
+                    }
+
+                    if state.hovering {
+                        state.hovering = false;
+                        return (Status::Ignored, Some(Message::HoveredPoint(None)));
+                    }
+                }
+
+                if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
+                    let minimap = minimap_bounds(bounds);
+                    if let (true, Some(pos)) = (self.minimap, cursor.position_in(minimap)) {
+                        let point = minimap_controls(&self.controls)
+                            .fractal_point(Vec2::new(pos.x, pos.y), minimap);
+                        return (Status::Captured, Some(Message::JumpTo(point)));
+                    }
+
+                    if self.grabs_split(cursor, bounds) {
+                        state.interaction = MouseInteraction::MovingSplit;
+                        return (Status::Captured, Some(Message::StopMomentum));
+                    }
+
+                    if let (true, Some(pos)) = (state.modifiers.alt(), cursor.position_over(bounds))
+                    {
+                        state.interaction = MouseInteraction::Rotating {
+                            offset: Vec2::new(pos.x, pos.y)
+                                - Vec2::new(bounds.center_x(), bounds.center_y()),
+                        };
+                        return (Status::Captured, Some(Message::StopMomentum));
+                    }
+
+                    let moves_trap =
+                        state.modifiers.control() && self.controls.coloring.trap() != 0;
+                    if let (true, Some(pos)) = (moves_trap, cursor.position_in(bounds)) {
+                        state.interaction = MouseInteraction::MovingTrap;
+                        let point = self.controls.fractal_point(Vec2::new(pos.x, pos.y), bounds);
+                        return (Status::Captured, Some(Message::MoveTrap(point.as_vec2())));
+                    }
+
+                    if let Some(pos) = cursor.position_over(bounds) {
+                        let now = Instant::now();
+                        let pos = Vec2::new(pos.x, pos.y);
+                        let double_click = state.last_press.is_some_and(|(time, last_pos)| {
+                            now - time < DOUBLE_CLICK_INTERVAL
+                                && pos.distance(last_pos) <= DOUBLE_CLICK_DISTANCE
+                        });
+
+                        // The second press doesn't start a pan.
+                        if double_click {
+                            state.last_press = None;
+                            let pos = pos - Vec2::new(bounds.x, bounds.y);
+                            let message = if state.modifiers.shift() {
+                                Message::StartAutoZoom(pos, bounds)
+                            } else {
+                                let point = self.controls.fractal_point(pos, bounds);
+                                Message::CenterOn(point, state.modifiers.control())
+                            };
+                            return (Status::Captured, Some(message));
+                        }
+
+                        state.last_press = Some((now, pos));
+                        // Ctrl+click inspects, without panning in between the clicks of a
+                        // Ctrl+double-click.
+                        if state.modifiers.control() {
+                            let pos = pos - Vec2::new(bounds.x, bounds.y);
+                            return (Status::Captured, Some(Message::Inspect(pos, bounds)));
+                        }
+
+                        state.interaction = MouseInteraction::panning(mouse::Button::Left, pos);
+                        return (Status::Captured, Some(Message::StopMomentum));
+                    }
+                }
+
+                if let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Middle)) = event {
+                    if let Some(pos) = cursor.position_over(bounds) {
+                        state.interaction = MouseInteraction::panning(
+                            mouse::Button::Middle,
+                            Vec2::new(pos.x, pos.y),
+                        );
+                        return (Status::Captured, Some(Message::StopMomentum));
+                    }
+                }
+            }
+            MouseInteraction::Panning {
+                button,
+                position,
+                last_move,
+                velocity,
+            } => match event {
+                // Other buttons pressed or released meanwhile don't affect the pan.
+                Event::Mouse(mouse::Event::ButtonReleased(released)) if released == *button => {
+                    let glides = last_move.elapsed() < MOMENTUM_RELEASE_WINDOW
+                        && velocity.length() > MOMENTUM_MIN_SPEED;
+                    let velocity = *velocity;
+                    let pos = *position - Vec2::new(bounds.x, bounds.y);
+                    state.interaction = MouseInteraction::Idle;
+                    if glides {
+                        return (
+                            Status::Captured,
+                            Some(Message::StartMomentum(velocity, bounds)),
+                        );
+                    }
+                    // Still counting toward a double-click, so the view wasn't dragged.
+                    if released == mouse::Button::Left && state.last_press.is_some() {
+                        let point = self.controls.fractal_point(pos, bounds);
+                        return (Status::Captured, Some(Message::Clicked(point)));
+                    }
+                }
+                Event::Mouse(mouse::Event::CursorMoved {
+                    position: new_position,
+                }) => {
+                    let pos = Vec2::new(new_position.x, new_position.y);
+                    let delta = pos - *position;
+                    *position = pos;
+
+                    // A press that dragged the view doesn't count toward a double-click,
+                    // even if the cursor came back.
+                    if let Some((_, last_pos)) = state.last_press {
+                        if pos.distance(last_pos) > DOUBLE_CLICK_DISTANCE {
+                            state.last_press = None;
+                        }
+                    }
+
+                    let now = Instant::now();
+                    let dt = (now - *last_move).as_secs_f32();
+                    if dt > 0.0 {
+                        *velocity = velocity.lerp(delta / dt, PAN_VELOCITY_SMOOTHING);
+                        *last_move = now;
+                    }
+                    return (Status::Captured, Some(Message::PanningDelta(delta, bounds)));
+                }
+                _ => {}
+            },
+            MouseInteraction::MovingTrap => match event {
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    state.interaction = MouseInteraction::Idle;
+                }
+                Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                    let pos = Vec2::new(position.x - bounds.x, position.y - bounds.y);
+                    let point = self.controls.fractal_point(pos, bounds);
+                    return (Status::Captured, Some(Message::MoveTrap(point.as_vec2())));
+                }
+                _ => {}
+            },
+            MouseInteraction::MovingSplit => match event {
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    state.interaction = MouseInteraction::Idle;
+                }
+                Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                    let split = ((position.x - bounds.x) / bounds.width).clamp(0.0, 1.0);
+                    return (Status::Captured, Some(Message::MoveSplit(split)));
+                }
+                _ => {}
+            },
+            MouseInteraction::Rotating { offset } => match event {
+                Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    state.interaction = MouseInteraction::Idle;
+                }
+                Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                    let new_offset = Vec2::new(position.x, position.y)
+                        - Vec2::new(bounds.center_x(), bounds.center_y());
+                    // Too close to the center for the angle to mean anything.
+                    if new_offset.length() < ROTATION_DEAD_ZONE {
+                        return (Status::Captured, None);
+                    }
+
+                    let angle = offset.angle_between(new_offset);
+                    *offset = new_offset;
+                    if angle.is_finite() {
+                        return (Status::Captured, Some(Message::RotationDelta(angle)));
+                    }
+                }
+                _ => {}
+            },
+            // Mouse input is ignored while touching, as platforms may emulate it from touches.
+            MouseInteraction::Touching { .. } | MouseInteraction::Pinching { .. } => {}
+        };
+
+        (Status::Ignored, None)
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> mouse::Interaction {
+        match state.interaction {
+            // Also outside the bounds, the drag goes on until the button is released.
+            MouseInteraction::Panning { .. } => mouse::Interaction::Grabbing,
+            MouseInteraction::MovingTrap => mouse::Interaction::Crosshair,
+            MouseInteraction::MovingSplit => mouse::Interaction::ResizingHorizontally,
+            MouseInteraction::Rotating { .. } => mouse::Interaction::Grabbing,
+            MouseInteraction::Touching { .. } | MouseInteraction::Pinching { .. } => {
+                mouse::Interaction::default()
+            }
+            MouseInteraction::Idle if !self.interactive => mouse::Interaction::default(),
+            MouseInteraction::Idle
+                if self.minimap
+                    && cursor.is_over(minimap_bounds(self.controls.view_bounds(bounds))) =>
+            {
+                mouse::Interaction::Pointer
+            }
+            MouseInteraction::Idle
+                if self.grabs_split(cursor, self.controls.view_bounds(bounds)) =>
+            {
+                mouse::Interaction::ResizingHorizontally
+            }
+            MouseInteraction::Idle if cursor.is_over(self.controls.view_bounds(bounds)) => {
+                mouse::Interaction::Grab
+            }
+            MouseInteraction::Idle => mouse::Interaction::default(),
+        }
+    }
+}
+
+/// Handles touch input: one finger pans, two fingers zoom by their change in distance and pan by
+/// the movement of their midpoint.
+fn update_touch(
+    interaction: &mut MouseInteraction,
+    event: touch::Event,
+    bounds: Rectangle,
+    shell: &mut Shell<'_, Message>,
+) -> (Status, Option<Message>) {
+    let to_vec = |position: iced::Point| Vec2::new(position.x, position.y);
+    match (&mut *interaction, event) {
+        (MouseInteraction::Idle, touch::Event::FingerPressed { id, position })
+            if bounds.contains(position) =>
+        {
+            *interaction = MouseInteraction::Touching {
+                finger: (id, to_vec(position)),
+            };
+            (Status::Captured, Some(Message::StopMomentum))
+        }
+        (MouseInteraction::Touching { finger }, touch::Event::FingerPressed { id, position }) => {
+            *interaction = MouseInteraction::Pinching {
+                fingers: [*finger, (id, to_vec(position))],
+            };
+            (Status::Captured, None)
+        }
+        (MouseInteraction::Touching { finger }, touch::Event::FingerMoved { id, position })
+            if finger.0 == id =>
+        {
+            let position = to_vec(position);
+            let delta = position - finger.1;
+            finger.1 = position;
+            (Status::Captured, Some(Message::PanningDelta(delta, bounds)))
+        }
+        (
+            MouseInteraction::Touching { finger },
+            touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. },
+        ) if finger.0 == id => {
+            *interaction = MouseInteraction::Idle;
+            (Status::Captured, None)
+        }
+        (MouseInteraction::Pinching { fingers }, touch::Event::FingerMoved { id, position }) => {
+            let Some(index) = fingers.iter().position(|finger| finger.0 == id) else {
+                return (Status::Ignored, None);
+            };
+            let [(_, a), (_, b)] = *fingers;
+            fingers[index].1 = to_vec(position);
+            let [(_, new_a), (_, new_b)] = *fingers;
+
+            let midpoint = (new_a + new_b) * 0.5;
+            shell.publish(Message::PanningDelta(midpoint - (a + b) * 0.5, bounds));
+
+            let (distance, new_distance) = (a.distance(b), new_a.distance(new_b));
+            if distance == 0.0 || new_distance == 0.0 {
+                return (Status::Captured, None);
+            }
+            // In wheel steps, so that the zoom follows the fingers.
+            let delta = (new_distance / distance).log2() / ZOOM_WHEEL_SCALE;
+            let pos = midpoint - Vec2::new(bounds.x, bounds.y);
+            (
+                Status::Captured,
+                Some(Message::PinchZoom(pos, bounds, delta)),
+            )
+        }
+        (
+            MouseInteraction::Pinching { fingers },
+            touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. },
+        ) => {
+            let Some(index) = fingers.iter().position(|finger| finger.0 == id) else {
+                return (Status::Ignored, None);
+            };
+            *interaction = MouseInteraction::Touching {
+                finger: fingers[1 - index],
+            };
+            (Status::Captured, None)
+        }
+        _ => (Status::Ignored, None),
+    }
+}
+
+fn parse_flag(arg: &str) -> Result<bool, String> {
+    match arg {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(String::from("must be 0 or 1")),
+    }
+}
+
+#[cfg(any(test, feature = "headless"))]
+/// Format of headless renders, matching the PNG's 8-bit sRGB pixels.
+const RENDER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[cfg(any(test, feature = "headless"))]
+/// Creates a device that doesn't render to a window, on a GPU or failing that a software adapter.
+/// `optional_features` are enabled if the adapter supports them.
+fn headless_device(
+    optional_features: wgpu::Features,
+) -> Result<(wgpu::AdapterInfo, wgpu::Device, wgpu::Queue), String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))
+        .or_else(|| {
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            }))
+        })
+        .ok_or("no GPU or software adapter available")?;
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("headless render device"),
+            required_features: adapter.features() & optional_features,
+            required_limits: adapter.limits(),
+        },
+        None,
+    ))
+    .map_err(|err| err.to_string())?;
+
+    Ok((adapter.get_info(), device, queue))
+}
+
+#[cfg(any(test, feature = "headless"))]
+/// Renders views into PNG files without iced.
+pub struct HeadlessRenderer {
+    pub adapter: wgpu::AdapterInfo,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    storage: shader::Storage,
+    errors: Arc<RenderErrors>,
+    size: Size<u32>,
+    format: wgpu::TextureFormat,
+    palette: Palette,
+    texture: wgpu::Texture,
+    readback: wgpu::Buffer,
+    /// Rows of a texture copy are padded to a fixed alignment.
+    padded_row_size: u32,
+    /// Measures whole frames, `None` without timestamp queries.
+    timer: Option<GpuTimer>,
+}
+
+#[cfg(any(test, feature = "headless"))]
+impl HeadlessRenderer {
+    pub fn new(size: Size<u32>) -> Result<Self, String> {
+        Self::with_format(size, RENDER_FORMAT)
+    }
+
+    fn with_format(size: Size<u32>, format: wgpu::TextureFormat) -> Result<Self, String> {
+        let (adapter, device, queue) =
+            headless_device(wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::PUSH_CONSTANTS)?;
+
+        let max_size = device.limits().max_texture_dimension_2d;
+        if size.width.max(size.height) > max_size {
+            return Err(format!(
+                "{}x{} exceeds the maximum size of {max_size}x{max_size}",
+                size.width, size.height
+            ));
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless render target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let padded_row_size = (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("headless render readback"),
+            size: padded_row_size as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let timer = GpuTimer::new(&device, &queue);
+
+        Ok(Self {
+            adapter,
+            device,
+            queue,
+            storage: shader::Storage::default(),
+            errors: Arc::default(),
+            size,
+            format,
+            palette: Palette::default(),
+            texture,
+            readback,
+            padded_row_size,
+            timer,
+        })
+    }
+
+    /// Colors the following frames by `palette`.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Whether frames are timed with GPU timestamp queries rather than the wall clock.
+    pub fn gpu_timestamps(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Renders `controls` into the target texture and waits for it to finish. Returns the GPU
+    /// time of the frame in seconds, or without timestamp queries the wall-clock time it took
+    /// after submitting, summed over the frames a tiled iteration takes.
+    pub fn draw(&mut self, controls: Controls) -> Result<f32, String> {
+        // The image size already sets the aspect ratio.
+        let controls = Controls {
+            aspect: AspectRatio::Free,
+            ..controls
+        };
+        let primitive = FragmentShaderPrimitive {
+            errors: self.errors.clone(),
+            palette: self.palette.clone(),
+            ..FragmentShaderPrimitive::new(0, controls)
+        };
+        let viewport = Rectangle {
+            x: 0,
+            y: 0,
+            width: self.size.width,
+            height: self.size.height,
+        };
+        // Tiles left over by a frame are iterated by the next ones, until the image is whole.
+        let mut time = 0.0;
+        loop {
+            time += self.draw_primitive(&primitive, viewport)?;
+            if !primitive.tile_progress.lock().unwrap().pending() {
+                return Ok(time);
+            }
+        }
+    }
+
+    /// Renders `primitive` into the `viewport` of the target texture, leaving the rest as is,
+    /// like a widget within a window.
+    fn draw_primitive(
+        &mut self,
+        primitive: &impl shader::Primitive,
+        viewport: Rectangle<u32>,
+    ) -> Result<f32, String> {
+        let size = self.size;
+        primitive.prepare(
+            self.format,
+            &self.device,
+            &self.queue,
+            viewport.into(),
+            size,
+            1.0,
+            &mut self.storage,
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless render"),
+            });
+        let view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Timestamps can only be written by passes, so empty ones mark the frame's start and end.
+        let timestamp_pass = |encoder: &mut wgpu::CommandEncoder, timer: &GpuTimer, index| {
+            encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("headless render timestamp"),
+                timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                    query_set: &timer.query_set,
+                    beginning_of_pass_write_index: Some(index),
+                    end_of_pass_write_index: None,
+                }),
+            });
+        };
+        if let Some(timer) = &self.timer {
+            timestamp_pass(&mut encoder, timer, 0);
+        }
+        primitive.render(&self.storage, &view, size, viewport, &mut encoder);
+        if let Some(timer) = &self.timer {
+            timestamp_pass(&mut encoder, timer, 1);
+            timer.copy_timestamps(&mut encoder);
+        }
+
+        let submitted = Instant::now();
+        self.queue.submit([encoder.finish()]);
+        if let Some(err) = self.errors.last.lock().unwrap().clone() {
+            return Err(err);
+        }
+
+        match &self.timer {
+            Some(timer) => timer.read_blocking(&self.device),
+            None => {
+                self.device.poll(wgpu::Maintain::Wait);
+                Ok(submitted.elapsed().as_secs_f32())
+            }
+        }
+    }
+
+    /// Renders `controls` into the PNG file at `path`.
+    pub fn render_png(&mut self, controls: Controls, path: &std::path::Path) -> Result<(), String> {
+        self.draw(controls)?;
+        let pixels = self.read_pixels()?;
+
+        let size = self.size;
+        let file = std::fs::File::create(path).map_err(|err| err.to_string())?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), size.width, size.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|err| err.to_string())
+    }
+
+    /// Copies the last drawn frame back from the GPU, as RGBA rows without padding.
+    fn read_pixels(&self) -> Result<Vec<u8>, String> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless render readback"),
+            });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_row_size),
+                    rows_per_image: None,
+                },
+            },
+            self.texture.size(),
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|err| err.to_string())?
+            .map_err(|err| err.to_string())?;
+
+        let size = self.size;
+        let row_size = size.width as usize * 4;
+        let pixels: Vec<u8> = self
+            .readback
+            .slice(..)
+            .get_mapped_range()
+            .chunks(self.padded_row_size as usize)
+            .flat_map(|row| &row[..row_size])
+            .copied()
+            .collect();
+        self.readback.unmap();
+        Ok(pixels)
+    }
+
+    /// Makes the next frame render from scratch even if the view didn't change.
+    pub fn invalidate(&mut self) {
+        if let Some(Ok(pipeline)) = self
+            .storage
+            .get_mut::<Result<FragmentShaderPipeline, String>>()
+        {
+            pipeline.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS_SIZE: Vec2 = Vec2::new(800.0, 600.0);
+
+    fn test_bounds() -> Rectangle {
+        Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y))
+    }
+
+    fn point_under(controls: &Controls, cursor: Vec2) -> DVec2 {
+        controls.fractal_point(cursor, test_bounds())
+    }
+
+    #[test]
+    fn zoom_keeps_point_under_cursor() {
+        let cursors = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(400.0, 300.0),
+            Vec2::new(123.0, 456.0),
+            Vec2::new(800.0, 600.0),
+        ];
+
+        for cursor in cursors {
+            for wheel_delta in [-3.0, -1.0, 1.0, 3.0] {
+                let before = Controls::default();
+                let after = apply_zoom(
+                    before,
+                    cursor,
+                    test_bounds(),
+                    wheel_delta,
+                    &Config::default(),
+                );
+
+                let error = (point_under(&before, cursor) - point_under(&after, cursor)).length();
+                assert!(error < 1e-12, "cursor {cursor} drifted by {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn extent_decreases_with_zoom() {
+        let mut prev_extent = f64::INFINITY;
+        let mut zoom = ZOOM_MIN;
+        while zoom <= ZOOM_MAX {
+            let extent = Controls {
+                zoom,
+                ..Controls::default()
+            }
+            .extent();
+            assert!(extent < prev_extent);
+            prev_extent = extent;
+            zoom += 0.5;
+        }
+    }
+
+    #[test]
+    fn view_height_is_kept_on_resize() {
+        let controls = Controls::default();
+        let top_edge = |width: f32, height: f32| {
+            let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(width, height));
+            controls.fractal_point(Vec2::new(width * 0.5, 0.0), bounds)
+        };
+
+        let extent = (top_edge(800.0, 600.0) - controls.center).length() * 2.0;
+        assert!((extent - controls.extent()).abs() < 1e-12);
+        for (width, height) in [(1600.0, 600.0), (200.0, 1200.0)] {
+            let edge = top_edge(width, height);
+            assert!((edge - top_edge(800.0, 600.0)).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn letterboxing_keeps_the_aspect_ratio() {
+        let bounds = Rectangle::new(iced::Point::new(10.0, 20.0), Size::new(800.0, 300.0));
+        let controls = |aspect| Controls {
+            aspect,
+            ..Controls::default()
+        };
+
+        assert_eq!(controls(AspectRatio::Free).view_bounds(bounds), bounds);
+        assert_eq!(
+            controls(AspectRatio::Square).view_bounds(bounds),
+            Rectangle::new(iced::Point::new(260.0, 20.0), Size::new(300.0, 300.0))
+        );
+        let tall = Rectangle::new(iced::Point::ORIGIN, Size::new(320.0, 400.0));
+        assert_eq!(
+            controls(AspectRatio::Widescreen).view_bounds(tall),
+            Rectangle::new(iced::Point::new(0.0, 110.0), Size::new(320.0, 180.0))
+        );
+    }
+
+    #[test]
+    fn letterbox_bars_ignore_input() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls {
+            aspect: AspectRatio::Square,
+            ..Controls::default()
+        });
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let press_at = |x, y| {
+            let mut state = ProgramState::default();
+            let mut messages = Vec::new();
+            let mut shell = Shell::new(&mut messages);
+            let cursor = Cursor::Available(iced::Point::new(x, y));
+            program.update(&mut state, press.clone(), test_bounds(), cursor, &mut shell)
+        };
+
+        // The view is the 600x600 square in the middle.
+        assert!(matches!(press_at(50.0, 300.0), (Status::Ignored, None)));
+        assert!(matches!(press_at(750.0, 300.0), (Status::Ignored, None)));
+        assert!(matches!(
+            press_at(400.0, 300.0),
+            (Status::Captured, Some(Message::StopMomentum))
+        ));
+    }
+
+    #[test]
+    fn minimap_clicks_center_the_view() {
+        use shader::Program as _;
+
+        let controls = Controls {
+            zoom: 20.0,
+            ..Controls::default()
+        };
+        let mut program = FragmentShaderProgram::new(controls);
+        let minimap = minimap_bounds(test_bounds());
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let press_at = |program: &FragmentShaderProgram, pos: iced::Point| {
+            let mut state = ProgramState::default();
+            let mut messages = Vec::new();
+            let mut shell = Shell::new(&mut messages);
+            let cursor = Cursor::Available(pos);
+            program.update(&mut state, press.clone(), test_bounds(), cursor, &mut shell)
+        };
+
+        assert!(matches!(
+            press_at(&program, minimap.center()),
+            (Status::Captured, Some(Message::StopMomentum))
+        ));
+
+        program.minimap = true;
+        let (status, message) = press_at(&program, minimap.center());
+        assert_eq!(status, Status::Captured);
+        let Some(Message::JumpTo(point)) = message else {
+            panic!("unexpected message {message:?}");
+        };
+        assert!(point.abs_diff_eq(minimap_controls(&controls).center, 1e-3));
+
+        // The top-left corner shows the far top-left of the set, whatever the view's zoom.
+        let (_, message) = press_at(&program, minimap.position());
+        let Some(Message::JumpTo(point)) = message else {
+            panic!("unexpected message {message:?}");
+        };
+        assert!(point.x < -2.5 && point.y < -1.4, "{point}");
+    }
+
+    #[test]
+    fn orbits_follow_the_formula() {
+        let controls = Controls::default();
+
+        // -1 cycles between -1 and 0, 1 escapes after 1, 2, 5.
+        let cycle = controls.orbit(DVec2::new(-1.0, 0.0), 5);
+        assert_eq!(cycle.len(), 5);
+        assert_eq!(cycle[1], DVec2::ZERO);
+        assert_eq!(cycle[4], DVec2::new(-1.0, 0.0));
+        let escaped = controls.orbit(DVec2::new(1.0, 0.0), TRACE_LEN);
+        assert!(escaped.len() < TRACE_LEN);
+        assert!(escaped.last().unwrap().length() > controls.escape_radius as f64);
+
+        // i under the tricorn: conj(i)^2 + i = -1 + i.
+        let tricorn = Controls {
+            formula: Formula::Tricorn,
+            ..controls
+        };
+        assert_eq!(tricorn.orbit(DVec2::Y, 2)[1], DVec2::new(-1.0, 1.0));
+
+        // Julia orbits start at the point and add the seed.
+        let julia = Controls {
+            julia: Some(Vec2::new(0.5, 0.0)),
+            ..controls
+        };
+        assert_eq!(julia.orbit(DVec2::ZERO, 2)[1], DVec2::new(0.5, 0.0));
+    }
+
+    #[test]
+    fn traces_leave_out_points_far_outside_the_view() {
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.5, -0.5),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(0.1, 0.1),
+            Vec2::new(-1.5, 1.5),
+            Vec2::new(1.9, 0.0),
+            Vec2::new(0.0, -30.0),
+            Vec2::new(0.2, 0.0),
+        ];
+        // The lone point between two far ones can't form a line.
+        assert_eq!(trace_runs(&points), vec![0..2, 3..6]);
+        assert!(trace_runs(&[Vec2::ZERO]).is_empty());
+        assert!(trace_runs(&[Vec2::ZERO, Vec2::NAN]).is_empty());
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let cursor = Vec2::new(100.0, 100.0);
+        let narrowed = Config::default().with_zoom_range(2.0..=10.0);
+
+        for (config, min, max) in [
+            (Config::default(), ZOOM_MIN, ZOOM_MAX),
+            (narrowed, 2.0, 10.0),
+        ] {
+            let zoomed_in = Controls {
+                zoom: max,
+                ..Controls::default()
+            };
+            let after = apply_zoom(zoomed_in, cursor, test_bounds(), 10.0, &config);
+            assert_eq!(after.zoom, max);
+            // The view doesn't pan at the limit.
+            assert_eq!(after.center, zoomed_in.center);
+
+            let zoomed_out = Controls {
+                zoom: min,
+                ..Controls::default()
+            };
+            let after = apply_zoom(zoomed_out, cursor, test_bounds(), -10.0, &config);
+            assert_eq!(after.zoom, min);
+            assert_eq!(after.center, zoomed_out.center);
+        }
+    }
+
+    #[test]
+    fn sanitize_keeps_controls_finite_and_in_range() {
+        let broken = [
+            (f32::NAN, DVec2::new(f64::NAN, 0.5), 0),
+            (
+                f32::INFINITY,
+                DVec2::new(f64::INFINITY, f64::NEG_INFINITY),
+                u32::MAX,
+            ),
+            (f32::NEG_INFINITY, DVec2::splat(f64::MAX), 1),
+            (1e30, DVec2::new(-1e300, 1e300), ITERS_MAX + 1),
+        ];
+
+        for (zoom, center, max_iter) in broken {
+            let mut controls = Controls {
+                zoom,
+                center,
+                max_iter,
+                rotation: f32::NAN,
+                ..Controls::default()
+            };
+            controls.sanitize(&Config::default());
+
+            assert!(
+                (ZOOM_MIN..=ZOOM_MAX).contains(&controls.zoom),
+                "{controls:?}"
+            );
+            assert!(controls.center.is_finite(), "{controls:?}");
+            assert!((ITERS_MIN..=ITERS_MAX).contains(&controls.max_iter));
+            assert!(controls.rotation.is_finite());
+            assert!(controls.extent().is_finite() && controls.extent() > 0.0);
+        }
+
+        // Valid values are left alone.
+        let mut controls = Controls::default();
+        controls.sanitize(&Config::default());
+        assert_eq!(controls.view_string(), Controls::default().view_string());
+
+        // Narrower limits are kept as well.
+        let config = Config::new()
+            .with_zoom_range(3.0..=10.0)
+            .with_iter_range(50..=100);
+        controls.sanitize(&config);
+        assert_eq!((controls.zoom, controls.max_iter), (3.0, 50));
+        // And can't go beyond what is supported.
+        let config = Config::new().with_zoom_range(0.0..=100.0);
+        assert_eq!(config.zoom_range(), ZOOM_MIN..=ZOOM_MAX);
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn configs_without_levels_or_counts_are_rejected() {
+        let panics = |build: fn() -> Config| std::panic::catch_unwind(build).is_err();
+        assert!(panics(|| Config::new().with_zoom_range(45.0..=60.0)));
+        assert!(panics(|| Config::new().with_zoom_range(10.0..=5.0)));
+        assert!(panics(|| Config::new().with_iter_range(10..=5)));
+        assert!(panics(
+            || Config::new().with_iter_range(ITERS_MAX + 1..=u32::MAX)
+        ));
+        assert!(!panics(|| Config::new().with_iter_range(100..=100)));
+    }
+
+    #[test]
+    fn auto_iterations_follow_zoom() {
+        let manual = Controls {
+            max_iter: 123,
+            zoom: 30.0,
+            ..Controls::default()
+        };
+        assert_eq!(manual.iterations(), 123);
+
+        let auto = |zoom| {
+            Controls {
+                zoom,
+                auto_iter: true,
+                ..manual
+            }
+            .iterations()
+        };
+        assert!(auto(ZOOM_MIN) >= ITERS_MIN);
+        // Doubling the zoom level quadruples the iterations added.
+        assert_eq!(auto(10.0) - ITERS_DEFAULT, 4 * (auto(5.0) - ITERS_DEFAULT));
+        assert_eq!(auto(ZOOM_MAX), ITERS_AUTO_MAX);
+    }
+
+    #[test]
+    fn coordinate_precision_resolves_pixels() {
+        for zoom in [ZOOM_MIN, ZOOM_DEFAULT, 20.0, ZOOM_MAX] {
+            let controls = Controls {
+                zoom,
+                ..Controls::default()
+            };
+            let step = 10.0_f64.powi(-(controls.coordinate_precision() as i32));
+            assert!(
+                step < controls.scale(PRECISION_MAX_HEIGHT as f32),
+                "zoom {zoom}"
+            );
+        }
+    }
+
+    #[test]
+    fn grid_lines_stay_round_and_readable() {
+        for zoom in [ZOOM_MIN, 2.7, 9.0, 23.5, ZOOM_MAX] {
+            let controls = Controls {
+                zoom,
+                ..Controls::default()
+            };
+            let spacing = controls.grid_spacing(600.0);
+            let pixels = spacing / controls.scale(600.0);
+            assert!(
+                (GRID_MIN_SPACING as f64..GRID_MIN_SPACING as f64 * 2.5).contains(&pixels),
+                "{pixels} pixels apart at zoom {zoom}"
+            );
+            let mantissa = spacing / 10.0_f64.powf(spacing.log10().floor());
+            assert!(
+                [1.0, 2.0, 5.0].iter().any(|m| (mantissa - m).abs() < 1e-9),
+                "{spacing}"
+            );
+        }
+    }
+
+    #[test]
+    fn grid_labels_sit_on_the_lines() {
+        let mut program = FragmentShaderProgram::new(Controls {
+            center: DVec2::new(-0.5, 0.1),
+            zoom: 2.0,
+            ..Controls::default()
+        });
+        program.grid = true;
+        let bounds = Rectangle::new(iced::Point::new(10.0, 20.0), Size::new(400.0, 300.0));
+
+        let labels = program.grid_labels(bounds);
+        assert_eq!(
+            labels,
+            [
+                GridLabel {
+                    position: Vec2::new(200.0, 300.0 - GRID_LABEL_MARGIN),
+                    text: String::from("-0.5"),
+                    real: true,
+                },
+                GridLabel {
+                    position: Vec2::new(GRID_LABEL_MARGIN, 110.0),
+                    text: String::from("0.0i"),
+                    real: false,
+                },
+            ]
+        );
+        for label in labels {
+            let pos = label.position;
+            let point = program.controls.fractal_point(pos, bounds);
+            let value = if label.real { point.x } else { point.y };
+            assert!(
+                (value - label.text.trim_end_matches('i').parse::<f64>().unwrap()).abs() < 1e-6
+            );
+        }
+    }
+
+    #[test]
+    fn shaders_with_the_shared_srgb_encoding_validate() {
+        for source in [BLIT_SHADER, TRACE_SHADER] {
+            let module = naga::front::wgsl::parse_str(source).unwrap();
+            naga::valid::Validator::new(Default::default(), Default::default())
+                .validate(&module)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn uniforms_match_wgsl_layout() {
+        // Sizes of the structs in shader.wgsl and color.wgsl, rounded up to their 8 byte
+        // alignment. Pod guarantees there's no implicit padding on the Rust side.
+        assert_eq!(std::mem::size_of::<Uniforms>(), 128);
+        assert_eq!(std::mem::size_of::<ColorUniforms>(), 128);
+        assert_eq!(std::mem::align_of::<ColorUniforms>(), 4);
+        assert_eq!(std::mem::offset_of!(ColorUniforms, gamma), 48);
+        assert_eq!(std::mem::offset_of!(ColorUniforms, marker_center), 56);
+        assert_eq!(std::mem::offset_of!(ColorUniforms, grid_offset), 88);
+        assert_eq!(SAMPLE_SIZE, 32);
+    }
+
+    /// Offsets of the fields of `$struct`, which have to be listed completely.
+    macro_rules! field_offsets {
+        ($struct:ident { $($field:ident),* $(,)? }) => {{
+            let $struct { $($field: _),* } = bytemuck::Zeroable::zeroed();
+            [$((stringify!($field), std::mem::offset_of!($struct, $field))),*]
+        }};
+    }
+
+    /// Asserts that the fields of a Rust struct of `size` bytes are at the same offsets as in
+    /// the WGSL struct `name` of `source`, skipping Rust padding fields.
+    fn assert_wgsl_layout(source: &str, name: &str, size: usize, offsets: &[(&str, usize)]) {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let (members, span) = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span } if ty.name.as_deref() == Some(name) => {
+                    Some((members, *span))
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no struct {name}"));
+
+        let wgsl: Vec<_> = members
+            .iter()
+            .map(|member| (member.name.as_deref().unwrap(), member.offset as usize))
+            .collect();
+        let rust: Vec<_> = offsets
+            .iter()
+            .copied()
+            .filter(|(field, _)| !field.starts_with('_'))
+            .collect();
+        assert_eq!(rust, wgsl, "{name}");
+        assert_eq!(size, span as usize, "{name} size");
+    }
+
+    #[test]
+    fn uniforms_match_the_wgsl_declarations() {
+        let shader = include_str!("shader.wgsl");
+        let color = include_str!("color.wgsl");
+
+        let offsets = field_offsets!(Uniforms {
+            resolution,
+            center_hi,
+            center_lo,
+            seed,
+            jitter,
+            grid_size,
+            trap_point,
+            extent,
+            max_iter,
+            julia,
+            formula,
+            deep,
+            power,
+            escape_radius,
+            perturbation,
+            orbit_len,
+            trap,
+            trap_radius,
+            period_detection,
+            tile_offset,
+            rotation,
+            stripe_frequency,
+            distance_estimate,
+            _padding,
+        });
+        assert_wgsl_layout(shader, "Uniforms", size_of::<Uniforms>(), &offsets);
+
+        let offsets = field_offsets!(ColorUniforms {
+            size,
+            aa_samples,
+            max_iter,
+            smooth_coloring,
+            coloring,
+            stripe,
+            escape_radius,
+            power,
+            line_width,
+            interior,
+            histogram,
+            gamma,
+            marker,
+            marker_center,
+            marker_half_size,
+            marker_axis,
+            grid,
+            grid_spacing,
+            grid_offset,
+            axes_offset,
+            grid_axis,
+            color_offset,
+            color_frequency,
+            elapsed,
+            _padding,
+        });
+        assert_wgsl_layout(color, "Uniforms", size_of::<ColorUniforms>(), &offsets);
+
+        let offsets = field_offsets!(Sample {
+            z,
+            iter,
+            distance,
+            trap,
+            period,
+            stripe,
+            _padding,
+        });
+        assert_wgsl_layout(shader, "Sample", size_of::<Sample>(), &offsets);
+        assert_wgsl_layout(color, "Sample", size_of::<Sample>(), &offsets);
+
+        let offsets = field_offsets!(BuddhabrotUniforms {
+            size,
+            center,
+            rotation,
+            scale,
+            max_iter,
+            escape_radius,
+            seed,
+            _padding,
+        });
+        assert_wgsl_layout(
+            include_str!("buddhabrot.wgsl"),
+            "Uniforms",
+            size_of::<BuddhabrotUniforms>(),
+            &offsets,
+        );
+
+        let offsets = field_offsets!(BuddhabrotDisplayUniforms {
+            size,
+            exposure,
+            _padding,
+        });
+        assert_wgsl_layout(
+            include_str!("buddhabrot_display.wgsl"),
+            "Uniforms",
+            size_of::<BuddhabrotDisplayUniforms>(),
+            &offsets,
+        );
+    }
+
+    #[test]
+    fn idle_views_have_no_frame_rate() {
+        let mut stats = FrameStats::default();
+        let start = Instant::now() - FPS_WINDOW * 3;
+        for frame in 0..10 {
+            stats.record(start + Duration::from_millis(16) * frame, true);
+        }
+        assert_eq!(stats.recent.len(), 10);
+        assert_eq!(stats.fps(), 0.0);
+        assert_eq!(stats.rendered, 10);
+
+        stats.record(Instant::now(), false);
+        assert_eq!(stats.fps(), 1.0);
+    }
+
+    #[test]
+    fn identical_uniforms_are_uploaded_once() {
+        let mut uploaded = Uploaded::new();
+        let uniforms: ColorUniforms = bytemuck::Zeroable::zeroed();
+        assert!(uploaded.update(&uniforms));
+        assert!(!uploaded.update(&uniforms));
+        assert!(!uploaded.update(&uniforms));
+
+        let changed = ColorUniforms {
+            max_iter: 100,
+            ..uniforms
+        };
+        assert!(uploaded.update(&changed));
+        assert!(!uploaded.update(&changed));
+
+        uploaded.invalidate();
+        assert!(uploaded.update(&changed));
+    }
+
+    #[test]
+    fn view_string_round_trips() {
+        let controls = Controls {
+            center: DVec2::new(-0.743643887037151, 0.131825904205330),
+            zoom: ZOOM_MAX,
+            max_iter: 800,
+            formula: Formula::Multibrot { power: 4.5 },
+            coloring: ColoringMode::DistanceLines,
+            interior: InteriorMode::Period,
+            histogram: true,
+            ..Controls::default()
+        };
+
+        let mut pasted = Controls::default();
+        pasted.apply_view_string(&controls.view_string()).unwrap();
+        assert_eq!(pasted.view_string(), controls.view_string());
+        assert_eq!(pasted.center, controls.center);
+        assert_eq!(pasted.zoom, controls.zoom);
+        assert_eq!(pasted.formula, controls.formula);
+    }
+
+    #[test]
+    fn view_string_is_validated() {
+        let mut controls = Controls::default();
+        controls
+            .apply_view_string("mandel:v1;cx=0.25;palette=fire;it=100")
+            .unwrap();
+        assert_eq!(controls.center.x, 0.25);
+        assert_eq!(controls.max_iter, 100);
+
+        for invalid in [
+            "cx=0.25",
+            "mandel:v2;cx=0.25",
+            "mandel:v1;cx=nan",
+            "mandel:v1;z=1000",
+            "mandel:v1;col=99",
+            "mandel:v1;cy",
+        ] {
+            let mut unchanged = controls;
+            assert!(unchanged.apply_view_string(invalid).is_err(), "{invalid}");
+            assert_eq!(unchanged.view_string(), controls.view_string());
+        }
+    }
+
+    #[test]
+    fn state_missing_fields_default() {
+        let controls: Controls =
+            serde_json::from_str(r#"{ "zoom": 5.0, "center": [0.25, -0.5] }"#).unwrap();
+
+        assert_eq!(controls.zoom, 5.0);
+        assert_eq!(controls.center, DVec2::new(0.25, -0.5));
+        assert_eq!(controls.max_iter, Controls::default().max_iter);
+        assert_eq!(controls.formula, Formula::Mandelbrot);
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let controls = Controls {
+            center: DVec2::new(-0.75, 0.1),
+            formula: Formula::Multibrot { power: 4.5 },
+            coloring: ColoringMode::Distance,
+            ..Controls::default()
+        };
+
+        let json = serde_json::to_string(&controls).unwrap();
+        let loaded: Controls = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.center, controls.center);
+        assert_eq!(loaded.formula, controls.formula);
+        assert_eq!(loaded.coloring, controls.coloring);
+    }
+
+    #[test]
+    fn zero_pans_leave_the_controls_as_they_were() {
+        let controls = Controls {
+            center: DVec2::new(-0.743643887, 0.131825904),
+            zoom: 20.0,
+            rotation: 1.0,
+            ..Controls::default()
+        };
+        let bounds = Rectangle::new(iced::Point::new(10.0, 20.0), Size::new(400.0, 300.0));
+
+        let mut panned = controls;
+        panned.pan(Vec2::ZERO, bounds);
+        assert_eq!(panned, controls);
+        assert_eq!(
+            panned.center.to_array().map(f64::to_bits),
+            controls.center.to_array().map(f64::to_bits)
+        );
+    }
+
+    #[test]
+    fn panning_ends_when_the_release_is_missed() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls::default());
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let inside = Cursor::Available(iced::Point::new(100.0, 100.0));
+        let mut state = ProgramState::default();
+        let mut messages = Vec::new();
+        let mut send = |state: &mut ProgramState, event, cursor| {
+            program.update(state, event, bounds, cursor, &mut Shell::new(&mut messages))
+        };
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+
+        assert_eq!(
+            program.mouse_interaction(&state, bounds, inside),
+            mouse::Interaction::Grab
+        );
+        assert_eq!(
+            program.mouse_interaction(&state, bounds, Cursor::Unavailable),
+            mouse::Interaction::Idle
+        );
+
+        send(&mut state, press.clone(), inside);
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+        assert_eq!(
+            program.mouse_interaction(&state, bounds, Cursor::Unavailable),
+            mouse::Interaction::Grabbing
+        );
+
+        // Released outside the window: the cursor left without a release event.
+        send(
+            &mut state,
+            Event::Mouse(mouse::Event::CursorLeft),
+            Cursor::Unavailable,
+        );
+        assert!(matches!(state.interaction, MouseInteraction::Idle));
+
+        // Released without the window noticing, then pressed again: a new pan starts.
+        state.last_press = None;
+        send(&mut state, press.clone(), inside);
+        state.last_press = None;
+        send(&mut state, press, inside);
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+
+        let release = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        send(&mut state, release, Cursor::Unavailable);
+        assert!(matches!(state.interaction, MouseInteraction::Idle));
+    }
+
+    #[test]
+    fn the_split_line_drags_instead_of_panning() {
+        use shader::Program as _;
+
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        program.compare = Some(Controls {
+            coloring: ColoringMode::Distance,
+            ..program.controls
+        });
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let release = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        let at = |x| Cursor::Available(iced::Point::new(x, 50.0));
+        let split_x = BOUNDS_SIZE.x * SPLIT_DEFAULT;
+
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        let press_at = |program: &FragmentShaderProgram, x, shell: &mut Shell<'_, Message>| {
+            let mut state = ProgramState::default();
+            program.update(&mut state, press.clone(), bounds, at(x), shell);
+            state
+        };
+
+        let mut state = press_at(&program, split_x + 3.0, &mut shell);
+        assert!(matches!(state.interaction, MouseInteraction::MovingSplit));
+        let moved = Event::Mouse(mouse::Event::CursorMoved {
+            position: iced::Point::new(BOUNDS_SIZE.x / 4.0, 50.0),
+        });
+        let (_, message) = program.update(
+            &mut state,
+            moved,
+            bounds,
+            at(BOUNDS_SIZE.x / 4.0),
+            &mut shell,
+        );
+        assert!(matches!(message, Some(Message::MoveSplit(split)) if split == 0.25));
+        program.update(&mut state, release, bounds, at(0.0), &mut shell);
+        assert!(matches!(state.interaction, MouseInteraction::Idle));
+
+        let state = press_at(&program, split_x + 10.0, &mut shell);
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+
+        program.compare = None;
+        let state = press_at(&program, split_x, &mut shell);
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+    }
+
+    #[test]
+    fn clicks_without_dragging_report_the_clicked_point() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls::default());
+        let bounds = Rectangle::new(
+            iced::Point::new(200.0, 50.0),
+            Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y),
+        );
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let release = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        let at = |x, y| Cursor::Available(iced::Point::new(x, y));
+        let moved = |x, y| {
+            Event::Mouse(mouse::Event::CursorMoved {
+                position: iced::Point::new(x, y),
+            })
+        };
+
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        let mut state = ProgramState::default();
+        program.update(
+            &mut state,
+            press.clone(),
+            bounds,
+            at(300.0, 150.0),
+            &mut shell,
+        );
+        let (_, message) = program.update(
+            &mut state,
+            release.clone(),
+            bounds,
+            at(300.0, 150.0),
+            &mut shell,
+        );
+        let expected = program
+            .controls
+            .fractal_point(Vec2::new(100.0, 100.0), bounds);
+        assert!(matches!(message, Some(Message::Clicked(point)) if point == expected));
+
+        // A drag isn't a click.
+        let mut state = ProgramState::default();
+        let mut message = None;
+        for (event, cursor) in [
+            (press, at(300.0, 150.0)),
+            (moved(350.0, 150.0), at(350.0, 150.0)),
+            (release, at(350.0, 150.0)),
+        ] {
+            (_, message) = program.update(&mut state, event, bounds, cursor, &mut shell);
+        }
+        assert!(!matches!(message, Some(Message::Clicked(_))));
+    }
+
+    #[test]
+    fn double_click_centers_without_panning() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls::default());
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let release = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        let at = |x, y| Cursor::Available(iced::Point::new(x, y));
+        let moved = |x, y| {
+            Event::Mouse(mouse::Event::CursorMoved {
+                position: iced::Point::new(x, y),
+            })
+        };
+
+        let click_twice = |modifiers, second: Cursor| {
+            let mut state = ProgramState {
+                modifiers,
+                ..Default::default()
+            };
+            let mut messages = Vec::new();
+            let mut shell = Shell::new(&mut messages);
+            let first = at(100.0, 100.0);
+            program.update(&mut state, press.clone(), bounds, first, &mut shell);
+            program.update(&mut state, release.clone(), bounds, first, &mut shell);
+            let (_, message) =
+                program.update(&mut state, press.clone(), bounds, second, &mut shell);
+            (state, message)
+        };
+
+        let expected = program
+            .controls
+            .fractal_point(Vec2::new(102.0, 100.0), bounds);
+        let (state, message) = click_twice(keyboard::Modifiers::default(), at(102.0, 100.0));
+        assert!(matches!(state.interaction, MouseInteraction::Idle));
+        assert!(matches!(message, Some(Message::CenterOn(point, false)) if point == expected));
+
+        let (_, message) = click_twice(keyboard::Modifiers::CTRL, at(102.0, 100.0));
+        assert!(matches!(message, Some(Message::CenterOn(_, true))));
+
+        // Too far from the first press.
+        let (state, message) = click_twice(keyboard::Modifiers::default(), at(110.0, 100.0));
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+        assert!(matches!(message, Some(Message::StopMomentum)));
+
+        // Dragged away and back between the presses.
+        let mut state = ProgramState::default();
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        for (event, cursor) in [
+            (press.clone(), at(100.0, 100.0)),
+            (moved(150.0, 100.0), at(150.0, 100.0)),
+            (moved(100.0, 100.0), at(100.0, 100.0)),
+            (release, at(100.0, 100.0)),
+            (press, at(100.0, 100.0)),
+        ] {
+            program.update(&mut state, event, bounds, cursor, &mut shell);
+        }
+        assert!(matches!(
+            state.interaction,
+            MouseInteraction::Panning { .. }
+        ));
+    }
+
+    #[test]
+    fn rotated_view_keeps_points_under_the_cursor() {
+        use shader::Program as _;
+
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        program.controls.rotation = 0.7;
+        let controls = program.controls;
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let pos = Vec2::new(300.0, 100.0);
+        let point = controls.fractal_point(pos, bounds);
+        let close = |other: DVec2| (other - point).length() < controls.scale(bounds.height) * 1e-3;
+
+        let delta = Vec2::new(30.0, -20.0);
+        let mut panned = controls;
+        panned.pan(delta, bounds);
+        assert!(close(panned.fractal_point(pos + delta, bounds)));
+
+        let zoomed = apply_zoom(controls, pos, test_bounds(), 3.0, &Config::default());
+        assert!(close(zoomed.fractal_point(pos, bounds)));
+
+        // Alt+drag a quarter turn around the view center.
+        let center = BOUNDS_SIZE * 0.5;
+        let turned = center + (pos - center).perp();
+        let mut state = ProgramState {
+            modifiers: keyboard::Modifiers::ALT,
+            ..Default::default()
+        };
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let cursor = Cursor::Available(iced::Point::new(pos.x, pos.y));
+        program.update(&mut state, press, bounds, cursor, &mut shell);
+        let moved = Event::Mouse(mouse::Event::CursorMoved {
+            position: iced::Point::new(turned.x, turned.y),
+        });
+        let (_, message) = program.update(&mut state, moved, bounds, cursor, &mut shell);
+        let Some(Message::RotationDelta(angle)) = message else {
+            panic!("expected a rotation, got {message:?}");
+        };
+        let mut rotated = controls;
+        rotated.rotate(-angle);
+        assert!(close(rotated.fractal_point(turned, bounds)));
+    }
+
+    #[test]
+    fn wheel_modifiers() {
+        use shader::Program as _;
+
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        program.controls.max_iter = 1000;
+        program.controls.auto_iter = false;
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let cursor = Cursor::Available(iced::Point::new(100.0, 100.0));
+        let scroll = |modifiers, delta| {
+            let mut state = ProgramState {
+                modifiers,
+                ..Default::default()
+            };
+            let mut messages = Vec::new();
+            let mut shell = Shell::new(&mut messages);
+            let event = Event::Mouse(mouse::Event::WheelScrolled { delta });
+            program
+                .update(&mut state, event, bounds, cursor, &mut shell)
+                .1
+        };
+        let lines = |y| mouse::ScrollDelta::Lines { x: 0.0, y };
+
+        assert!(matches!(
+            scroll(keyboard::Modifiers::default(), lines(1.0)),
+            Some(Message::ZoomDelta(_, _, delta)) if delta == 1.0
+        ));
+        assert!(matches!(
+            scroll(keyboard::Modifiers::CTRL, lines(1.0)),
+            Some(Message::UpdateMaxIterations(1100))
+        ));
+        assert!(matches!(
+            scroll(keyboard::Modifiers::CTRL, lines(-100.0)),
+            Some(Message::UpdateMaxIterations(ITERS_MIN))
+        ));
+        let expected = Vec2::new(BOUNDS_SIZE.x * WHEEL_PAN_FRACTION, 0.0);
+        assert!(matches!(
+            scroll(keyboard::Modifiers::SHIFT, lines(1.0)),
+            Some(Message::PanningDelta(delta, _)) if delta == expected
+        ));
+        // A trackpad scrolling by pixels moves as far as the same number of notches.
+        let pixels = mouse::ScrollDelta::Pixels {
+            x: 0.0,
+            y: WHEEL_PIXELS_PER_LINE,
+        };
+        assert!(matches!(
+            scroll(keyboard::Modifiers::SHIFT, pixels),
+            Some(Message::PanningDelta(delta, _)) if delta == expected
+        ));
+    }
+
+    #[test]
+    fn wheel_zoom_is_anchored_away_from_the_window_origin() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls {
+            center: DVec2::new(-0.5, 0.25),
+            ..Default::default()
+        });
+        // Beside a side panel and below a toolbar.
+        let bounds = Rectangle::new(
+            iced::Point::new(240.0, 56.0),
+            Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y),
+        );
+        let cursor = iced::Point::new(420.0, 170.0);
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        let event = Event::Mouse(mouse::Event::WheelScrolled {
+            delta: mouse::ScrollDelta::Lines { x: 0.0, y: 2.0 },
+        });
+        let message = program
+            .update(
+                &mut ProgramState::default(),
+                event,
+                bounds,
+                Cursor::Available(cursor),
+                &mut shell,
+            )
+            .1;
+        let Some(Message::ZoomDelta(pos, view, delta)) = message else {
+            panic!("expected a zoom, got {message:?}");
+        };
+
+        let anchor = Vec2::new(cursor.x - bounds.x, cursor.y - bounds.y);
+        assert_eq!(pos, anchor);
+        let before = program.controls.fractal_point(anchor, bounds);
+        let zoomed = apply_zoom(program.controls, pos, view, delta, &program.config);
+        assert!(zoomed.zoom > program.controls.zoom);
+        let after = zoomed.fractal_point(anchor, bounds);
+        assert!((after - before).length() < zoomed.scale(bounds.height) * 1e-3);
+    }
+
+    #[test]
+    fn key_shortcuts_only_apply_while_hovered() {
+        use shader::Program as _;
+
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        program.controls.max_iter = 1000;
+        program.controls.auto_iter = false;
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(BOUNDS_SIZE.x, BOUNDS_SIZE.y));
+        let press_with = |program: &FragmentShaderProgram, key: &str, modifiers, cursor| {
+            let mut state = ProgramState::default();
+            let mut messages = Vec::new();
+            let mut shell = Shell::new(&mut messages);
+            let event = Event::Keyboard(keyboard::Event::KeyPressed {
+                key: keyboard::Key::Character(key.into()),
+                location: keyboard::Location::Standard,
+                modifiers,
+                text: None,
+            });
+            program
+                .update(&mut state, event, bounds, cursor, &mut shell)
+                .1
+        };
+        let press = |program: &FragmentShaderProgram, key: &str, cursor| {
+            press_with(program, key, keyboard::Modifiers::default(), cursor)
+        };
+        let hovered = Cursor::Available(iced::Point::new(10.0, 10.0));
+
+        assert!(matches!(
+            press(&program, "+", hovered),
+            Some(Message::UpdateMaxIterations(1010))
+        ));
+        assert!(matches!(
+            press(&program, "-", hovered),
+            Some(Message::UpdateMaxIterations(990))
+        ));
+        let center = BOUNDS_SIZE / 2.0;
+        assert!(matches!(
+            press(&program, "]", hovered),
+            Some(Message::ZoomDelta(pos, _, delta))
+                if pos == center && wheel_zoom(0.0, delta) == KEY_ZOOM_STEP
+        ));
+        assert!(matches!(
+            press(&program, "[", hovered),
+            Some(Message::ZoomDelta(_, _, delta)) if wheel_zoom(0.0, delta) == -KEY_ZOOM_STEP
+        ));
+        assert!(press(&program, "a", hovered).is_none());
+        assert!(press(&program, "+", Cursor::Unavailable).is_none());
+        // Shortcuts with other modifiers are left to the rest of the app.
+        for modifiers in [
+            keyboard::Modifiers::CTRL,
+            keyboard::Modifiers::ALT,
+            keyboard::Modifiers::LOGO,
+        ] {
+            assert!(press_with(&program, "-", modifiers, hovered).is_none());
+        }
+        assert!(matches!(
+            press_with(&program, "+", keyboard::Modifiers::SHIFT, hovered),
+            Some(Message::UpdateMaxIterations(1010))
+        ));
+
+        program.controls.max_iter = ITERS_MIN;
+        assert!(matches!(
+            press(&program, "-", hovered),
+            Some(Message::UpdateMaxIterations(ITERS_MIN))
+        ));
+    }
+
+    #[test]
+    fn probing_inspects_under_the_cursor() {
+        use shader::Program as _;
+
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        program.probe = true;
+        let bounds = Rectangle::new(iced::Point::new(10.0, 20.0), Size::new(400.0, 300.0));
+        let mut state = ProgramState::default();
+        let mut messages = Vec::new();
+        let mut shell = Shell::new(&mut messages);
+        let event = Event::Mouse(mouse::Event::CursorMoved {
+            position: iced::Point::new(110.0, 70.0),
+        });
+        let cursor = Cursor::Available(iced::Point::new(110.0, 70.0));
+        let (_, message) = program.update(&mut state, event, bounds, cursor, &mut shell);
+        assert!(matches!(message, Some(Message::Probe(pos, _)) if pos == Vec2::new(100.0, 50.0)));
+
+        // The last value read back stays until the next one arrives.
+        program.inspect(Vec2::new(100.0, 50.0), bounds);
+        let info = PixelInfo {
+            point: DVec2::ZERO,
+            iterations: Some(12.5),
+            magnitude: 3.0,
+        };
+        *program.inspection.as_ref().unwrap().result.lock().unwrap() = Some(Ok(info));
+        program.inspect(Vec2::new(101.0, 50.0), bounds);
+        assert!(program.inspecting());
+        assert_eq!(program.inspected(), Some(info));
+    }
+
+    #[test]
+    fn pinching_zooms_around_the_fingers() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls::default());
+        let bounds = Rectangle::new(iced::Point::new(10.0, 20.0), Size::new(400.0, 300.0));
+        let finger = |id, event: fn(touch::Finger, iced::Point) -> touch::Event, x, y| {
+            Event::Touch(event(touch::Finger(id), iced::Point::new(x, y)))
+        };
+        let pressed = |id, position| touch::Event::FingerPressed { id, position };
+        let moved = |id, position| touch::Event::FingerMoved { id, position };
+        let lifted = |id, position| touch::Event::FingerLifted { id, position };
+
+        let mut state = ProgramState::default();
+        let mut messages = Vec::new();
+        let mut send = |state: &mut ProgramState, event| {
+            let mut shell = Shell::new(&mut messages);
+            let (_, message) =
+                program.update(state, event, bounds, Cursor::Unavailable, &mut shell);
+            messages.extend(message);
+            std::mem::take(&mut messages)
+        };
+
+        send(&mut state, finger(0, pressed, 100.0, 100.0));
+        send(&mut state, finger(1, pressed, 200.0, 100.0));
+        // A third finger doesn't take part.
+        send(&mut state, finger(2, pressed, 300.0, 300.0));
+        assert!(send(&mut state, finger(2, moved, 350.0, 300.0)).is_empty());
+
+        // Spreading the fingers to twice the distance zooms in one level around the midpoint,
+        // which moved by 50 pixels.
+        let messages = send(&mut state, finger(1, moved, 300.0, 100.0));
+        assert!(matches!(
+            messages[..],
+            [Message::PanningDelta(pan, _), Message::PinchZoom(pos, _, delta)]
+                if pan == Vec2::new(50.0, 0.0)
+                    && pos == Vec2::new(190.0, 80.0)
+                    && (delta * ZOOM_WHEEL_SCALE - 1.0).abs() < 1e-6
+        ));
+
+        // Lifting either finger leaves the other one panning.
+        send(&mut state, finger(2, lifted, 350.0, 300.0));
+        send(&mut state, finger(0, lifted, 100.0, 100.0));
+        let messages = send(&mut state, finger(1, moved, 310.0, 105.0));
+        assert!(matches!(
+            messages[..],
+            [Message::PanningDelta(pan, _)] if pan == Vec2::new(10.0, 5.0)
+        ));
+
+        send(&mut state, finger(1, lifted, 310.0, 105.0));
+        assert!(matches!(state.interaction, MouseInteraction::Idle));
+    }
+
+    #[test]
+    fn fingers_arriving_or_lifting_dont_move_the_view() {
+        use shader::Program as _;
+
+        let program = FragmentShaderProgram::new(Controls::default());
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(400.0, 300.0));
+        let finger = |id, event: fn(touch::Finger, iced::Point) -> touch::Event, x, y| {
+            Event::Touch(event(touch::Finger(id), iced::Point::new(x, y)))
+        };
+        let pressed = |id, position| touch::Event::FingerPressed { id, position };
+        let moved = |id, position| touch::Event::FingerMoved { id, position };
+        let lifted = |id, position| touch::Event::FingerLifted { id, position };
+
+        let mut state = ProgramState::default();
+        let mut messages = Vec::new();
+        let mut send = |state: &mut ProgramState, event| {
+            let mut shell = Shell::new(&mut messages);
+            let (_, message) =
+                program.update(state, event, bounds, Cursor::Unavailable, &mut shell);
+            messages.extend(message);
+            std::mem::take(&mut messages)
+        };
+        let panned = |messages: &[Message]| -> Vec2 {
+            messages
+                .iter()
+                .map(|message| match message {
+                    Message::PanningDelta(delta, _) => *delta,
+                    _ => Vec2::ZERO,
+                })
+                .sum()
+        };
+
+        send(&mut state, finger(0, pressed, 100.0, 100.0));
+        send(&mut state, finger(0, moved, 120.0, 100.0));
+        // A second finger arriving mid-pan pans nothing by itself, and the pinch starts from
+        // where the first finger is now.
+        assert!(send(&mut state, finger(1, pressed, 220.0, 100.0)).is_empty());
+        let messages = send(&mut state, finger(0, moved, 110.0, 100.0));
+        assert_eq!(panned(&messages), Vec2::new(-5.0, 0.0));
+
+        // Once the second finger lifts, the first one pans on from its last position.
+        assert!(send(&mut state, finger(1, lifted, 220.0, 100.0)).is_empty());
+        let messages = send(&mut state, finger(0, moved, 115.0, 102.0));
+        assert!(matches!(messages[..], [Message::PanningDelta(..)]));
+        assert_eq!(panned(&messages), Vec2::new(5.0, 2.0));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn tiled_iteration_matches_a_single_pass() {
+        let mut renderer = HeadlessRenderer::new(Size::new(100, 70)).unwrap();
+        let controls = Controls {
+            center: DVec2::new(-0.743643887, 0.131825904),
+            zoom: 8.0,
+            max_iter: 300,
+            aa_samples: 2,
+            ..Controls::default()
+        };
+
+        renderer.draw(controls).unwrap();
+        let single_pass = renderer.read_pixels().unwrap();
+        for tiles in [2, 8] {
+            // Overwrites the samples, so that any left out by the tiles would differ.
+            renderer.draw(Controls::default()).unwrap();
+            renderer.draw(Controls { tiles, ..controls }).unwrap();
+            assert!(
+                renderer.read_pixels().unwrap() == single_pass,
+                "{tiles} tiles"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn lost_devices_have_the_pipelines_rebuilt() {
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        renderer.draw(Controls::default()).unwrap();
+        let expected = renderer.read_pixels().unwrap();
+        let blit = |renderer: &HeadlessRenderer| {
+            let Some(Ok(pipeline)) = renderer
+                .storage
+                .get::<Result<FragmentShaderPipeline, String>>()
+            else {
+                panic!("no pipeline");
+            };
+            pipeline.blit_pipeline.global_id()
+        };
+        let before = blit(&renderer);
+        renderer.errors.lose("simulated".to_owned());
+        renderer.draw(Controls::default()).unwrap();
+        assert_ne!(blit(&renderer), before);
+        assert!(renderer.read_pixels().unwrap() == expected);
+
+        // Destroying the device simulates a GPU reset.
+        renderer.device.destroy();
+        renderer.device.poll(wgpu::Maintain::Wait);
+        assert!(renderer.errors.take_loss());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn tiles_over_the_frame_budget_continue_until_the_view_changes() {
+        const SIZE: Size<u32> = Size::new(64, 48);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let controls = Controls {
+            zoom: 1.0,
+            tiles: 2,
+            ..Controls::default()
+        };
+        renderer
+            .draw(Controls {
+                tiles: 1,
+                ..controls
+            })
+            .unwrap();
+        let single_pass = renderer.read_pixels().unwrap();
+        let Some(Ok(pipeline)) = renderer
+            .storage
+            .get_mut::<Result<FragmentShaderPipeline, String>>()
+        else {
+            panic!("no pipeline");
+        };
+        // A single tile per frame.
+        pipeline.tile_budget = Duration::ZERO;
+
+        let mut frame = |controls| {
+            let primitive = FragmentShaderPrimitive::new(0, controls);
+            shader::Primitive::prepare(
+                &primitive,
+                RENDER_FORMAT,
+                &renderer.device,
+                &renderer.queue,
+                Rectangle::with_size(Size::new(64.0, 48.0)),
+                SIZE,
+                1.0,
+                &mut renderer.storage,
+            );
+            let progress = *primitive.tile_progress.lock().unwrap();
+            (progress.done, progress.total)
+        };
+
+        let moved = Controls {
+            zoom: 2.0,
+            ..controls
+        };
+        assert_eq!(frame(moved), (1, 4));
+        assert_eq!(frame(moved), (2, 4));
+        let moved = Controls {
+            zoom: 3.0,
+            ..controls
+        };
+        assert_eq!(frame(moved), (1, 4));
+        assert_eq!(frame(moved), (2, 4));
+        assert_eq!(frame(moved), (3, 4));
+        assert_eq!(frame(moved), (4, 4));
+        // Nothing left to iterate.
+        assert_eq!(frame(moved), (0, 0));
+        assert_eq!(frame(Controls { tiles: 1, ..moved }), (0, 0));
+
+        // Drawing still finishes the image, over as many frames as it takes.
+        renderer.draw(controls).unwrap();
+        assert!(renderer.read_pixels().unwrap() == single_pass);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn tile_batches_fit_the_time_earlier_tiles_took() {
+        const SIZE: Size<u32> = Size::new(64, 48);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let controls = Controls {
+            tiles: 4,
+            ..Controls::default()
+        };
+
+        let mut frame = |tile_time| {
+            // Callbacks of the last frame's tiles would overwrite `tile_time` otherwise.
+            renderer.device.poll(wgpu::Maintain::Wait);
+            if let Some(Ok(pipeline)) = renderer
+                .storage
+                .get_mut::<Result<FragmentShaderPipeline, String>>()
+            {
+                pipeline.tile_budget = Duration::from_millis(30);
+                *pipeline.instances[&0].tile_time.lock().unwrap() = tile_time;
+            }
+            let primitive = FragmentShaderPrimitive::new(0, controls);
+            shader::Primitive::prepare(
+                &primitive,
+                RENDER_FORMAT,
+                &renderer.device,
+                &renderer.queue,
+                Rectangle::with_size(Size::new(64.0, 48.0)),
+                SIZE,
+                1.0,
+                &mut renderer.storage,
+            );
+            let progress = *primitive.tile_progress.lock().unwrap();
+            (progress.done, progress.total)
+        };
+
+        // Nothing measured yet.
+        let (done, total) = frame(None);
+        assert_eq!(done, 1);
+        assert!(total > 4);
+        assert_eq!(frame(Some(Duration::from_millis(10))), (4, total));
+        assert_eq!(frame(Some(Duration::from_millis(1))), (total, total));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn srgb_and_plain_targets_show_the_same_colors() {
+        const SIZE: Size<u32> = Size::new(64, 48);
+        let render = |format| {
+            let mut renderer = HeadlessRenderer::with_format(SIZE, format).unwrap();
+            renderer.draw(Controls::default()).unwrap();
+            renderer.read_pixels().unwrap()
+        };
+        let srgb = render(wgpu::TextureFormat::Rgba8UnormSrgb);
+        let plain = render(wgpu::TextureFormat::Rgba8Unorm);
+
+        // Both hold sRGB-encoded bytes, up to rounding.
+        let differing = srgb.iter().zip(&plain).filter(|(a, b)| a.abs_diff(**b) > 1);
+        assert_eq!(differing.count(), 0);
+        assert!(srgb.iter().any(|&byte| byte > 0 && byte < 255));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn format_changes_keep_the_rendered_view() {
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        renderer.draw(Controls::default()).unwrap();
+        let ids = |renderer: &HeadlessRenderer| {
+            let Some(Ok(pipeline)) = renderer
+                .storage
+                .get::<Result<FragmentShaderPipeline, String>>()
+            else {
+                panic!("no pipeline");
+            };
+            (
+                pipeline.format,
+                pipeline.blit_pipeline.global_id(),
+                pipeline.instances[&0].uniform_buffer.global_id(),
+            )
+        };
+        let (_, blit, uniforms) = ids(&renderer);
+
+        let primitive = FragmentShaderPrimitive::new(0, Controls::default());
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        shader::Primitive::prepare(
+            &primitive,
+            format,
+            &renderer.device,
+            &renderer.queue,
+            Rectangle::with_size(Size::new(32.0, 32.0)),
+            SIZE,
+            1.0,
+            &mut renderer.storage,
+        );
+        let (new_format, new_blit, new_uniforms) = ids(&renderer);
+        assert_eq!(new_format, format);
+        assert_ne!(new_blit, blit);
+        assert_eq!(new_uniforms, uniforms);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn palette_changes_only_recolor() {
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        renderer.draw(Controls::default()).unwrap();
+
+        let controls = Controls {
+            color_offset: 0.5,
+            color_frequency: 3.0,
+            aspect: AspectRatio::Free,
+            ..Controls::default()
+        };
+        shader::Primitive::prepare(
+            &FragmentShaderPrimitive::new(0, controls),
+            RENDER_FORMAT,
+            &renderer.device,
+            &renderer.queue,
+            Rectangle::with_size(Size::new(32.0, 32.0)),
+            SIZE,
+            1.0,
+            &mut renderer.storage,
+        );
+        let Some(Ok(pipeline)) = renderer
+            .storage
+            .get::<Result<FragmentShaderPipeline, String>>()
+        else {
+            panic!("no pipeline");
+        };
+        let instance = &pipeline.instances[&0];
+        assert!(!instance.compute_pending);
+        assert!(instance.color_pending);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn stripes_of_escaped_points_do_not_depend_on_the_iteration_count() {
+        let mut renderer = HeadlessRenderer::new(Size::new(32, 32)).unwrap();
+        // Outside the set, every point escapes within a dozen iterations.
+        let controls = Controls {
+            center: DVec2::new(-0.3, 1.2),
+            zoom: 4.0,
+            max_iter: 50,
+            smooth: true,
+            ..Controls::default()
+        };
+        let mut render = |controls| {
+            renderer.draw(controls).unwrap();
+            renderer.read_pixels().unwrap()
+        };
+
+        let striped = Controls {
+            stripe: true,
+            ..controls
+        };
+        let pixels = render(striped);
+        assert_eq!(
+            render(Controls {
+                max_iter: 500,
+                ..striped
+            }),
+            pixels
+        );
+        assert!(pixels.chunks(4).any(|pixel| pixel != &pixels[..4]));
+        // Unlike iteration counts, which are relative to the iteration range.
+        assert_ne!(
+            render(controls),
+            render(Controls {
+                max_iter: 500,
+                ..controls
+            })
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn buddhabrots_accumulate_until_the_view_changes() {
+        let mut renderer = HeadlessRenderer::new(Size::new(48, 32)).unwrap();
+        let controls = Controls {
+            mode: RenderMode::Buddhabrot,
+            max_iter: 100,
+            zoom: 0.0,
+            center: DVec2::new(-0.5, 0.0),
+            ..Controls::default()
+        };
+        let samples = |renderer: &HeadlessRenderer| {
+            renderer
+                .storage
+                .get::<Result<BuddhabrotPipeline, String>>()
+                .and_then(|pipeline| pipeline.as_ref().ok())
+                .expect("no pipeline")
+                .instances[&0]
+                .samples
+        };
+
+        renderer.draw(controls).unwrap();
+        let batch = samples(&renderer);
+        assert!(batch > 0);
+        renderer.draw(controls).unwrap();
+        assert_eq!(samples(&renderer), 2 * batch);
+
+        let pixels = renderer.read_pixels().unwrap();
+        assert!(pixels.chunks(4).any(|pixel| pixel[0] > 0));
+        assert!(pixels.chunks(4).all(|pixel| pixel[0] == pixel[1]));
+
+        renderer
+            .draw(Controls {
+                exposure: 2.0,
+                ..controls
+            })
+            .unwrap();
+        assert_eq!(samples(&renderer), 3 * batch);
+
+        renderer
+            .draw(Controls {
+                zoom: 1.0,
+                ..controls
+            })
+            .unwrap();
+        assert_eq!(samples(&renderer), batch);
+    }
+
+    #[test]
+    fn buddhabrot_counts_fit_the_buffer_limits() {
+        let limits = wgpu::Limits {
+            max_storage_buffer_binding_size: 1 << 20,
+            ..wgpu::Limits::default()
+        };
+        let size = accumulation_size(&limits, UVec2::new(1920, 1080));
+        assert_eq!(size, UVec2::new(480, 270));
+        assert!(accumulation_bytes(size) <= 1 << 20);
+        assert_eq!(
+            accumulation_size(&limits, UVec2::new(320, 200)),
+            UVec2::new(320, 200)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn broken_shaders_keep_the_working_pipeline() {
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let controls = Controls {
+            aspect: AspectRatio::Free,
+            ..Controls::default()
+        };
+        renderer.draw(controls).unwrap();
+        let rendered = renderer.read_pixels().unwrap();
+
+        let shader = Arc::new(ShaderSource::new("fn broken(".to_owned()));
+        let primitive = FragmentShaderPrimitive {
+            shader: Some(shader.clone()),
+            errors: renderer.errors.clone(),
+            ..FragmentShaderPrimitive::new(0, controls)
+        };
+        let viewport = Rectangle {
+            x: 0,
+            y: 0,
+            width: SIZE.width,
+            height: SIZE.height,
+        };
+        renderer.draw_primitive(&primitive, viewport).unwrap();
+        assert!(matches!(*shader.result.lock().unwrap(), Some(Err(_))));
+        assert_eq!(renderer.read_pixels().unwrap(), rendered);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn failed_pipelines_are_not_rebuilt_every_frame() {
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let primitive = FragmentShaderPrimitive {
+            errors: renderer.errors.clone(),
+            ..FragmentShaderPrimitive::new(0, Controls::default())
+        };
+        let mut prepare = |format| {
+            shader::Primitive::prepare(
+                &primitive,
+                format,
+                &renderer.device,
+                &renderer.queue,
+                Rectangle::with_size(Size::new(32.0, 32.0)),
+                SIZE,
+                1.0,
+                &mut renderer.storage,
+            );
+            renderer.errors.last.lock().unwrap().take()
+        };
+
+        // Not a format that can be rendered to.
+        let unrenderable = wgpu::TextureFormat::Rgb9e5Ufloat;
+        assert!(prepare(unrenderable).is_some());
+        assert!(prepare(unrenderable).is_none());
+        assert!(prepare(RENDER_FORMAT).is_none());
+        assert!(matches!(
+            renderer
+                .storage
+                .get::<Result<FragmentShaderPipeline, String>>(),
+            Some(Ok(_))
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn removed_views_have_their_instances_dropped() {
+        const SIZE: Size<u32> = Size::new(8, 8);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let viewport = Rectangle {
+            x: 0,
+            y: 0,
+            width: SIZE.width,
+            height: SIZE.height,
+        };
+        let mut draw = |id| {
+            let primitive = FragmentShaderPrimitive::new(id, Controls::default());
+            renderer.draw_primitive(&primitive, viewport).unwrap();
+            let pipeline = renderer
+                .storage
+                .get::<Result<FragmentShaderPipeline, String>>();
+            pipeline
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .instances
+                .contains_key(&1)
+        };
+
+        draw(0);
+        assert!(draw(1));
+        // Next frame, view 1 is no longer drawn.
+        assert!(draw(0));
+        // The frame after that finds it wasn't.
+        assert!(!draw(0));
+    }
+
+    #[test]
+    fn palettes_stay_sorted_with_two_stops() {
+        let stop = |position, color| ColorStop { position, color };
+        assert!(Palette::new(vec![stop(0.5, [0; 3])]).is_err());
+        assert!(serde_json::from_str::<Palette>(r#"[{"position":0,"color":[0,0,0]}]"#).is_err());
+
+        let mut palette =
+            Palette::new(vec![stop(1.0, [255, 0, 0]), stop(0.0, [0, 0, 255])]).unwrap();
+        assert_eq!(palette.stops()[0].color, [0, 0, 255]);
+
+        // Added stops split the widest gap without changing the gradient.
+        let middle = palette.color_at(0.5);
+        assert_eq!(palette.add_stop(), 1);
+        assert_eq!(palette.stops()[1], stop(0.5, middle));
+        assert_eq!(palette.add_stop(), 2);
+        assert_eq!(palette.stops()[2].position, 0.75);
+
+        // Stops don't move past their neighbors.
+        palette.set_position(1, 0.9);
+        assert_eq!(palette.stops()[1].position, 0.75);
+
+        palette.remove_stop(1);
+        palette.remove_stop(1);
+        palette.remove_stop(1);
+        assert_eq!(palette.stops().len(), 2);
+
+        // Interpolated in linear space, so the middle of black and white is lighter than 128.
+        assert_eq!(Palette::default().color_at(0.5), [188; 3]);
+        let json = serde_json::to_string(&palette).unwrap();
+        assert_eq!(serde_json::from_str::<Palette>(&json).unwrap(), palette);
+    }
+
+    #[test]
+    fn color_stops_print_as_hex() {
+        let stop = ColorStop {
+            position: 0.0,
+            color: [255, 128, 0],
+        };
+        assert_eq!(stop.hex(), "#ff8000");
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn views_are_colored_by_the_palette() {
+        let mut renderer = HeadlessRenderer::new(Size::new(32, 32)).unwrap();
+        let red = ColorStop {
+            position: 0.0,
+            color: [255, 0, 0],
+        };
+        renderer.set_palette(
+            Palette::new(vec![
+                red,
+                ColorStop {
+                    position: 1.0,
+                    ..red
+                },
+            ])
+            .unwrap(),
+        );
+        // Zoomed out far enough that every pixel escapes.
+        let controls = Controls {
+            zoom: -3.0,
+            center: DVec2::new(100.0, 0.0),
+            ..Controls::default()
+        };
+        renderer.draw(controls).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+        assert!(pixels.chunks(4).all(|pixel| pixel[..3] == [255, 0, 0]));
+    }
+
+    #[test]
+    fn magnification_round_trips_through_the_zoom() {
+        // The widest view is not magnified.
+        let widest = Controls {
+            zoom: ZOOM_MIN,
+            ..Controls::default()
+        };
+        assert_eq!(widest.magnification(), 1.0);
+        for zoom in [-2.0, 0.0, 0.5, 20.0, 90.25] {
+            let controls = Controls {
+                zoom,
+                ..Controls::default()
+            };
+            let magnification = controls.magnification();
+            assert_eq!(magnification, 2.0_f64.powf(zoom as f64 - 1.0));
+            assert_eq!(
+                controls.extent() * magnification,
+                Controls {
+                    zoom: 1.0,
+                    ..controls
+                }
+                .extent()
+            );
+            assert_eq!(Controls::zoom_for_magnification(magnification), zoom);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn rotation_turns_the_rendered_image() {
+        const SIZE: usize = 64;
+        let mut renderer = HeadlessRenderer::new(Size::new(SIZE as u32, SIZE as u32)).unwrap();
+        let controls = Controls {
+            center: DVec2::new(-0.743643887, 0.131825904),
+            zoom: 8.0,
+            max_iter: 300,
+            ..Controls::default()
+        };
+
+        renderer.draw(controls).unwrap();
+        let upright = renderer.read_pixels().unwrap();
+        renderer
+            .draw(Controls {
+                rotation: PI / 2.0,
+                ..controls
+            })
+            .unwrap();
+        let turned = renderer.read_pixels().unwrap();
+
+        // The pixel at (x, y) shows what was a quarter turn around the center away from it.
+        let pixel = |pixels: &[u8], x: usize, y: usize| pixels[(y * SIZE + x) * 4];
+        let matching = (0..SIZE * SIZE)
+            .filter(|i| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                pixel(&turned, x, y) == pixel(&upright, SIZE - 1 - y, x)
+            })
+            .count();
+        // Rounding may still flip a few pixels along the set boundary.
+        assert!(matching > SIZE * SIZE * 99 / 100, "{matching} matching");
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn orbit_traps_look_the_same_with_perturbation() {
+        const SIZE: usize = 32;
+        let mut renderer = HeadlessRenderer::new(Size::new(SIZE as u32, SIZE as u32)).unwrap();
+        let controls = Controls {
+            center: DVec2::new(-0.743643887, 0.131825904),
+            max_iter: 500,
+            auto_iter: false,
+            ..Controls::default()
+        };
+
+        for coloring in [ColoringMode::OrbitTrapPoint, ColoringMode::OrbitTrapLine] {
+            let mut render = |zoom| {
+                let controls = Controls {
+                    zoom,
+                    coloring,
+                    ..controls
+                };
+                renderer.draw(controls).unwrap();
+                renderer.read_pixels().unwrap()
+            };
+            let shallow = render(ZOOM_DEEP_THRESHOLD - 0.001);
+            let deep = render(ZOOM_DEEP_THRESHOLD + 0.001);
+
+            let matching = shallow
+                .iter()
+                .zip(&deep)
+                .filter(|(a, b)| a.abs_diff(**b) <= 8)
+                .count();
+            // Single floats and perturbation round differently near the set boundary.
+            assert!(
+                matching > shallow.len() * 95 / 100,
+                "{coloring:?}: {matching} of {} matching",
+                shallow.len()
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn comparisons_show_each_side_with_its_own_settings() {
+        use shader::Program as _;
+
+        const SIZE: Size<u32> = Size::new(64, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let left = Controls {
+            max_iter: 40,
+            ..Controls::default()
+        };
+        let right = Controls {
+            max_iter: 200,
+            coloring: ColoringMode::Distance,
+            interior: InteriorMode::Magnitude,
+            ..left
+        };
+        renderer.draw(left).unwrap();
+        let left_pixels = renderer.read_pixels().unwrap();
+        renderer.draw(right).unwrap();
+        let right_pixels = renderer.read_pixels().unwrap();
+        assert_ne!(left_pixels, right_pixels);
+
+        let mut program = FragmentShaderProgram::new(left);
+        program.compare = Some(Controls {
+            // Ignored in favor of the left side's view.
+            center: DVec2::new(0.3, 0.2),
+            ..right
+        });
+        let viewport = Rectangle {
+            x: 0,
+            y: 0,
+            width: SIZE.width,
+            height: SIZE.height,
+        };
+        let primitive = program.draw(
+            &ProgramState::default(),
+            Cursor::Unavailable,
+            viewport.into(),
+        );
+        renderer.draw_primitive(&primitive, viewport).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+
+        let split = (SIZE.width as f32 * SPLIT_DEFAULT) as usize;
+        for (i, pixel) in pixels.chunks(4).enumerate() {
+            let x = i % SIZE.width as usize;
+            // Leaving out the split line drawn between them.
+            if x + 1 < split {
+                assert_eq!(pixel, &left_pixels[i * 4..i * 4 + 4], "left at {x}");
+            } else if x > split + 1 {
+                assert_eq!(pixel, &right_pixels[i * 4..i * 4 + 4], "right at {x}");
+            }
+        }
+        // Orange, like orbit traces.
+        let on_line = pixels
+            .chunks(4)
+            .enumerate()
+            .filter(|(i, pixel)| {
+                (split - 1..=split + 1).contains(&(i % SIZE.width as usize))
+                    && pixel[0] == 255
+                    && pixel[2] == 0
+            })
+            .count();
+        assert!(on_line >= SIZE.height as usize, "{on_line} line pixels");
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn recolored_accumulations_show_once_restarted() {
+        use shader::Program as _;
+
+        const SIZE: Size<u32> = Size::new(32, 32);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let viewport = Rectangle {
+            x: 0,
+            y: 0,
+            width: SIZE.width,
+            height: SIZE.height,
+        };
+        let mut program = FragmentShaderProgram::new(Controls::default());
+        let mut draw = |program: &FragmentShaderProgram| {
+            let primitive = program.draw(
+                &ProgramState::default(),
+                Cursor::Unavailable,
+                viewport.into(),
+            );
+            renderer.draw_primitive(&primitive, viewport).unwrap();
+            renderer.read_pixels().unwrap()
+        };
+
+        for frame_index in 0..4 {
+            program.frame_index = Some(frame_index);
+            draw(&program);
+        }
+        let settled = draw(&program);
+
+        program.palette = Palette::new(vec![
+            ColorStop {
+                position: 0.0,
+                color: [255, 0, 0],
+            },
+            ColorStop {
+                position: 1.0,
+                color: [255; 3],
+            },
+        ])
+        .unwrap();
+        // Settled samples are only blitted, the application restarts them for a new image.
+        assert_eq!(draw(&program), settled);
+        program.frame_index = Some(0);
+        assert_ne!(draw(&program), settled);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn resizing_overwrites_the_whole_view_and_nothing_else() {
+        const SIZE: Size<u32> = Size::new(64, 48);
+        let mut renderer = HeadlessRenderer::new(SIZE).unwrap();
+        let primitive = FragmentShaderPrimitive::new(0, Controls::default());
+        // The palette is gray, so red is left over from the fill.
+        let fill_red = |renderer: &HeadlessRenderer| {
+            let view = renderer.texture.create_view(&Default::default());
+            let mut encoder = renderer.device.create_command_encoder(&Default::default());
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            renderer.queue.submit([encoder.finish()]);
+        };
+        // Like the view above the controls, growing with the window.
+        for height in [16, 32, 24] {
+            fill_red(&renderer);
+            let viewport = Rectangle {
+                x: 0,
+                y: 0,
+                width: SIZE.width,
+                height,
+            };
+            renderer.draw_primitive(&primitive, viewport).unwrap();
+
+            let pixels = renderer.read_pixels().unwrap();
+            let (view, below) = pixels.split_at((SIZE.width * height * 4) as usize);
+            let red = |pixel: &[u8]| pixel == [255, 0, 0, 255];
+            assert!(!view.chunks(4).any(red), "view {height} tall");
+            assert!(below.chunks(4).all(red), "view {height} tall");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn inspected_pixels_are_read_back() {
+        use shader::Program as _;
+
+        let mut renderer = HeadlessRenderer::new(Size::new(64, 48)).unwrap();
+        let controls = Controls {
+            center: DVec2::new(-0.2, 0.0),
+            zoom: 1.0,
+            max_iter: 100,
+            ..Controls::default()
+        };
+        let bounds = Rectangle::new(iced::Point::ORIGIN, Size::new(64.0, 48.0));
+        let mut program = FragmentShaderProgram::new(controls);
+        let mut inspect = |pos: Vec2| {
+            program.inspect(pos, bounds);
+            let primitive = program.draw(&ProgramState::default(), Cursor::Unavailable, bounds);
+            // Copied, then mapped, then read.
+            for _ in 0..3 {
+                renderer.draw_primitive(&primitive, bounds.snap()).unwrap();
+            }
+            program.inspected().unwrap()
+        };
+
+        // The view center is in the main cardioid.
+        let center = inspect(Vec2::new(32.0, 24.0));
+        assert_eq!(center.iterations, None);
+        assert!(center.magnitude <= 2.0);
+
+        let pos = Vec2::new(0.5, 0.5);
+        let corner = inspect(pos);
+        let point = controls.fractal_point(pos, bounds);
+        assert_eq!(corner.point, point);
+        let orbit = controls.orbit(point, 100);
+        let escaped = orbit.len() - 1;
+        let iterations = corner.iterations.unwrap();
+        assert!(
+            (escaped as f32 - 1.0..=escaped as f32 + 1.0).contains(&iterations),
+            "{iterations} iterations, escaped after {escaped}"
+        );
+        let magnitude = orbit.last().unwrap().length() as f32;
+        assert!((corner.magnitude - magnitude).abs() < magnitude * 1e-3);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn failed_readbacks_end_the_inspection() {
+        let renderer = HeadlessRenderer::new(Size::new(8, 8)).unwrap();
+        let inspection = Arc::new(Inspection {
+            pos: Vec2::ZERO,
+            point: DVec2::ZERO,
+            max_iter: 100,
+            escape_radius: 2.0,
+            power: 2.0,
+            result: Mutex::new(None),
+        });
+        let mut readback = Readback::new(&renderer.device, inspection.clone(), 0);
+        // Mapping a destroyed buffer fails, which is also a validation error.
+        readback.buffer.destroy();
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+        assert!(!readback.update(&renderer.device));
+        assert!(pollster::block_on(renderer.device.pop_error_scope()).is_some());
+        renderer.device.poll(wgpu::Maintain::Wait);
+        assert!(readback.update(&renderer.device));
+        assert!(matches!(*inspection.result.lock().unwrap(), Some(Err(_))));
+    }
+}
@@ -0,0 +1,38 @@
+//! Number parsing shared by view strings and the command line arguments of the viewer.
+
+use crate::{ZOOM_MAX, ZOOM_MIN};
+
+pub fn parse_finite(arg: &str) -> Result<f64, String> {
+    let value: f64 = arg.parse().map_err(|err| format!("{err}"))?;
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(String::from("must be a finite number"))
+    }
+}
+
+pub fn parse_in_range<T>(arg: &str, range: std::ops::RangeInclusive<T>) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+    T::Err: std::fmt::Display,
+{
+    let value: T = arg.parse().map_err(|err| format!("{err}"))?;
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!(
+            "must be between {} and {}",
+            range.start(),
+            range.end()
+        ))
+    }
+}
+
+pub fn parse_zoom(arg: &str) -> Result<f32, String> {
+    let zoom: f32 = arg.parse().map_err(|err| format!("{err}"))?;
+    if (ZOOM_MIN..=ZOOM_MAX).contains(&zoom) {
+        Ok(zoom)
+    } else {
+        Err(format!("must be between {ZOOM_MIN} and {ZOOM_MAX}"))
+    }
+}
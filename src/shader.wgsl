@@ -1,44 +1,477 @@
+// Iteration stage: computes the escape data of every sample and stores it for color.wgsl.
+
 struct Uniforms {
+	// Widget size in physical pixels.
 	resolution: vec2f,
-	center: vec2f,
-	scale: f32,
+	// Double-float center, the exact value is center_hi + center_lo.
+	center_hi: vec2f,
+	center_lo: vec2f,
+	seed: vec2f,
+	// Sub-pixel sample offset for progressive supersampling.
+	jitter: vec2f,
+	// Number of samples per axis, the widget size in pixels times the supersampling factor.
+	grid_size: vec2u,
+	// Orbit trap shape, see trap_distance().
+	trap_point: vec2f,
+	// Height of the view on the complex plane, the width follows from the aspect ratio.
+	extent: f32,
 	max_iter: u32,
+	julia: u32,
+	formula: u32,
+	deep: u32,
+	power: f32,
+	escape_radius: f32,
+	perturbation: u32,
+	orbit_len: u32,
+	trap: u32,
+	trap_radius: f32,
+	// Stop iterating interior points once their orbit is found to be periodic.
+	period_detection: u32,
+	// Sample the dispatch starts at when the iteration is split into tiles.
+	tile_offset: vec2u,
+	// Counterclockwise view rotation about its center, in radians.
+	rotation: f32,
+	// Stripes per turn of stripe average coloring, 0 skips it.
+	stripe_frequency: f32,
+	// Track the derivative for the distance estimate, 0 leaves it at 1.
+	distance_estimate: u32,
+}
+
+// Must match the declaration in color.wgsl.
+struct Sample {
+	z: vec2f,
+	iter: u32,
+	// Estimated distance to the set boundary, in pixels.
+	distance: f32,
+	// Closest approach of the orbit to the trap.
+	trap: f32,
+	// Cycle length of a periodic interior orbit, 0 if none was detected.
+	period: u32,
+	// Stripe average of an escaped orbit, see stripe_average().
+	stripe: f32,
 }
 
+const FORMULA_BURNING_SHIP = 1u;
+const FORMULA_TRICORN = 2u;
+
+const TRAP_POINT = 1u;
+const TRAP_LINE = 2u;
+const TRAP_CIRCLE = 3u;
+
+// Orbit points closer than this many pixels are considered the same by cycle detection.
+const PERIOD_TOLERANCE = .01;
+
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
 
-struct VertexIn {
-	@builtin(vertex_index) vertex_index: u32,
+// Reference orbit of the view center, computed on the CPU in double precision.
+@group(1) @binding(0) var<storage, read> orbit: array<vec2f>;
+
+@group(2) @binding(0) var<storage, read_write> samples: array<Sample>;
+
+// Number of samples escaping at each iteration, see histogram_main.
+@group(3) @binding(0) var<storage, read_write> histogram: array<atomic<u32>>;
+// Fraction of escaped samples that escaped at or before each iteration, see cdf_main.
+@group(3) @binding(1) var<storage, read_write> cdf: array<f32>;
+
+// Double-float ("df64") arithmetic: a number is stored as vec2f(hi, lo) with |lo| <= ulp(hi) / 2,
+// giving roughly 48 bits of mantissa out of two f32s. See Dekker (1971) and Thall (2006).
+// This relies on the compiler not reassociating or fusing the error terms away.
+
+fn df_two_sum(a: f32, b: f32) -> vec2f {
+	let s = a + b;
+	let v = s - a;
+	let e = (a - (s - v)) + (b - v);
+	return vec2f(s, e);
+}
+
+fn df_quick_two_sum(a: f32, b: f32) -> vec2f {
+	let s = a + b;
+	let e = b - (s - a);
+	return vec2f(s, e);
+}
+
+fn df_split(a: f32) -> vec2f {
+	let t = 4097. * a;
+	let hi = t - (t - a);
+	return vec2f(hi, a - hi);
+}
+
+fn df_two_prod(a: f32, b: f32) -> vec2f {
+	let p = a * b;
+	let a_s = df_split(a);
+	let b_s = df_split(b);
+	let e = ((a_s.x * b_s.x - p) + a_s.x * b_s.y + a_s.y * b_s.x) + a_s.y * b_s.y;
+	return vec2f(p, e);
+}
+
+fn df_add(a: vec2f, b: vec2f) -> vec2f {
+	let s = df_two_sum(a.x, b.x);
+	return df_quick_two_sum(s.x, s.y + a.y + b.y);
+}
+
+fn df_mul(a: vec2f, b: vec2f) -> vec2f {
+	let p = df_two_prod(a.x, b.x);
+	return df_quick_two_sum(p.x, p.y + a.x * b.y + a.y * b.x);
+}
+
+fn df_abs(a: vec2f) -> vec2f {
+	return select(a, -a, a.x < 0.);
+}
+
+fn df_cmul(ax: vec2f, ay: vec2f, bx: vec2f, by: vec2f) -> array<vec2f, 2> {
+	let x = df_add(df_mul(ax, bx), -df_mul(ay, by));
+	let y = df_add(df_mul(ax, by), df_mul(ay, bx));
+	return array(x, y);
+}
+
+fn cmul(a: vec2f, b: vec2f) -> vec2f {
+	return vec2f(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// Size of a physical pixel on the complex plane.
+fn pixel_size() -> f32 {
+	return uniforms.extent / uniforms.resolution.y;
+}
+
+fn is_integer_power() -> bool {
+	return fract(uniforms.power) == 0.;
+}
+
+// z^power, using repeated multiplication for integer powers and polar form otherwise.
+fn cpow(z: vec2f, power: f32) -> vec2f {
+	if (fract(power) == 0.) {
+		var zn = vec2f(1., 0.);
+		for (var k = 0u; k < u32(power); k = k + 1) {
+			zn = cmul(zn, z);
+		}
+		return zn;
+	}
+
+	let r = length(z);
+	if (r == 0.) {
+		return z;
+	}
+	let theta = atan2(z.y, z.x) * power;
+	return pow(r, power) * vec2f(cos(theta), sin(theta));
+}
+
+// Next value of the derivative dz/dc for the distance estimate, given the current z.
+fn derivative(z: vec2f, dz: vec2f) -> vec2f {
+	if (uniforms.distance_estimate == 0) {
+		return dz;
+	}
+	var next = uniforms.power * cmul(cpow(z, uniforms.power - 1.), dz);
+	if (uniforms.julia == 0) {
+		next.x += 1.;
+	}
+	return next;
+}
+
+// Result of iterating a single point: the iteration it escaped at (max_iter if it never did),
+// the final value of z, its derivative, the orbit's closest approach to the trap, the
+// period of the orbit if it was found to be periodic and its stripe sum, see stripe_add().
+struct Escape {
+	iter: u32,
+	z: vec2f,
+	dz: vec2f,
+	trap: f32,
+	period: u32,
+	stripe: vec2f,
+}
+
+// Brent's cycle detection: the orbit is compared against a saved point, which is replaced
+// after windows of doubling length.
+struct Cycle {
+	check: vec2f,
+	check_iter: u32,
+	window: u32,
+}
+
+fn cycle_start(z: vec2f) -> Cycle {
+	return Cycle(z, 0u, 1u);
+}
+
+// Returns the period once z, the iter-th point of the orbit, comes back to the saved point,
+// or 0 while it hasn't.
+fn cycle_period(cycle: ptr<function, Cycle>, z: vec2f, iter: u32) -> u32 {
+	let d = z - (*cycle).check;
+	let tolerance = pixel_size() * PERIOD_TOLERANCE;
+	if (dot(d, d) < tolerance * tolerance) {
+		return iter - (*cycle).check_iter;
+	}
+
+	if (iter - (*cycle).check_iter >= (*cycle).window) {
+		(*cycle).check = z;
+		(*cycle).check_iter = iter;
+		(*cycle).window *= 2u;
+	}
+	return 0u;
+}
+
+// Distance from z to the orbit trap: the trap point, the horizontal line through it, or the
+// circle around it.
+fn trap_distance(z: vec2f) -> f32 {
+	switch uniforms.trap {
+		case TRAP_POINT: {
+			return distance(z, uniforms.trap_point);
+		}
+		case TRAP_LINE: {
+			return abs(z.y - uniforms.trap_point.y);
+		}
+		case TRAP_CIRCLE: {
+			return abs(distance(z, uniforms.trap_point) - uniforms.trap_radius);
+		}
+		default: {
+			return 0.;
+		}
+	}
+}
+
+// Adds the stripe term of an orbit point to the sum in x, keeping the term in y so that the
+// average can be interpolated between the last two iterations (Härkönen, 2007).
+fn stripe_add(stripe: vec2f, z: vec2f) -> vec2f {
+	if (uniforms.stripe_frequency == 0.) {
+		return stripe;
+	}
+	let term = .5 + .5 * sin(uniforms.stripe_frequency * atan2(z.y, z.x));
+	return vec2f(stripe.x + term, term);
 }
 
-struct VertexOut {
-	@builtin(position) position: vec4f,
+// Average stripe term of an escaped orbit of iter + 1 points, blended with the average without
+// the last point by the fractional escape count so that it doesn't band. It only depends on the
+// points up to the escape, not on max_iter.
+fn stripe_average(escape: Escape) -> f32 {
+	if (uniforms.stripe_frequency == 0. || escape.iter >= uniforms.max_iter) {
+		return 0.;
+	}
+
+	let sum = escape.stripe.x;
+	let average = sum / f32(escape.iter + 1u);
+	if (escape.iter == 0u) {
+		return average;
+	}
+	let previous = (sum - escape.stripe.y) / f32(escape.iter);
+	let log_ratio = log(length(escape.z)) / log(uniforms.escape_radius);
+	let fraction = saturate(1. - log(log_ratio) / log(uniforms.power));
+	return mix(previous, average, fraction);
 }
 
-@vertex
-fn vs_main(in: VertexIn) -> VertexOut {
-	let uv = vec2f(vec2u((in.vertex_index << 1) & 2, in.vertex_index & 2));
-	let position = vec4f(uv * 2. - 1., 0., 1.);
-	return VertexOut(position);
+fn iterate(offset: vec2f) -> Escape {
+	var p = uniforms.center_hi + offset;
+	var p0 = p;
+	if (uniforms.julia != 0) {
+		p0 = uniforms.seed;
+	}
+
+	let bailout = uniforms.escape_radius * uniforms.escape_radius;
+	var dz = vec2f(1., 0.);
+	var trap = trap_distance(p);
+	var stripe = stripe_add(vec2f(0.), p);
+	var cycle = cycle_start(p);
+	var i: u32 = 0;
+	for (; i < uniforms.max_iter; i = i + 1) {
+		if (dot(p, p) > bailout) {
+			break;
+		}
+
+		switch uniforms.formula {
+			case FORMULA_BURNING_SHIP: {
+				p = abs(p);
+			}
+			case FORMULA_TRICORN: {
+				p.y = -p.y;
+			}
+			default: {}
+		}
+
+		dz = derivative(p, dz);
+		p = cpow(p, uniforms.power) + p0;
+		trap = min(trap, trap_distance(p));
+		stripe = stripe_add(stripe, p);
+
+		if (uniforms.period_detection != 0) {
+			let period = cycle_period(&cycle, p, i + 1);
+			if (period != 0) {
+				return Escape(uniforms.max_iter, p, dz, trap, period, stripe);
+			}
+		}
+	}
+
+	return Escape(i, p, dz, trap, 0u, stripe);
 }
 
-@fragment
-fn fs_main(in: VertexOut) -> @location(0) vec4f {
-	let p0 = uniforms.center + (in.position.xy - uniforms.resolution * .5) * uniforms.scale;
-	var p = p0;
+// Same as iterate(), but with the real and imaginary parts kept as double-floats.
+fn iterate_deep(offset: vec2f) -> Escape {
+	var x = df_add(vec2f(uniforms.center_hi.x, uniforms.center_lo.x), vec2f(offset.x, 0.));
+	var y = df_add(vec2f(uniforms.center_hi.y, uniforms.center_lo.y), vec2f(offset.y, 0.));
+	var x0 = x;
+	var y0 = y;
+	if (uniforms.julia != 0) {
+		x0 = vec2f(uniforms.seed.x, 0.);
+		y0 = vec2f(uniforms.seed.y, 0.);
+	}
+
+	let bailout = uniforms.escape_radius * uniforms.escape_radius;
+	// The derivative only needs relative precision, single floats suffice.
+	var dz = vec2f(1., 0.);
+	var trap = trap_distance(vec2f(x.x, y.x));
+	var stripe = stripe_add(vec2f(0.), vec2f(x.x, y.x));
+	var cycle = cycle_start(vec2f(x.x, y.x));
 	var i: u32 = 0;
 	for (; i < uniforms.max_iter; i = i + 1) {
-		let d = p * p;
-		if (d.x + d.y > 4.) {
+		if (x.x * x.x + y.x * y.x > bailout) {
 			break;
 		}
 
-		p = vec2f(d.x - d.y + p0.x, 2. * p.x * p.y + p0.y);
+		switch uniforms.formula {
+			case FORMULA_BURNING_SHIP: {
+				x = df_abs(x);
+				y = df_abs(y);
+			}
+			case FORMULA_TRICORN: {
+				y = -y;
+			}
+			default: {}
+		}
+
+		dz = derivative(vec2f(x.x, y.x), dz);
+
+		var xn = x;
+		var yn = y;
+		if (is_integer_power()) {
+			for (var k = 1u; k < u32(uniforms.power); k = k + 1) {
+				let zn = df_cmul(xn, yn, x, y);
+				xn = zn[0];
+				yn = zn[1];
+			}
+		} else {
+			// No double-float pow/atan2, fractional powers lose precision at deep zoom levels.
+			let zn = cpow(vec2f(x.x, y.x), uniforms.power);
+			xn = vec2f(zn.x, 0.);
+			yn = vec2f(zn.y, 0.);
+		}
+		x = df_add(xn, x0);
+		y = df_add(yn, y0);
+		let z = vec2f(x.x, y.x);
+		trap = min(trap, trap_distance(z));
+		stripe = stripe_add(stripe, z);
+
+		if (uniforms.period_detection != 0) {
+			let period = cycle_period(&cycle, z, i + 1);
+			if (period != 0) {
+				return Escape(uniforms.max_iter, z, dz, trap, period, stripe);
+			}
+		}
 	}
 
-	if (i >= uniforms.max_iter) {
-		return vec4f(vec3f(0.), 1.);
+	return Escape(i, vec2f(x.x, y.x), dz, trap, 0u, stripe);
+}
+
+// Iterates only the difference `dz` of the pixel's orbit from the reference orbit:
+// dz' = 2 * Z * dz + dz^2 + dc. Only valid for z^2 + c.
+fn iterate_perturbed(dc: vec2f) -> Escape {
+	let bailout = uniforms.escape_radius * uniforms.escape_radius;
+	var dz = vec2f(0.);
+	var z = vec2f(0.);
+	var der = vec2f(0.);
+	// These start at z_1 like the other iterations: z_0 = 0 has no angle, and would put every
+	// orbit on a trap through the origin.
+	var trap = 0.;
+	var stripe = vec2f(0.);
+	var cycle: Cycle;
+	var m: u32 = 0;
+	var i: u32 = 0;
+	for (; i < uniforms.max_iter; i = i + 1) {
+		der = derivative(z, der);
+		dz = 2. * cmul(orbit[m], dz) + cmul(dz, dz) + dc;
+		m = m + 1;
+		z = orbit[m] + dz;
+		if (i == 0u) {
+			trap = trap_distance(z);
+			cycle = cycle_start(z);
+		} else {
+			trap = min(trap, trap_distance(z));
+		}
+		stripe = stripe_add(stripe, z);
+		if (dot(z, z) > bailout) {
+			break;
+		}
+
+		// z is z_(i+1), numbered from z_1 like in iterate().
+		if (uniforms.period_detection != 0 && i != 0u) {
+			let period = cycle_period(&cycle, z, i);
+			if (period != 0) {
+				return Escape(uniforms.max_iter, z, der, trap, period, stripe);
+			}
+		}
+
+		// Rebase onto the start of the reference orbit when the pixel's orbit gets closer to
+		// zero than its difference from the reference, or the reference has run out.
+		// This avoids precision glitches (Zhuoran, 2021).
+		if (dot(z, z) < dot(dz, dz) || m + 1 >= uniforms.orbit_len) {
+			dz = z;
+			m = 0u;
+		}
+	}
+
+	return Escape(i, z, der, trap, 0u, stripe);
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+	let cell = id.xy + uniforms.tile_offset;
+	// The grid size is generally not a multiple of the workgroup size.
+	if (any(cell >= uniforms.grid_size)) {
+		return;
+	}
+
+	let uv = (vec2f(cell) + .5) / vec2f(uniforms.grid_size);
+	let pixel = uv * uniforms.resolution + uniforms.jitter;
+	let rotation = mat2x2f(cos(uniforms.rotation), sin(uniforms.rotation), -sin(uniforms.rotation), cos(uniforms.rotation));
+	// Offset from the center is small and fits a single f32 even at deep zoom levels.
+	let offset = rotation * (pixel - uniforms.resolution * .5) * pixel_size();
+
+	var escape: Escape;
+	if (uniforms.perturbation != 0) {
+		escape = iterate_perturbed(offset);
+	} else if (uniforms.deep != 0) {
+		escape = iterate_deep(offset);
 	} else {
-		return vec4f(vec3f(f32(i) / f32(uniforms.max_iter)), 1.);
+		escape = iterate(offset);
+	}
+
+	let r = length(escape.z);
+	let distance = .5 * r * log(r) / length(escape.dz) / pixel_size();
+	samples[cell.y * uniforms.grid_size.x + cell.x] =
+		Sample(escape.z, escape.iter, distance, escape.trap, escape.period, stripe_average(escape));
+}
+
+// Counts escaped samples by iteration. Interior samples are left out, they would otherwise
+// dominate the distribution.
+@compute @workgroup_size(8, 8)
+fn histogram_main(@builtin(global_invocation_id) id: vec3u) {
+	if (any(id.xy >= uniforms.grid_size)) {
+		return;
+	}
+
+	let iter = samples[id.y * uniforms.grid_size.x + id.x].iter;
+	if (iter < uniforms.max_iter) {
+		atomicAdd(&histogram[iter], 1u);
+	}
+}
+
+// Turns the histogram into a cumulative distribution, in a single invocation.
+@compute @workgroup_size(1)
+fn cdf_main() {
+	var total = 0u;
+	for (var i = 0u; i < uniforms.max_iter; i = i + 1) {
+		total += atomicLoad(&histogram[i]);
+	}
+
+	let scale = 1. / f32(max(total, 1u));
+	var sum = 0u;
+	for (var i = 0u; i < uniforms.max_iter; i = i + 1) {
+		sum += atomicLoad(&histogram[i]);
+		cdf[i] = f32(sum) * scale;
 	}
 }
@@ -0,0 +1,8 @@
+// Appended to blit.wgsl and trace.wgsl.
+
+// Targets without an sRGB format store colors as they are, so linear colors have to be encoded
+// like the hardware does for sRGB ones.
+fn encode_srgb(linear: vec3f) -> vec3f {
+	let c = saturate(linear);
+	return select(1.055 * pow(c, vec3f(1. / 2.4)) - .055, c * 12.92, c <= vec3f(.0031308));
+}
@@ -0,0 +1,20 @@
+// Orbit trace: the orbit of a point, drawn as line strips over the view.
+// `encode_srgb` is appended from srgb.wgsl.
+
+// Linear, like all colors until they are written to the target.
+const TRACE_COLOR = vec4f(1., .4, 0., 1.);
+
+@vertex
+fn vs_main(@location(0) position: vec2f) -> @builtin(position) vec4f {
+	return vec4f(position, 0., 1.);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+	return TRACE_COLOR;
+}
+
+@fragment
+fn fs_encode_srgb() -> @location(0) vec4f {
+	return vec4f(encode_srgb(TRACE_COLOR.rgb), TRACE_COLOR.a);
+}